@@ -11,11 +11,13 @@ use std::{
 
 pub use deku;
 use deku::{
-	DekuError, DekuRead, DekuReader, DekuWrite,
+	DekuContainerWrite, DekuError, DekuRead, DekuReader, DekuWrite,
 	reader::{Reader, ReaderRet},
 	writer::Writer,
 };
 
+pub mod overlay;
+
 pub use entry::{Entry, EntryError};
 pub mod entry {
 	use super::*;
@@ -274,6 +276,69 @@ impl Package {
 		self.handle.entries()
 	}
 
+	/// Packs every file directly inside of the directory at `path` into a package
+	/// and writes it to `writer`. Each directory inside of `path` is packed into a
+	/// nested package entry. Entries are sorted by name.
+	///
+	/// Entry names are limited to [`Entry::NAME_LENGTH`] ASCII characters and
+	/// packages are limited to 4 GiB, an error is returned otherwise.
+	pub fn write_dir<P: AsRef<Path>, W: Write>(
+		path: P,
+		writer: &mut W,
+	) -> Result<(), EntryError<'static>> {
+		let bytes = Self::pack_dir(path.as_ref())?;
+		writer.write_all(&bytes)?;
+
+		Ok(())
+	}
+
+	fn pack_dir(path: &Path) -> Result<Vec<u8>, EntryError<'static>> {
+		let mut assets = Vec::new();
+		for dir_entry in path.read_dir()? {
+			let dir_entry = dir_entry?;
+			let entry_path = dir_entry.path();
+
+			let file_name = dir_entry.file_name();
+			let file_name = file_name.to_str().ok_or(EntryError::InvalidUtf8(
+				file_name.display().to_string().into(),
+			))?;
+			if file_name.len() > Entry::NAME_LENGTH || !file_name.is_ascii() {
+				return Err(EntryError::InvalidPath {
+					expected: "entry name of at most 16 ASCII characters".into(),
+					found: entry_path.into(),
+				});
+			}
+
+			let bytes = if entry_path.is_dir() {
+				Self::pack_dir(&entry_path)?
+			} else {
+				std::fs::read(&entry_path)?
+			};
+			assets.push((Entry::new(file_name), bytes));
+		}
+		assets.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+		let too_large = || io::Error::other("package is larger than 4 GiB");
+		let table_byte_length =
+			u32::try_from(assets.len() * Entry::RAW_SIZE).map_err(|_| too_large())?;
+		let mut table = Vec::with_capacity(table_byte_length as _);
+		let mut data = Vec::new();
+		for (mut entry, bytes) in assets {
+			entry.byte_offset = u32::try_from(data.len()).map_err(|_| too_large())?;
+			entry.byte_length = u32::try_from(bytes.len()).map_err(|_| too_large())?;
+			table.extend(entry.to_bytes()?);
+			data.extend(bytes);
+		}
+
+		let mut package = Vec::with_capacity(8 + table.len() + data.len());
+		package.extend(Self::MAGIC);
+		package.extend(table_byte_length.to_le_bytes());
+		package.extend(table);
+		package.extend(data);
+
+		Ok(package)
+	}
+
 	/// A naive implementation of traversing entries within a [`Package`] with basic
 	/// loose file support but has a few points to consider that may result in
 	/// unintended behavior:
@@ -0,0 +1,1202 @@
+//! Planning a virtual [`Package`] that overlays assets from mods on top of an
+//! original package, without touching the filesystem.
+//!
+//! A [`Plan`] is built from the entries of the original package and the
+//! [`Override`]s provided by mods. It holds the virtual header and entry table
+//! the game reads in place of the original ones, and resolves any read range of
+//! the virtual package into [`Segment`]s of where those bytes actually live.
+//!
+//! Overrides can target assets of nested packages, such as
+//! `actors.rpk/opponent03`, in which case the table of the nested package is
+//! rebuilt virtually as well and only the changed assets have to be provided.
+
+use std::{
+	borrow::Cow,
+	collections::{BTreeMap, BTreeSet, HashMap},
+	fs::File,
+	io::{self, Read, Seek, SeekFrom, Write},
+	path::{Path, PathBuf},
+};
+
+use deku::{DekuContainerWrite, DekuReader, reader::Reader};
+use serde::{Deserialize, Serialize};
+
+use crate::{Entry, EntryError, Package, read_table};
+
+/// An asset provided by a mod, replacing the entry of the same name in the
+/// original package or being added to it as a new entry.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Override {
+	pub name: String,
+	pub kind: OverrideKind,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum OverrideKind {
+	/// The whole asset is provided by the mod.
+	Asset { byte_length: u32 },
+	/// The asset is a nested package overlaid according to its own plan.
+	Package(Plan),
+}
+
+impl Override {
+	#[inline]
+	pub fn new(name: &str, byte_length: u32) -> Self {
+		Self {
+			name: name.to_owned(),
+			kind: OverrideKind::Asset { byte_length },
+		}
+	}
+
+	#[inline]
+	pub fn package(name: &str, plan: Plan) -> Self {
+		Self {
+			name: name.to_owned(),
+			kind: OverrideKind::Package(plan),
+		}
+	}
+}
+
+/// Removals and renames of the original entries of a package, declared by mods.
+///
+/// Paths are relative to the package, such as `actors.rpk/opponent03` for an
+/// entry of a nested package. Entries are renamed within the package they are
+/// in, so new names are plain entry names.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Edits {
+	/// Paths of original entries to remove.
+	#[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+	pub remove: BTreeSet<String>,
+	/// Paths of original entries to rename, mapped to their new name.
+	#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+	pub rename: BTreeMap<String, String>,
+}
+
+impl Edits {
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.remove.is_empty() && self.rename.is_empty()
+	}
+
+	/// Adds the edits of `other`, with its renames taking precedence.
+	#[inline]
+	pub fn extend(&mut self, other: Edits) {
+		self.remove.extend(other.remove);
+		self.rename.extend(other.rename);
+	}
+
+	/// Splits the edits of nested packages from the edits of this package, keyed
+	/// by the original name of the nested package.
+	fn split_nested(&self) -> (Edits, BTreeMap<&str, Edits>) {
+		let mut edits = Edits::default();
+		let mut nested: BTreeMap<&str, Edits> = BTreeMap::new();
+		for path in &self.remove {
+			match path.split_once('/') {
+				Some((package, path)) => {
+					nested
+						.entry(package)
+						.or_default()
+						.remove
+						.insert(path.to_owned());
+				}
+				None => {
+					edits.remove.insert(path.clone());
+				}
+			}
+		}
+		for (path, name) in &self.rename {
+			match path.split_once('/') {
+				Some((package, path)) => {
+					nested
+						.entry(package)
+						.or_default()
+						.rename
+						.insert(path.to_owned(), name.clone());
+				}
+				None => {
+					edits.rename.insert(path.clone(), name.clone());
+				}
+			}
+		}
+
+		(edits, nested)
+	}
+}
+
+/// Where the bytes of a [`PlannedEntry`] are read from.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Source {
+	/// The entry is read from the original package, where its asset starts at
+	/// `byte_offset` from the start of the package.
+	Original { byte_offset: u32 },
+	/// The entry is replaced by an [`Override`] of the same name.
+	Modified,
+	/// The entry does not exist in the original package and is read from an
+	/// [`Override`] of the same name.
+	Foreign,
+	/// The entry is a nested package overlaid according to `plan`, where the
+	/// original nested package starts at `byte_offset` from the start of the
+	/// package. The offset is 0 if the nested package is foreign.
+	Nested { byte_offset: u32, plan: Box<Plan> },
+}
+
+/// An entry of the virtual package along with where its bytes are read from.
+#[derive(Debug, PartialEq, Eq)]
+pub struct PlannedEntry {
+	/// The entry as written to the virtual table.
+	pub entry: Entry,
+	pub source: Source,
+}
+
+/// Where the bytes of a [`Segment`] are read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Region<'a> {
+	/// Bytes of the virtual header of a package, see [`Plan::header`].
+	Header(&'a [u8]),
+	/// Bytes of the original package, starting at `offset` from the start of the
+	/// file.
+	Original { offset: u32 },
+	/// Bytes of the [`Override`] at `path`, starting at `offset` from the start
+	/// of the override. The path of an override within a nested package is
+	/// prefixed by the name of the nested package, such as
+	/// `actors.rpk/opponent03`.
+	Override { path: Cow<'a, str>, offset: u32 },
+}
+
+/// A contiguous part of a read from the virtual package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment<'a> {
+	pub region: Region<'a>,
+	pub byte_length: u32,
+}
+
+/// The layout of a virtual package, see the [module level documentation](self).
+#[derive(Debug, PartialEq, Eq)]
+pub struct Plan {
+	original_end_of_table: u32,
+	header: Vec<u8>,
+	/// Sorted by name, as written to the virtual table.
+	entries: Vec<PlannedEntry>,
+	/// Indices into `entries`, sorted by the offset of their assets.
+	layout: Vec<usize>,
+	data_byte_length: u32,
+}
+
+impl Plan {
+	/// Returns a result to the plan of overlaying `overrides` on top of the
+	/// package with `original_entries`.
+	///
+	/// Assets keep the order of the original package, with overrides of existing
+	/// entries taking their place and new entries being appended in order of
+	/// name. The virtual table is sorted by name. If several overrides share a
+	/// name, the last one is used.
+	///
+	/// `edits` of this package are applied to the original entries first, so
+	/// overrides replace entries by their new name. An entry renamed to the name
+	/// of another original entry replaces it. Edits of entries that do not exist
+	/// are ignored.
+	///
+	/// # Errors
+	///
+	/// Errors may be returned according to:
+	///
+	/// - An override name or new name longer than [`Entry::NAME_LENGTH`] or not
+	///   ASCII
+	/// - Several entries being renamed to the same name
+	/// - The virtual package being larger than 4 GiB
+	/// - [`DekuContainerWrite::to_bytes`]
+	pub fn new<I: IntoIterator<Item = Override>>(
+		mut original_entries: Vec<Entry>,
+		overrides: I,
+		edits: &Edits,
+	) -> Result<Self, EntryError<'static>> {
+		let too_large = || io::Error::other("package is larger than 4 GiB");
+
+		let mut overrides: BTreeMap<String, OverrideKind> =
+			overrides.into_iter().map(|o| (o.name, o.kind)).collect();
+		if let Some(name) = overrides
+			.keys()
+			.chain(edits.rename.values())
+			.find(|name| name.len() > Entry::NAME_LENGTH || !name.is_ascii() || name.contains('/'))
+		{
+			return Err(EntryError::InvalidPath {
+				expected: "entry name of at most 16 ASCII characters".into(),
+				found: Path::new(name).to_owned().into(),
+			});
+		}
+
+		let original_table_byte_length =
+			u32::try_from(original_entries.len() * Entry::RAW_SIZE).map_err(|_| too_large())?;
+		let original_end_of_table = 8 + original_table_byte_length;
+
+		// The game expects assets in the same order as the original package.
+		original_entries.sort_by_key(|entry| entry.byte_offset);
+
+		let mut entries = Vec::with_capacity(original_entries.len() + overrides.len());
+		let mut byte_offset = 0u32;
+		let mut push = |name: Box<str>, byte_length: u32, source: Source| {
+			entries.push(PlannedEntry {
+				entry: Entry {
+					name,
+					byte_offset,
+					byte_length,
+				},
+				source,
+			});
+			byte_offset = byte_offset.checked_add(byte_length).ok_or_else(too_large)?;
+			Ok::<_, io::Error>(())
+		};
+		let new_names: BTreeSet<&str> = edits.rename.values().map(String::as_str).collect();
+		for mut original in original_entries {
+			if edits.remove.contains(&*original.name) {
+				continue;
+			}
+			match edits.rename.get(&*original.name) {
+				Some(name) => original.name = name.as_str().into(),
+				// replaced by an entry renamed to its name
+				None if new_names.contains(&*original.name) => continue,
+				None => {}
+			}
+
+			let original_offset = original_end_of_table
+				.checked_add(original.byte_offset)
+				.ok_or_else(too_large)?;
+			let (byte_length, source) = match overrides.remove(&*original.name) {
+				Some(OverrideKind::Asset { byte_length }) => (byte_length, Source::Modified),
+				Some(OverrideKind::Package(plan)) => (
+					plan.byte_length(),
+					Source::Nested {
+						byte_offset: original_offset,
+						plan: plan.into(),
+					},
+				),
+				None => (
+					original.byte_length,
+					Source::Original {
+						byte_offset: original_offset,
+					},
+				),
+			};
+			push(original.name, byte_length, source)?;
+		}
+		for (name, kind) in overrides {
+			let (byte_length, source) = match kind {
+				OverrideKind::Asset { byte_length } => (byte_length, Source::Foreign),
+				OverrideKind::Package(plan) => (
+					plan.byte_length(),
+					Source::Nested {
+						byte_offset: 0,
+						plan: plan.into(),
+					},
+				),
+			};
+			push(name.into(), byte_length, source)?;
+		}
+
+		let mut layout: Vec<usize> = (0..entries.len()).collect();
+		// The game expects the table to be sorted by name.
+		entries.sort_by(|a, b| a.entry.cmp(&b.entry));
+		if let Some(pair) = entries
+			.windows(2)
+			.find(|pair| pair[0].entry == pair[1].entry)
+		{
+			return Err(EntryError::InvalidPath {
+				expected: "entries renamed to unique names".into(),
+				found: Path::new(&*pair[0].entry.name).to_owned().into(),
+			});
+		}
+		layout.sort_by_key(|&i| (entries[i].entry.byte_offset, entries[i].entry.byte_length));
+
+		let table_byte_length =
+			u32::try_from(entries.len() * Entry::RAW_SIZE).map_err(|_| too_large())?;
+		table_byte_length
+			.checked_add(8)
+			.and_then(|end_of_table| end_of_table.checked_add(byte_offset))
+			.ok_or_else(too_large)?;
+
+		let mut header = Vec::with_capacity(8 + table_byte_length as usize);
+		header.extend(Package::MAGIC);
+		header.extend(table_byte_length.to_le_bytes());
+		for planned in &entries {
+			header.extend(planned.entry.to_bytes()?);
+		}
+
+		Ok(Self {
+			original_end_of_table,
+			header,
+			entries,
+			layout,
+			data_byte_length: byte_offset,
+		})
+	}
+
+	/// Returns a result to the plan of overlaying `overrides` on top of the
+	/// package read from `reader`, after applying `edits`.
+	///
+	/// Overrides are keyed by their path within the package along with their
+	/// byte length. A path such as `actors.rpk/opponent03` overrides an asset of
+	/// the nested package `actors.rpk`, whose table is read from `reader` and
+	/// rebuilt with [`Plan::new`]. Nested packages are referred to by their new
+	/// name if they are renamed by `edits`. An override of a whole nested package
+	/// takes precedence over overrides and edits of entries within it.
+	///
+	/// # Errors
+	///
+	/// Errors may be returned according to:
+	///
+	/// - [`read_table`] of the package or a nested package with overrides
+	/// - [`Plan::new`]
+	pub fn read<R: Read + Seek>(
+		reader: &mut R,
+		overrides: &BTreeMap<String, u32>,
+		edits: &Edits,
+	) -> Result<Self, EntryError<'static>> {
+		let overrides = overrides
+			.iter()
+			.map(|(path, byte_length)| (path.as_str(), *byte_length))
+			.collect();
+
+		Self::read_at(reader, Some(0), overrides, edits)
+	}
+
+	/// Plans the package starting at `offset` of `reader`, or a foreign package
+	/// if `offset` is `None`.
+	fn read_at<R: Read + Seek>(
+		reader: &mut R,
+		offset: Option<u32>,
+		overrides: Vec<(&str, u32)>,
+		edits: &Edits,
+	) -> Result<Self, EntryError<'static>> {
+		let original_entries = match offset {
+			Some(offset) => read_entries(reader, offset)?,
+			None => Vec::new(),
+		};
+
+		let mut assets = Vec::new();
+		let mut nested_overrides: BTreeMap<&str, Vec<(&str, u32)>> = BTreeMap::new();
+		for (path, byte_length) in overrides {
+			match path.split_once('/') {
+				Some((package, path)) => nested_overrides
+					.entry(package)
+					.or_default()
+					.push((path, byte_length)),
+				None => assets.push(Override::new(path, byte_length)),
+			}
+		}
+		let (edits, nested_edits) = edits.split_nested();
+
+		// original entries by their name after edits, the same way as `Plan::new`
+		let new_names: BTreeSet<&str> = edits.rename.values().map(String::as_str).collect();
+		let renamed: BTreeMap<&str, &Entry> = original_entries
+			.iter()
+			.filter(|entry| !edits.remove.contains(&*entry.name))
+			.filter_map(|entry| match edits.rename.get(&*entry.name) {
+				Some(name) => Some((name.as_str(), entry)),
+				None if new_names.contains(&*entry.name) => None,
+				None => Some((&*entry.name, entry)),
+			})
+			.collect();
+
+		let mut packages: BTreeSet<&str> = nested_overrides.keys().copied().collect();
+		packages.extend(
+			renamed.iter().filter_map(|(name, entry)| {
+				nested_edits.contains_key(&*entry.name).then_some(*name)
+			}),
+		);
+
+		let end_of_table = u32::try_from(original_entries.len() * Entry::RAW_SIZE)
+			.ok()
+			.and_then(|table_byte_length| table_byte_length.checked_add(8))
+			.ok_or_else(offset_out_of_bounds)?;
+		for package in packages {
+			if assets.iter().any(|asset| asset.name == package) {
+				continue;
+			}
+
+			let original = renamed.get(package);
+			let nested_offset = original
+				.zip(offset)
+				.map(|(entry, offset)| {
+					offset
+						.checked_add(end_of_table)
+						.and_then(|offset| offset.checked_add(entry.byte_offset))
+						.ok_or_else(offset_out_of_bounds)
+				})
+				.transpose()?;
+			let overrides = nested_overrides.remove(package).unwrap_or_default();
+			let edits = original
+				.and_then(|entry| nested_edits.get(&*entry.name))
+				.cloned()
+				.unwrap_or_default();
+			let plan = Self::read_at(reader, nested_offset, overrides, &edits)?;
+			assets.push(Override::package(package, plan));
+		}
+
+		Self::new(original_entries, assets, &edits)
+	}
+
+	/// The magic, table byte length and entry table of the virtual package.
+	#[inline]
+	pub fn header(&self) -> &[u8] {
+		&self.header
+	}
+
+	/// The entries of the virtual package, sorted by name.
+	#[inline]
+	pub fn entries(&self) -> &[PlannedEntry] {
+		&self.entries
+	}
+
+	/// Offset of the first asset in the original package.
+	#[inline]
+	pub fn original_end_of_table(&self) -> u32 {
+		self.original_end_of_table
+	}
+
+	/// Offset of the first asset in the virtual package.
+	#[inline]
+	pub fn end_of_table(&self) -> u32 {
+		self.header.len() as _
+	}
+
+	/// Total byte length of the virtual package.
+	#[inline]
+	pub fn byte_length(&self) -> u32 {
+		self.end_of_table() + self.data_byte_length
+	}
+
+	/// Returns the segments making up a read of `byte_length` bytes starting at
+	/// `position` in the virtual package, in order.
+	///
+	/// The segments are shorter than `byte_length` in total when the read goes
+	/// past the end of the virtual package, like a short read of a file.
+	pub fn resolve(&self, position: u32, byte_length: u32) -> Vec<Segment<'_>> {
+		let mut segments = Vec::new();
+		self.resolve_into(None, 0, position, byte_length, &mut segments);
+
+		segments
+	}
+
+	/// Pushes the segments of a read into `segments`, for a package nested at
+	/// `package` whose original starts at `base` of the original file.
+	fn resolve_into<'a>(
+		&'a self,
+		package: Option<&str>,
+		base: u32,
+		position: u32,
+		byte_length: u32,
+		segments: &mut Vec<Segment<'a>>,
+	) {
+		let end = position.saturating_add(byte_length).min(self.byte_length());
+		let mut position = position;
+
+		if position < self.end_of_table() && position < end {
+			let segment_end = end.min(self.end_of_table());
+			segments.push(Segment {
+				region: Region::Header(&self.header[position as usize..segment_end as usize]),
+				byte_length: segment_end - position,
+			});
+			position = segment_end;
+		}
+
+		let first = self.layout.partition_point(|&i| {
+			let entry = &self.entries[i].entry;
+			self.end_of_table() + entry.byte_offset + entry.byte_length <= position
+		});
+		for &i in &self.layout[first..] {
+			if position >= end {
+				break;
+			}
+
+			let PlannedEntry { entry, source } = &self.entries[i];
+			let entry_start = self.end_of_table() + entry.byte_offset;
+			let entry_end = entry_start + entry.byte_length;
+			if entry_end <= position {
+				// zero length entries take up no bytes of the read
+				continue;
+			}
+
+			let offset = position - entry_start;
+			let segment_end = end.min(entry_end);
+			let path = || match package {
+				Some(package) => Cow::Owned(format!("{package}/{}", entry.name)),
+				None => Cow::Borrowed(&*entry.name),
+			};
+			let region = match source {
+				Source::Original { byte_offset } => Region::Original {
+					offset: base + byte_offset + offset,
+				},
+				Source::Modified | Source::Foreign => Region::Override {
+					path: path(),
+					offset,
+				},
+				Source::Nested { byte_offset, plan } => {
+					plan.resolve_into(
+						Some(&path()),
+						base + byte_offset,
+						offset,
+						segment_end - position,
+						segments,
+					);
+					position = segment_end;
+					continue;
+				}
+			};
+			segments.push(Segment {
+				region,
+				byte_length: segment_end - position,
+			});
+			position = segment_end;
+		}
+	}
+}
+
+/// Returns a result to the entries of the package starting at `offset` of
+/// `reader`.
+fn read_entries<R: Read + Seek>(
+	reader: &mut R,
+	offset: u32,
+) -> Result<Vec<Entry>, EntryError<'static>> {
+	reader.seek(SeekFrom::Start(offset as _))?;
+	let mut reader = Reader::new(reader);
+	let table_byte_length = read_table(&mut reader)?.table_byte_length();
+
+	// the table byte length is not trusted to preallocate with, a corrupt one
+	// fails at the end of the reader instead
+	let mut entries = Vec::new();
+	for _ in 0..table_byte_length as usize / Entry::RAW_SIZE {
+		entries.push(Entry::from_reader_with_ctx(&mut reader, ())?);
+	}
+
+	Ok(entries)
+}
+
+/// The error of a nested package starting past the end of a package.
+fn offset_out_of_bounds() -> EntryError<'static> {
+	io::Error::new(
+		io::ErrorKind::InvalidData,
+		"nested package offset out of bounds",
+	)
+	.into()
+}
+
+/// Reads the bytes of overrides for a [`VirtualPackage`].
+pub trait Overrides {
+	/// Reads exactly `buf.len()` bytes of the override at `path`, starting at
+	/// `offset` from the start of the override.
+	fn read_at(&self, path: &str, offset: u32, buf: &mut [u8]) -> io::Result<()>;
+}
+
+impl<O: Overrides + ?Sized> Overrides for &O {
+	#[inline]
+	fn read_at(&self, path: &str, offset: u32, buf: &mut [u8]) -> io::Result<()> {
+		(**self).read_at(path, offset, buf)
+	}
+}
+
+/// Overrides read from files, by path within the package.
+impl Overrides for HashMap<String, PathBuf> {
+	fn read_at(&self, path: &str, offset: u32, buf: &mut [u8]) -> io::Result<()> {
+		let file_path = self.get(path).ok_or_else(|| {
+			io::Error::new(io::ErrorKind::NotFound, format!("no override at {path}"))
+		})?;
+		let mut file = File::open(file_path)?;
+		file.seek(SeekFrom::Start(offset as _))?;
+		file.read_exact(buf)
+	}
+}
+
+/// Overrides held in memory, by path within the package.
+impl Overrides for HashMap<String, Vec<u8>> {
+	fn read_at(&self, path: &str, offset: u32, buf: &mut [u8]) -> io::Result<()> {
+		let bytes = self.get(path).ok_or_else(|| {
+			io::Error::new(io::ErrorKind::NotFound, format!("no override at {path}"))
+		})?;
+		let bytes = bytes
+			.get(offset as usize..offset as usize + buf.len())
+			.ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+		buf.copy_from_slice(bytes);
+
+		Ok(())
+	}
+}
+
+/// A reader over the virtual package described by a [`Plan`], serving any read
+/// from the virtual header, the `original` package and the `overrides`.
+///
+/// Reads spanning several entries are split at entry boundaries and reads past
+/// the end of the virtual package are short, like reads of a file.
+#[derive(Debug)]
+pub struct VirtualPackage<'a, R, O> {
+	plan: &'a Plan,
+	original: R,
+	overrides: O,
+	position: u64,
+}
+
+impl<'a, R: Read + Seek, O: Overrides> VirtualPackage<'a, R, O> {
+	#[inline]
+	pub fn new(plan: &'a Plan, original: R, overrides: O) -> Self {
+		Self {
+			plan,
+			original,
+			overrides,
+			position: 0,
+		}
+	}
+
+	#[inline]
+	pub fn into_inner(self) -> R {
+		self.original
+	}
+}
+
+impl<R: Read + Seek, O: Overrides> Read for VirtualPackage<'_, R, O> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let Ok(position) = u32::try_from(self.position) else {
+			return Ok(0);
+		};
+		let byte_length = u32::try_from(buf.len()).unwrap_or(u32::MAX);
+
+		let mut bytes_read = 0;
+		for Segment {
+			region,
+			byte_length,
+		} in self.plan.resolve(position, byte_length)
+		{
+			let buf = &mut buf[bytes_read..bytes_read + byte_length as usize];
+			match region {
+				Region::Header(header) => buf.copy_from_slice(header),
+				Region::Original { offset } => {
+					self.original.seek(SeekFrom::Start(offset as _))?;
+					self.original.read_exact(buf)?;
+				}
+				Region::Override { path, offset } => self.overrides.read_at(&path, offset, buf)?,
+			}
+			bytes_read += buf.len();
+		}
+		self.position += bytes_read as u64;
+
+		Ok(bytes_read)
+	}
+}
+
+impl<R, O> Seek for VirtualPackage<'_, R, O> {
+	fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+		let (base, offset) = match pos {
+			SeekFrom::Start(offset) => {
+				self.position = offset;
+				return Ok(offset);
+			}
+			SeekFrom::End(offset) => (self.plan.byte_length() as u64, offset),
+			SeekFrom::Current(offset) => (self.position, offset),
+		};
+		self.position = base.checked_add_signed(offset).ok_or_else(|| {
+			io::Error::new(
+				io::ErrorKind::InvalidInput,
+				"invalid seek to a negative or overflowing position",
+			)
+		})?;
+
+		Ok(self.position)
+	}
+}
+
+/// Writes the virtual package of `plan` to `writer`, physically merging the
+/// overrides and edits into a package that can be used without the overlay.
+/// Returns the amount of bytes written.
+///
+/// # Errors
+///
+/// Errors may be returned according to:
+///
+/// - [`VirtualPackage`] reading from `original` or `overrides`
+/// - [`io::copy`]
+pub fn write_merged<R: Read + Seek, O: Overrides, W: Write + ?Sized>(
+	plan: &Plan,
+	original: R,
+	overrides: O,
+	writer: &mut W,
+) -> io::Result<u64> {
+	io::copy(&mut VirtualPackage::new(plan, original, overrides), writer)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::Cursor;
+
+	use deku::reader::Reader;
+	use proptest::{collection, prelude::*};
+
+	use super::*;
+	use crate::read_table;
+
+	/// Packs `assets` into the bytes of a package, in the given order and with
+	/// the given amount of padding after each asset.
+	fn padded_package(assets: &[(&str, &[u8], usize)]) -> (Vec<Entry>, Vec<u8>) {
+		let mut entries = Vec::new();
+		let mut data = Vec::new();
+		for (name, bytes, padding) in assets {
+			let mut entry = Entry::new(name).with_byte_length(bytes.len() as _);
+			entry.byte_offset = data.len() as _;
+			entries.push(entry);
+			data.extend(*bytes);
+			data.extend(vec![0xEE; *padding]);
+		}
+
+		let mut package = Vec::new();
+		package.extend(Package::MAGIC);
+		package.extend(((entries.len() * Entry::RAW_SIZE) as u32).to_le_bytes());
+		let mut table = entries.iter().collect::<Vec<_>>();
+		table.sort();
+		for entry in table {
+			package.extend(entry.to_bytes().unwrap());
+		}
+		package.extend(data);
+
+		(entries, package)
+	}
+
+	fn package(assets: &[(&str, &[u8])]) -> (Vec<Entry>, Vec<u8>) {
+		let assets: Vec<_> = assets
+			.iter()
+			.map(|(name, bytes)| (*name, *bytes, 0))
+			.collect();
+		padded_package(&assets)
+	}
+
+	/// Physically merges `overrides` into the package of `assets`, laid out the
+	/// same way as a [`Plan`].
+	fn merged_package(
+		assets: &[(&str, &[u8], usize)],
+		overrides: &HashMap<String, Vec<u8>>,
+	) -> Vec<u8> {
+		let mut merged: Vec<(&str, &[u8])> = assets
+			.iter()
+			.map(|(name, bytes, _)| match overrides.get(*name) {
+				Some(bytes) => (*name, bytes.as_slice()),
+				None => (*name, *bytes),
+			})
+			.collect();
+		let mut foreign: Vec<_> = overrides
+			.iter()
+			.filter(|(name, _)| !assets.iter().any(|(asset, _, _)| asset == name))
+			.map(|(name, bytes)| (name.as_str(), bytes.as_slice()))
+			.collect();
+		foreign.sort();
+		merged.extend(foreign);
+
+		package(&merged).1
+	}
+
+	fn overrides(overrides: &[(&str, &[u8])]) -> HashMap<String, Vec<u8>> {
+		overrides
+			.iter()
+			.map(|(name, bytes)| (name.to_string(), bytes.to_vec()))
+			.collect()
+	}
+
+	fn plan(entries: Vec<Entry>, overrides: &HashMap<String, Vec<u8>>) -> Plan {
+		Plan::new(
+			entries,
+			overrides
+				.iter()
+				.map(|(name, bytes)| Override::new(name, bytes.len() as _)),
+			&Edits::default(),
+		)
+		.unwrap()
+	}
+
+	#[test]
+	fn plan_overlay() {
+		let (entries, original) =
+			package(&[("zebra", b"zzzz"), ("apple", b"aa"), ("mango", b"mmmmmm")]);
+		let overrides = overrides(&[("apple", b"APPLE!"), ("banana", b"BANANA")]);
+		let plan = plan(entries, &overrides);
+
+		assert_eq!(plan.original_end_of_table(), 8 + 3 * 32);
+		assert_eq!(plan.end_of_table(), 8 + 4 * 32);
+		assert_eq!(plan.byte_length(), plan.end_of_table() + 4 + 6 + 6 + 6);
+
+		let planned: Vec<_> = plan
+			.entries()
+			.iter()
+			.map(|p| {
+				(
+					&*p.entry.name,
+					p.entry.byte_offset,
+					p.entry.byte_length,
+					&p.source,
+				)
+			})
+			.collect();
+		assert_eq!(
+			planned,
+			vec![
+				("apple", 4, 6, &Source::Modified),
+				("banana", 16, 6, &Source::Foreign),
+				(
+					"mango",
+					10,
+					6,
+					&Source::Original {
+						byte_offset: 104 + 6
+					}
+				),
+				("zebra", 0, 4, &Source::Original { byte_offset: 104 }),
+			]
+		);
+
+		let mut virtual_package = Vec::new();
+		VirtualPackage::new(&plan, Cursor::new(&original), &overrides)
+			.read_to_end(&mut virtual_package)
+			.unwrap();
+		assert_eq!(virtual_package.len(), plan.byte_length() as usize);
+		let mut cursor = Cursor::new(&virtual_package);
+		let mut reader = Reader::new(&mut cursor);
+		let table = read_table(&mut reader).unwrap();
+		let table_byte_length = table.table_byte_length();
+		let names: Vec<_> = table
+			.take(4)
+			.map(|entry| entry.unwrap())
+			.map(|entry| {
+				let start = entry.asset_offset(table_byte_length) as usize;
+				let bytes = &virtual_package[start..start + entry.byte_length as usize];
+				(entry.name.to_string(), bytes.to_vec())
+			})
+			.collect();
+		assert_eq!(
+			names,
+			vec![
+				("apple".to_string(), b"APPLE!".to_vec()),
+				("banana".to_string(), b"BANANA".to_vec()),
+				("mango".to_string(), b"mmmmmm".to_vec()),
+				("zebra".to_string(), b"zzzz".to_vec()),
+			]
+		);
+	}
+
+	#[test]
+	fn resolve_reads() {
+		let (entries, original) = package(&[("first", b"1111"), ("second", b"2222")]);
+		let overrides = overrides(&[("second", b"22")]);
+		let plan = plan(entries, &overrides);
+		let end_of_table = plan.end_of_table();
+
+		assert_eq!(
+			plan.resolve(0, 8),
+			vec![Segment {
+				region: Region::Header(&plan.header()[..8]),
+				byte_length: 8
+			}]
+		);
+		assert_eq!(
+			plan.resolve(end_of_table - 2, 8),
+			vec![
+				Segment {
+					region: Region::Header(&plan.header()[end_of_table as usize - 2..]),
+					byte_length: 2
+				},
+				Segment {
+					region: Region::Original {
+						offset: plan.original_end_of_table()
+					},
+					byte_length: 4
+				},
+				Segment {
+					region: Region::Override {
+						path: "second".into(),
+						offset: 0
+					},
+					byte_length: 2
+				},
+			]
+		);
+		assert!(plan.resolve(plan.byte_length(), 8).is_empty());
+
+		let mut virtual_package = VirtualPackage::new(&plan, Cursor::new(&original), &overrides);
+		let mut buf = [0; 100];
+		virtual_package
+			.seek(SeekFrom::Start(end_of_table as u64 + 3))
+			.unwrap();
+		assert_eq!(virtual_package.read(&mut buf).unwrap(), 3);
+		assert_eq!(&buf[..3], b"122");
+		assert_eq!(virtual_package.read(&mut buf).unwrap(), 0);
+		virtual_package.seek(SeekFrom::End(-1)).unwrap();
+		assert_eq!(virtual_package.read(&mut buf).unwrap(), 1);
+		assert_eq!(&buf[..1], b"2");
+		assert!(virtual_package.seek(SeekFrom::Current(-100_000)).is_err());
+	}
+
+	#[test]
+	fn plan_nested_overrides() {
+		let nested_assets = [
+			("opponent01", b"o1".as_slice(), 0),
+			("opponent03", b"o3o3", 0),
+		];
+		let (_, nested) = padded_package(&nested_assets);
+		let assets = [("actors.rpk", nested.as_slice(), 2), ("readme", b"r", 0)];
+		let (_, original) = padded_package(&assets);
+		let overrides = overrides(&[
+			("actors.rpk/opponent03", b"NEW3"),
+			("actors.rpk/opponent09", b"9"),
+			("extra.rpk/x", b"x"),
+			("readme", b"R"),
+		]);
+		let byte_lengths = overrides
+			.iter()
+			.map(|(path, bytes)| (path.clone(), bytes.len() as u32))
+			.collect();
+		let plan = Plan::read(
+			&mut Cursor::new(&original),
+			&byte_lengths,
+			&Edits::default(),
+		)
+		.unwrap();
+
+		let sources: Vec<_> = plan
+			.entries()
+			.iter()
+			.map(|p| match &p.source {
+				Source::Nested { byte_offset, plan } => (
+					&*p.entry.name,
+					Some(*byte_offset),
+					plan.entries().iter().map(|p| &*p.entry.name).collect(),
+				),
+				_ => (&*p.entry.name, None, Vec::new()),
+			})
+			.collect();
+		assert_eq!(
+			sources,
+			vec![
+				(
+					"actors.rpk",
+					Some(8 + 2 * 32),
+					vec!["opponent01", "opponent03", "opponent09"]
+				),
+				("extra.rpk", Some(0), vec!["x"]),
+				("readme", None, vec![]),
+			]
+		);
+
+		let nested_merged = merged_package(
+			&nested_assets,
+			&self::overrides(&[("opponent03", b"NEW3"), ("opponent09", b"9")]),
+		);
+		let extra = merged_package(&[], &self::overrides(&[("x", b"x")]));
+		let merged = merged_package(
+			&assets,
+			&self::overrides(&[
+				("actors.rpk", &nested_merged),
+				("extra.rpk", &extra),
+				("readme", b"R"),
+			]),
+		);
+		let mut virtual_package = Vec::new();
+		VirtualPackage::new(&plan, Cursor::new(&original), &overrides)
+			.read_to_end(&mut virtual_package)
+			.unwrap();
+		assert_eq!(virtual_package, merged);
+
+		// a whole nested package takes precedence over overrides within it
+		let byte_lengths = BTreeMap::from([
+			("actors.rpk".to_string(), 4),
+			("actors.rpk/opponent03".to_string(), 4),
+		]);
+		let plan = Plan::read(
+			&mut Cursor::new(&original),
+			&byte_lengths,
+			&Edits::default(),
+		)
+		.unwrap();
+		assert_eq!(plan.entries()[0].source, Source::Modified);
+
+		// overrides within an asset that is not a package
+		let byte_lengths = BTreeMap::from([("readme/x".to_string(), 1)]);
+		assert!(
+			Plan::read(
+				&mut Cursor::new(&original),
+				&byte_lengths,
+				&Edits::default()
+			)
+			.is_err()
+		);
+	}
+
+	#[test]
+	fn plan_edits() {
+		let (_, nested) = package(&[
+			("opponent01", b"o1"),
+			("opponent03", b"o3"),
+			("opponent05", b"o5"),
+		]);
+		let (_, original) = package(&[("actors.rpk", &nested), ("readme", b"r"), ("old", b"old")]);
+		let overrides = overrides(&[("old", b"OLD")]);
+		let byte_lengths = BTreeMap::from([("old".to_string(), 3)]);
+		let edits = Edits {
+			remove: BTreeSet::from(["actors.rpk/opponent03".into(), "readme".into()]),
+			rename: BTreeMap::from([
+				("actors.rpk/opponent05".into(), "opponent01".into()),
+				("old".into(), "new".into()),
+				("missing".into(), "ignored".into()),
+			]),
+		};
+		let plan = Plan::read(&mut Cursor::new(&original), &byte_lengths, &edits).unwrap();
+
+		let mut merged = Vec::new();
+		write_merged(&plan, Cursor::new(&original), &overrides, &mut merged).unwrap();
+		let (_, nested_merged) = package(&[("opponent01", b"o5")]);
+		let (_, expected) = package(&[
+			("actors.rpk", &nested_merged),
+			("new", b"old"),
+			("old", b"OLD"),
+		]);
+		assert_eq!(merged, expected);
+
+		// overrides refer to nested packages by their new name
+		let (_, original) = package(&[("actors.rpk", &nested)]);
+		let byte_lengths = BTreeMap::from([("npcs.rpk/opponent03".to_string(), 2)]);
+		let edits = Edits {
+			remove: BTreeSet::from(["actors.rpk/opponent01".into()]),
+			rename: BTreeMap::from([("actors.rpk".into(), "npcs.rpk".into())]),
+		};
+		let plan = Plan::read(&mut Cursor::new(&original), &byte_lengths, &edits).unwrap();
+		let mut merged = Vec::new();
+		write_merged(
+			&plan,
+			Cursor::new(&original),
+			self::overrides(&[("npcs.rpk/opponent03", b"O3")]),
+			&mut merged,
+		)
+		.unwrap();
+		let (_, nested_merged) = package(&[("opponent03", b"O3"), ("opponent05", b"o5")]);
+		assert_eq!(merged, package(&[("npcs.rpk", &nested_merged)]).1);
+
+		let (entries, _) = package(&[("first", b"1"), ("second", b"2")]);
+		let edits = Edits {
+			remove: BTreeSet::new(),
+			rename: BTreeMap::from([
+				("first".into(), "same".into()),
+				("second".into(), "same".into()),
+			]),
+		};
+		assert!(Plan::new(entries, [], &edits).is_err());
+	}
+
+	#[test]
+	fn invalid_overrides() {
+		let (entries, _) = package(&[("first", b"1111")]);
+		assert!(
+			Plan::new(
+				entries,
+				[Override::new("a name that is too long", 1)],
+				&Edits::default()
+			)
+			.is_err()
+		);
+
+		let (entries, _) = package(&[("first", b"1111")]);
+		let edits = Edits {
+			remove: BTreeSet::new(),
+			rename: BTreeMap::from([("first".into(), "nested/first".into())]),
+		};
+		assert!(Plan::new(entries, [], &edits).is_err());
+
+		let (entries, _) = package(&[("first", b"1111")]);
+		assert!(
+			Plan::new(
+				entries,
+				[
+					Override::new("large", u32::MAX - 8),
+					Override::new("other", 8)
+				],
+				&Edits::default()
+			)
+			.is_err()
+		);
+	}
+
+	#[test]
+	fn invalid_packages() {
+		// a table byte length larger than the package
+		let mut corrupt = Vec::new();
+		corrupt.extend(Package::MAGIC);
+		corrupt.extend(u32::MAX.to_le_bytes());
+		assert!(
+			Plan::read(
+				&mut Cursor::new(&corrupt),
+				&BTreeMap::new(),
+				&Edits::default()
+			)
+			.is_err()
+		);
+
+		// a nested package starting past the end of the addressable package
+		let (mut entries, _) = package(&[("nested", b"")]);
+		entries[0].byte_offset = u32::MAX;
+		let mut outer = Vec::new();
+		outer.extend(Package::MAGIC);
+		outer.extend((Entry::RAW_SIZE as u32).to_le_bytes());
+		outer.extend(entries[0].to_bytes().unwrap());
+		let overrides = BTreeMap::from([("nested/first".to_string(), 4)]);
+		let error =
+			Plan::read(&mut Cursor::new(&outer), &overrides, &Edits::default()).unwrap_err();
+		assert!(matches!(
+			error,
+			EntryError::Io(e) if e.kind() == io::ErrorKind::InvalidData
+		));
+	}
+
+	/// Assets of an original package in the order they are laid out in, with
+	/// padding after each asset.
+	fn assets() -> impl Strategy<Value = Vec<(String, Vec<u8>, usize)>> {
+		collection::btree_map(
+			"[a-f]{1,2}",
+			(collection::vec(any::<u8>(), 0..24), 0..4usize),
+			0..8,
+		)
+		.prop_map(|assets| {
+			assets
+				.into_iter()
+				.map(|(name, (bytes, padding))| (name, bytes, padding))
+				.collect::<Vec<_>>()
+		})
+		.prop_shuffle()
+	}
+
+	proptest! {
+		#[test]
+		fn virtual_package_matches_merged_package(
+			assets in assets(),
+			overrides in collection::hash_map(
+				"[a-h]{1,2}",
+				collection::vec(any::<u8>(), 0..24),
+				0..6,
+			),
+			reads in collection::vec((any::<prop::sample::Index>(), 0..96usize), 1..16),
+		) {
+			let assets: Vec<_> = assets
+				.iter()
+				.map(|(name, bytes, padding)| (name.as_str(), bytes.as_slice(), *padding))
+				.collect();
+			let (entries, original) = padded_package(&assets);
+			let merged = merged_package(&assets, &overrides);
+			let plan = plan(entries, &overrides);
+			let mut virtual_package = VirtualPackage::new(&plan, Cursor::new(&original), &overrides);
+
+			let mut bytes = Vec::new();
+			virtual_package.read_to_end(&mut bytes).unwrap();
+			prop_assert_eq!(&bytes, &merged);
+
+			for (position, byte_length) in reads {
+				let position = position.index(merged.len() + 16);
+				virtual_package.seek(SeekFrom::Start(position as _)).unwrap();
+				let mut buf = vec![0; byte_length];
+				let bytes_read = virtual_package.read(&mut buf).unwrap();
+				let expected = merged.get(position..).unwrap_or_default();
+				let expected = &expected[..expected.len().min(byte_length)];
+				prop_assert_eq!(&buf[..bytes_read], expected);
+			}
+		}
+	}
+}
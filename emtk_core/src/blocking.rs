@@ -0,0 +1,188 @@
+//! A blocking API over the async API of `emtk_core` for callers without a tokio
+//! runtime, such as the framework running inside the game or simple scripts.
+//!
+//! Each function drives the async API to completion on a single threaded
+//! runtime, so the logic is shared with the async API. These functions must not
+//! be called from within an async runtime.
+
+use std::{fmt, future::Future, path::Path};
+
+use tokio::runtime;
+use tracing::instrument;
+
+use crate::{
+	Error, Result, conflict::Ownership, prelude::*, profile::LoadOrderEntry, signature::Signatures,
+};
+
+/// Returns a result to the output of driving `future` to completion on a new
+/// single threaded runtime.
+///
+/// # Errors
+///
+/// Errors may be returned according to:
+///
+/// - `tokio::runtime::Builder::build`
+///
+/// # Panics
+///
+/// This function panics if called from within an async runtime.
+#[instrument(level = "trace", skip(future))]
+pub fn block_on<F: Future>(future: F) -> Result<F::Output> {
+	let runtime = runtime::Builder::new_current_thread()
+		.enable_all()
+		.build()
+		.map_err(Error::msg("failed to create runtime"))?;
+
+	Ok(runtime.block_on(future))
+}
+
+/// Returns a result to the instance built from the game directory at `path`.
+///
+/// See [`Instance::with_path`] and [`instance::InstanceBuilder::build`].
+#[instrument(level = "trace")]
+pub fn build_instance<P: AsRef<Path> + fmt::Debug>(path: P, force: bool) -> Result<Instance> {
+	block_on(async {
+		let instance_builder = Instance::with_path(path.as_ref())?;
+		if force {
+			instance_builder.force().build().await
+		} else {
+			instance_builder.build().await
+		}
+	})?
+}
+
+/// Returns a result to the profile built from the profile directory at `path`.
+///
+/// See [`Profile::with_path`] and [`profile::ProfileBuilder::build`].
+#[instrument(level = "trace")]
+pub fn build_profile<P: AsRef<Path> + fmt::Debug>(path: P) -> Result<Profile> {
+	block_on(async { Profile::with_path(path.as_ref()).await?.build().await })?
+}
+
+/// Returns a result to the existing profile at `path` without discovering mods
+/// or writing its load order, for callers that must not change the profile such
+/// as the framework running inside the game.
+///
+/// See [`Profile::read`].
+#[instrument(level = "trace")]
+pub fn read_profile<P: AsRef<Path> + fmt::Debug>(path: P) -> Result<Profile> {
+	block_on(Profile::read(path.as_ref()))?
+}
+
+/// Returns a result to the enabled mods of the profile's load order along with
+/// their manifests, ordered by priority.
+///
+/// See [`Profile::enabled_mods`].
+#[instrument(level = "trace")]
+pub fn enabled_mods(
+	profile: &Profile,
+) -> Result<Vec<(plugin::Id, LoadOrderEntry, plugin::Manifest)>> {
+	block_on(profile.enabled_mods())?
+}
+
+/// Returns a result to the ownership of the assets provided by the enabled mods
+/// of the profile's load order.
+///
+/// See [`Profile::asset_ownership`].
+#[instrument(level = "trace")]
+pub fn asset_ownership(profile: &Profile) -> Result<Ownership> {
+	block_on(profile.asset_ownership())?
+}
+
+/// Returns a result to the manifest deserialized from the file at `path`.
+///
+/// See [`plugin::Manifest::read`].
+#[instrument(level = "trace")]
+pub fn read_manifest<P: AsRef<Path> + fmt::Debug>(path: P) -> Result<plugin::Manifest> {
+	block_on(plugin::Manifest::read(path.as_ref()))?
+}
+
+/// Returns a result to the signature registry deserialized from the file at
+/// `path`.
+///
+/// See [`Signatures::read`].
+#[instrument(level = "trace")]
+pub fn read_signatures<P: AsRef<Path> + fmt::Debug>(path: P) -> Result<Signatures> {
+	block_on(Signatures::read(path.as_ref()))?
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fs;
+
+	use pretty_assertions::assert_eq;
+	use tempfile::tempdir;
+
+	use crate::{blocking, prelude::*};
+
+	#[test]
+	fn blocking_instance() {
+		let cwd = tempdir().unwrap();
+		fs::write(cwd.path().join(Instance::BINARY), "").unwrap();
+		let mods_dir = cwd.path().join(Instance::MODS_DIR);
+		for (id, name) in [
+			("com.example.first", "First"),
+			("com.example.second", "Second"),
+		] {
+			fs::create_dir_all(mods_dir.join(id)).unwrap();
+			fs::write(
+				mods_dir.join(id).join(plugin::Manifest::TOML),
+				format!("[plugin]\nname = \"{name}\"\nversion = \"1.0.0\"\n"),
+			)
+			.unwrap();
+		}
+
+		let instance = blocking::build_instance(cwd.path(), false).unwrap();
+		let profile_path = instance.profile().path().clone();
+		fs::write(
+			profile_path.join(Profile::LOAD_ORDER_TOML),
+			"[\"com.example.first\"]\nenabled = true\npriority = 1\n\n[\"com.example.second\"]\nenabled = true\npriority = 0\n",
+		)
+		.unwrap();
+
+		// reading the profile leaves the load order untouched, even with new mods
+		let third_dir = mods_dir.join("com.example.third");
+		fs::create_dir_all(&third_dir).unwrap();
+		fs::write(
+			third_dir.join(plugin::Manifest::TOML),
+			"[plugin]\nname = \"Third\"\nversion = \"1.0.0\"\n",
+		)
+		.unwrap();
+		let load_order = fs::read_to_string(profile_path.join(Profile::LOAD_ORDER_TOML)).unwrap();
+		let profile = blocking::read_profile(&profile_path).unwrap();
+		assert_eq!(profile.load_order().len(), 2);
+		assert_eq!(
+			fs::read_to_string(profile_path.join(Profile::LOAD_ORDER_TOML)).unwrap(),
+			load_order
+		);
+		assert_eq!(
+			blocking::build_profile(&profile_path)
+				.unwrap()
+				.load_order()
+				.len(),
+			3
+		);
+
+		let enabled_mods: Vec<_> = blocking::enabled_mods(&profile)
+			.unwrap()
+			.into_iter()
+			.map(|(id, _, manifest)| (id.to_string(), manifest.plugin.name))
+			.collect();
+		assert_eq!(
+			enabled_mods,
+			vec![
+				("com.example.second".to_string(), "Second".to_string()),
+				("com.example.first".to_string(), "First".to_string())
+			]
+		);
+
+		let manifest = blocking::read_manifest(
+			mods_dir
+				.join("com.example.first")
+				.join(plugin::Manifest::TOML),
+		)
+		.unwrap();
+		assert_eq!(manifest.plugin.version, "1.0.0");
+		assert!(blocking::read_manifest(mods_dir.join("missing.toml")).is_err());
+	}
+}
@@ -0,0 +1,300 @@
+//! Ownership of the assets that enabled mods provide for the game's packages,
+//! and the conflicts between mods providing the same asset.
+//!
+//! Mods provide assets as loose files in a directory of
+//! [`plugin::Id::packages_dir`] named after the package they override, such as
+//! `Resource`. Directories with an `.rpk` extension hold the assets of a nested
+//! package, such as `Resource/actors.rpk/opponent03`. When several enabled
+//! mods provide the same asset, the mod loaded last in the load order wins.
+
+use std::{
+	collections::BTreeMap,
+	fmt::{self, Display, Formatter},
+	path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+use tracing::{info, instrument};
+
+use crate::{Error, Profile, Result, plugin};
+
+/// A mod providing an asset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Owner {
+	pub id: plugin::Id,
+	/// Path to the loose file of the asset
+	pub path: PathBuf,
+}
+
+/// An asset provided by several enabled mods.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+	/// Name of the package without its extension
+	pub package: String,
+	/// Path of the asset within the package, such as `actors.rpk/opponent03`
+	pub path: String,
+	/// The mod whose asset is used
+	pub winner: plugin::Id,
+	/// The other mods providing the asset, from the highest to lowest priority
+	pub overridden: Vec<plugin::Id>,
+}
+
+impl Display for Conflict {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"{}/{}: {} overrides ",
+			self.package, self.path, self.winner
+		)?;
+		for (i, id) in self.overridden.iter().enumerate() {
+			if i > 0 {
+				f.write_str(", ")?;
+			}
+			write!(f, "{id}")?;
+		}
+		Ok(())
+	}
+}
+
+/// The owners of every asset provided by enabled mods.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Ownership {
+	/// Owners of the assets by package and path, from the lowest to highest
+	/// priority.
+	assets: BTreeMap<String, BTreeMap<String, Vec<Owner>>>,
+}
+
+impl Ownership {
+	/// Returns a result to the ownership of the assets provided by `mods` in
+	/// the game directory at `game_dir`, with `mods` ordered from the lowest to
+	/// highest priority.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if:
+	///
+	/// - a directory of assets could not be read
+	/// - the name of an asset is not valid UTF-8
+	#[instrument(level = "trace")]
+	pub fn read(game_dir: &Path, mods: &[plugin::Id]) -> Result<Self> {
+		let mut ownership = Self::default();
+		for id in mods {
+			let packages_dir = game_dir.join(id.packages_dir());
+			if !packages_dir.is_dir() {
+				continue;
+			}
+
+			for (package_dir, package) in read_dir(&packages_dir)? {
+				if !package_dir.is_dir() {
+					continue;
+				}
+				let mut files = Vec::new();
+				collect_assets(&package_dir, None, &mut files)?;
+				let assets = ownership.assets.entry(package).or_default();
+				for (path, file) in files {
+					assets.entry(path).or_default().push(Owner {
+						id: id.clone(),
+						path: file,
+					});
+				}
+			}
+		}
+		info!("ownership of assets read from {} mods", mods.len());
+
+		Ok(ownership)
+	}
+
+	/// Returns an iterator over the names of the packages with assets provided
+	/// by mods.
+	pub fn packages(&self) -> impl Iterator<Item = &str> {
+		self.assets.keys().map(String::as_str)
+	}
+
+	/// Returns the mod whose asset at `path` within `package` is used.
+	pub fn winner(&self, package: &str, path: &str) -> Option<&Owner> {
+		self.assets.get(package)?.get(path)?.last()
+	}
+
+	/// Returns an iterator over the paths of the assets provided for `package`
+	/// along with the mods whose assets are used.
+	pub fn winners<'a>(&'a self, package: &str) -> impl Iterator<Item = (&'a str, &'a Owner)> {
+		self.assets
+			.get(package)
+			.into_iter()
+			.flatten()
+			.filter_map(|(path, owners)| Some((path.as_str(), owners.last()?)))
+	}
+
+	/// Returns the assets provided by more than one mod, ordered by package and
+	/// path.
+	pub fn conflicts(&self) -> Vec<Conflict> {
+		self.assets
+			.iter()
+			.flat_map(|(package, assets)| {
+				assets.iter().filter_map(move |(path, owners)| {
+					let (winner, overridden) = owners.split_last()?;
+					if overridden.is_empty() {
+						return None;
+					}
+					Some(Conflict {
+						package: package.clone(),
+						path: path.clone(),
+						winner: winner.id.clone(),
+						overridden: overridden.iter().rev().map(|o| o.id.clone()).collect(),
+					})
+				})
+			})
+			.collect()
+	}
+}
+
+impl Profile {
+	/// Returns a result to the ownership of the assets provided by the enabled
+	/// mods of the load order.
+	///
+	/// # Errors
+	///
+	/// Errors may be returned according to:
+	///
+	/// - [`Profile::game_dir`]
+	/// - [`Profile::enabled_mods`]
+	/// - [`Ownership::read`]
+	#[instrument(level = "trace")]
+	pub async fn asset_ownership(&self) -> Result<Ownership> {
+		let game_dir = self.game_dir().await?;
+		let mods: Vec<_> = self
+			.enabled_mods()
+			.await?
+			.into_iter()
+			.map(|(id, ..)| id)
+			.collect();
+
+		tokio::task::spawn_blocking(move || Ownership::read(&game_dir, &mods))
+			.await
+			.map_err(|e| Error::new(anyhow!(e), "failed to read asset ownership"))?
+	}
+}
+
+/// Returns a result to the paths and names of the entries in `dir`, sorted by
+/// name.
+fn read_dir(dir: &Path) -> Result<Vec<(PathBuf, String)>> {
+	let mut entries = std::fs::read_dir(dir)
+		.map_err(Error::msg("failed to read directory of assets"))?
+		.map(|entry| entry.map(|entry| entry.path()))
+		.collect::<std::io::Result<Vec<PathBuf>>>()
+		.map_err(Error::msg(
+			"failed to read next entry in directory of assets",
+		))?;
+	entries.sort();
+
+	entries
+		.into_iter()
+		.map(|path| {
+			let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+				return Err(Error::new(
+					anyhow!("\"{}\" is not valid UTF-8", path.display()),
+					"failed to read asset name",
+				));
+			};
+			let name = name.to_string();
+			Ok((path, name))
+		})
+		.collect()
+}
+
+/// Collects the loose files inside of `dir` into `files`, keyed by their path
+/// within the package.
+fn collect_assets(
+	dir: &Path,
+	prefix: Option<&str>,
+	files: &mut Vec<(String, PathBuf)>,
+) -> Result<()> {
+	for (path, name) in read_dir(dir)? {
+		let name = match prefix {
+			Some(prefix) => format!("{prefix}/{name}"),
+			None => name,
+		};
+		if path.is_dir() {
+			if path
+				.extension()
+				.is_some_and(|ext| ext.eq_ignore_ascii_case("rpk"))
+			{
+				collect_assets(&path, Some(&name), files)?;
+			}
+		} else {
+			files.push((name, path));
+		}
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fs;
+
+	use pretty_assertions::assert_eq;
+	use tempfile::tempdir;
+
+	use super::*;
+
+	#[test]
+	fn asset_ownership() {
+		let cwd = tempdir().unwrap();
+		let ids: Vec<_> = [
+			"com.example.first",
+			"com.example.second",
+			"com.example.third",
+		]
+		.into_iter()
+		.map(|id| plugin::Id::try_from(id).unwrap())
+		.collect();
+		for (id, assets) in ids.iter().zip([
+			&["Resource/opponent03", "Resource/actors.rpk/opponent03"][..],
+			&["Resource/opponent03", "Resource/ignored/opponent03"][..],
+			&["Resource/actors.rpk/opponent03", "Sound/theme"][..],
+		]) {
+			for asset in assets {
+				let path = cwd.path().join(id.packages_dir()).join(asset);
+				fs::create_dir_all(path.parent().unwrap()).unwrap();
+				fs::write(path, id.to_string()).unwrap();
+			}
+		}
+
+		let ownership = Ownership::read(cwd.path(), &ids).unwrap();
+		assert_eq!(
+			ownership.packages().collect::<Vec<_>>(),
+			["Resource", "Sound"]
+		);
+		assert_eq!(
+			ownership.winner("Resource", "opponent03").unwrap().path,
+			cwd.path()
+				.join(ids[1].packages_dir())
+				.join("Resource/opponent03")
+		);
+		assert_eq!(
+			ownership
+				.winners("Resource")
+				.map(|(path, owner)| (path, owner.id.to_string()))
+				.collect::<Vec<_>>(),
+			[
+				("actors.rpk/opponent03", "com.example.third".to_string()),
+				("opponent03", "com.example.second".to_string())
+			]
+		);
+		assert!(ownership.winner("Resource", "ignored/opponent03").is_none());
+
+		let conflicts: Vec<_> = ownership
+			.conflicts()
+			.iter()
+			.map(ToString::to_string)
+			.collect();
+		assert_eq!(
+			conflicts,
+			[
+				"Resource/actors.rpk/opponent03: com.example.third overrides com.example.first",
+				"Resource/opponent03: com.example.second overrides com.example.first",
+			]
+		);
+	}
+}
@@ -0,0 +1,524 @@
+//! Discovery of Exanima installs from Steam libraries, GOG and other common
+//! install locations.
+
+use std::{
+	env, fmt,
+	path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+use tokio::fs;
+use tracing::{info, instrument, warn};
+
+use crate::{Error, Result, prelude::*};
+
+/// The Steam app ID of Exanima.
+pub const STEAM_APP_ID: &str = "362490";
+
+/// The name of the file listing every Steam library folder. This is a child of
+/// `STEAMAPPS_DIR` in the Steam install directory.
+pub const LIBRARY_FOLDERS_VDF: &str = "libraryfolders.vdf";
+
+/// The name of the directory of a Steam library folder containing app manifests
+/// and installed games.
+pub const STEAMAPPS_DIR: &str = "steamapps";
+
+/// The name of the directory inside `STEAMAPPS_DIR` containing installed games.
+pub const STEAM_COMMON_DIR: &str = "common";
+
+/// The name of the directory Exanima is installed to by default.
+pub const GAME_DIR: &str = "Exanima";
+
+/// Where an install of Exanima was discovered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+	Steam,
+	Gog,
+	Common,
+}
+
+impl fmt::Display for Source {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Source::Steam => write!(f, "Steam"),
+			Source::Gog => write!(f, "GOG"),
+			Source::Common => write!(f, "Common"),
+		}
+	}
+}
+
+/// A discovered game directory that can be used with `Instance::with_path`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+	pub path: PathBuf,
+	pub source: Source,
+}
+
+/// Returns the directories Steam is installed to by default.
+#[instrument(level = "trace")]
+pub fn steam_dirs() -> Vec<PathBuf> {
+	let mut dirs = Vec::new();
+	if cfg!(windows) {
+		for var in ["ProgramFiles(x86)", "ProgramFiles"] {
+			if let Some(program_files) = env::var_os(var) {
+				dirs.push(PathBuf::from(program_files).join("Steam"));
+			}
+		}
+	} else if let Some(home_dir) = dirs::home_dir() {
+		dirs.push(home_dir.join(".steam").join("steam"));
+		dirs.push(home_dir.join(".local").join("share").join("Steam"));
+		dirs.push(
+			home_dir
+				.join(".var")
+				.join("app")
+				.join("com.valvesoftware.Steam")
+				.join(".local")
+				.join("share")
+				.join("Steam"),
+		);
+	}
+
+	dirs
+}
+
+/// Returns the directories GOG installs Exanima to by default.
+#[instrument(level = "trace")]
+pub fn gog_dirs() -> Vec<PathBuf> {
+	let mut dirs = Vec::new();
+	if cfg!(windows) {
+		dirs.push(PathBuf::from("C:\\GOG Games").join(GAME_DIR));
+		for var in ["ProgramFiles(x86)", "ProgramFiles"] {
+			if let Some(program_files) = env::var_os(var) {
+				dirs.push(
+					PathBuf::from(program_files)
+						.join("GOG Galaxy")
+						.join("Games")
+						.join(GAME_DIR),
+				);
+			}
+		}
+	} else if let Some(home_dir) = dirs::home_dir() {
+		dirs.push(home_dir.join("GOG Games").join(GAME_DIR));
+		dirs.push(home_dir.join("Games").join("gog").join(GAME_DIR));
+	}
+
+	dirs
+}
+
+/// Returns other directories Exanima is commonly installed to.
+#[instrument(level = "trace")]
+pub fn common_dirs() -> Vec<PathBuf> {
+	let mut dirs = Vec::new();
+	if cfg!(windows) {
+		dirs.push(PathBuf::from("C:\\Games").join(GAME_DIR));
+		if let Some(program_files) = env::var_os("ProgramFiles") {
+			dirs.push(PathBuf::from(program_files).join(GAME_DIR));
+		}
+	} else if let Some(home_dir) = dirs::home_dir() {
+		dirs.push(home_dir.join("Games").join(GAME_DIR));
+	}
+
+	dirs
+}
+
+/// Returns Exanima installs found in the default Steam, GOG and common install
+/// locations.
+///
+/// See [`discover_in`].
+#[instrument(level = "trace")]
+pub async fn discover() -> Vec<Candidate> {
+	discover_in(&steam_dirs(), &gog_dirs(), &common_dirs()).await
+}
+
+/// Returns Exanima installs found in the Steam libraries of `steam_dirs`, and
+/// in `gog_dirs` and `common_dirs`. Only directories containing
+/// `Instance::BINARY` are returned and each install is only returned once.
+#[instrument(level = "trace")]
+pub async fn discover_in(
+	steam_dirs: &[PathBuf],
+	gog_dirs: &[PathBuf],
+	common_dirs: &[PathBuf],
+) -> Vec<Candidate> {
+	let mut maybe_candidates = Vec::new();
+	for steam_dir in steam_dirs.iter().filter(|dir| dir.is_dir()) {
+		for library_dir in steam_library_dirs(steam_dir).await {
+			match steam_install_dir(&library_dir).await {
+				Ok(Some(path)) => maybe_candidates.push((path, Source::Steam)),
+				Ok(None) => (),
+				Err(e) => warn!("{e}"),
+			}
+		}
+	}
+	maybe_candidates.extend(gog_dirs.iter().map(|dir| (dir.clone(), Source::Gog)));
+	maybe_candidates.extend(common_dirs.iter().map(|dir| (dir.clone(), Source::Common)));
+
+	let mut candidates: Vec<Candidate> = Vec::new();
+	for (path, source) in maybe_candidates {
+		if !path.join(Instance::BINARY).is_file() {
+			continue;
+		}
+		let path = fs::canonicalize(&path).await.unwrap_or(path);
+		if candidates.iter().any(|candidate| candidate.path == path) {
+			continue;
+		}
+		info!("found {} install at \"{}\"", source, path.display());
+		candidates.push(Candidate { path, source });
+	}
+
+	candidates
+}
+
+/// Returns the library folders of the Steam install at `steam_dir`, including
+/// the Steam install itself.
+#[instrument(level = "trace")]
+async fn steam_library_dirs(steam_dir: &Path) -> Vec<PathBuf> {
+	let mut library_dirs = vec![steam_dir.to_path_buf()];
+
+	let library_folders_path = steam_dir.join(STEAMAPPS_DIR).join(LIBRARY_FOLDERS_VDF);
+	let Ok(buffer) = fs::read_to_string(&library_folders_path).await else {
+		return library_dirs;
+	};
+	let library_folders = match vdf::parse(&buffer) {
+		Ok(library_folders) => library_folders,
+		Err(e) => {
+			warn!("{e}");
+			return library_dirs;
+		}
+	};
+
+	let folders = library_folders
+		.get("libraryfolders")
+		.and_then(vdf::Value::as_object)
+		.unwrap_or_default();
+	for (key, folder) in folders {
+		// only numbered keys are library folders, others are metadata such as
+		// "TimeNextStatsReport" in the old format
+		if !key.chars().all(|c| c.is_ascii_digit()) {
+			continue;
+		}
+		// old format only stores the path while the new format stores an object
+		let path = match folder {
+			vdf::Value::String(path) => Some(path.as_str()),
+			vdf::Value::Object(_) => folder.get("path").and_then(vdf::Value::as_str),
+		};
+		if let Some(path) = path {
+			let path = PathBuf::from(path);
+			if !library_dirs.contains(&path) {
+				library_dirs.push(path);
+			}
+		}
+	}
+
+	library_dirs
+}
+
+/// Returns a result to the path Exanima is installed to in the Steam library
+/// folder `library_dir` according to its app manifest, or `None` if the game is
+/// not installed to the library.
+///
+/// # Errors
+///
+/// Errors may be returned according to:
+///
+/// - `tokio::fs::read_to_string`
+/// - `vdf::parse`
+#[instrument(level = "trace")]
+async fn steam_install_dir(library_dir: &Path) -> Result<Option<PathBuf>> {
+	let steamapps_dir = library_dir.join(STEAMAPPS_DIR);
+	let app_manifest_path = steamapps_dir.join(format!("appmanifest_{STEAM_APP_ID}.acf"));
+	if !app_manifest_path.is_file() {
+		return Ok(None);
+	}
+
+	let buffer = fs::read_to_string(&app_manifest_path)
+		.await
+		.map_err(Error::msg("failed to read steam app manifest into buffer"))?;
+	let app_manifest = vdf::parse(&buffer)?;
+	let install_dir = app_manifest
+		.get("AppState")
+		.and_then(|app_state| app_state.get("installdir"))
+		.and_then(vdf::Value::as_str)
+		.ok_or(Error::new(
+			anyhow!("install directory is missing"),
+			"failed to read steam app manifest",
+		))?;
+
+	Ok(Some(steamapps_dir.join(STEAM_COMMON_DIR).join(install_dir)))
+}
+
+/// A minimal parser of Valve's text KeyValues format used by `.vdf` and `.acf`
+/// files.
+pub(crate) mod vdf {
+	use std::{iter::Peekable, str::Chars};
+
+	use anyhow::anyhow;
+
+	use crate::{Error, Result};
+
+	#[derive(Debug, Clone, PartialEq, Eq)]
+	pub enum Value {
+		String(String),
+		Object(Vec<(String, Value)>),
+	}
+
+	impl Value {
+		/// Returns the first value of `key`, ignoring case like Steam does.
+		pub fn get(&self, key: &str) -> Option<&Value> {
+			self.as_object()?
+				.iter()
+				.find(|(k, _)| k.eq_ignore_ascii_case(key))
+				.map(|(_, value)| value)
+		}
+
+		pub fn as_str(&self) -> Option<&str> {
+			match self {
+				Value::String(value) => Some(value),
+				Value::Object(_) => None,
+			}
+		}
+
+		pub fn as_object(&self) -> Option<&[(String, Value)]> {
+			match self {
+				Value::String(_) => None,
+				Value::Object(object) => Some(object),
+			}
+		}
+	}
+
+	enum Token {
+		String(String),
+		Open,
+		Close,
+	}
+
+	/// Returns a result to the root object of the KeyValues in `buffer`.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if `buffer` is not valid KeyValues,
+	/// such as an unterminated string or unbalanced braces.
+	pub fn parse(buffer: &str) -> Result<Value> {
+		let mut chars = buffer.chars().peekable();
+		let object = parse_object(&mut chars, false)?;
+		Ok(Value::Object(object))
+	}
+
+	fn parse_object(chars: &mut Peekable<Chars>, nested: bool) -> Result<Vec<(String, Value)>> {
+		let mut object = Vec::new();
+		loop {
+			let key = match next_token(chars)? {
+				Some(Token::String(key)) => key,
+				Some(Token::Close) if nested => return Ok(object),
+				None if !nested => return Ok(object),
+				_ => return Err(invalid("expected key")),
+			};
+			let value = match next_token(chars)? {
+				Some(Token::String(value)) => Value::String(value),
+				Some(Token::Open) => Value::Object(parse_object(chars, true)?),
+				_ => return Err(invalid("expected value")),
+			};
+			object.push((key, value));
+		}
+	}
+
+	fn next_token(chars: &mut Peekable<Chars>) -> Result<Option<Token>> {
+		loop {
+			match chars.next() {
+				None => return Ok(None),
+				Some(c) if c.is_whitespace() => (),
+				Some('/') if chars.peek() == Some(&'/') => {
+					for c in chars.by_ref() {
+						if c == '\n' {
+							break;
+						}
+					}
+				}
+				// conditionals such as [$WIN32] are ignored
+				Some('[') => {
+					for c in chars.by_ref() {
+						if c == ']' {
+							break;
+						}
+					}
+				}
+				Some('{') => return Ok(Some(Token::Open)),
+				Some('}') => return Ok(Some(Token::Close)),
+				Some('"') => {
+					let mut string = String::new();
+					loop {
+						match chars.next() {
+							None => return Err(invalid("unterminated string")),
+							Some('"') => break,
+							Some('\\') => match chars.next() {
+								Some('n') => string.push('\n'),
+								Some('t') => string.push('\t'),
+								Some(c) => string.push(c),
+								None => return Err(invalid("unterminated string")),
+							},
+							Some(c) => string.push(c),
+						}
+					}
+					return Ok(Some(Token::String(string)));
+				}
+				Some(c) => {
+					let mut string = String::from(c);
+					while let Some(&c) = chars.peek() {
+						if c.is_whitespace() || matches!(c, '"' | '{' | '}') {
+							break;
+						}
+						string.push(c);
+						chars.next();
+					}
+					return Ok(Some(Token::String(string)));
+				}
+			}
+		}
+	}
+
+	fn invalid(reason: &'static str) -> Error {
+		Error::new(anyhow!(reason), "failed to parse vdf")
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{fs, path::Path};
+
+	use pretty_assertions::assert_eq;
+	use tempfile::tempdir;
+
+	use crate::{
+		discover::{self, Candidate, Source, vdf},
+		prelude::*,
+	};
+
+	fn dummy_game_dir(path: &Path) {
+		fs::create_dir_all(path).unwrap();
+		fs::write(path.join(Instance::BINARY), "").unwrap();
+	}
+
+	fn dummy_app_manifest(library_dir: &Path, install_dir: &str) {
+		let steamapps_dir = library_dir.join(discover::STEAMAPPS_DIR);
+		fs::create_dir_all(&steamapps_dir).unwrap();
+		fs::write(
+			steamapps_dir.join(format!("appmanifest_{}.acf", discover::STEAM_APP_ID)),
+			format!(
+				"\"AppState\"\n{{\n\t\"appid\"\t\t\"{}\"\n\t\"name\"\t\t\"Exanima\"\n\t\"installdir\"\t\t\"{}\"\n}}\n",
+				discover::STEAM_APP_ID,
+				install_dir
+			),
+		)
+		.unwrap();
+	}
+
+	#[test]
+	fn parse_vdf() {
+		let value = vdf::parse(
+			"// comment\n\"libraryfolders\"\n{\n\t\"0\"\n\t{\n\t\t\"path\"\t\t\"C:\\\\Program Files (x86)\\\\Steam\"\n\t\t\"apps\" { \"362490\" \"123\" }\n\t}\n\tcontentstatsid unquoted\n}\n",
+		)
+		.unwrap();
+		let folder = value.get("LibraryFolders").unwrap().get("0").unwrap();
+		assert_eq!(
+			folder.get("path").unwrap().as_str(),
+			Some("C:\\Program Files (x86)\\Steam")
+		);
+		assert_eq!(
+			folder
+				.get("apps")
+				.unwrap()
+				.get(discover::STEAM_APP_ID)
+				.unwrap()
+				.as_str(),
+			Some("123")
+		);
+		assert_eq!(
+			value
+				.get("libraryfolders")
+				.unwrap()
+				.get("contentstatsid")
+				.unwrap()
+				.as_str(),
+			Some("unquoted")
+		);
+
+		assert!(vdf::parse("\"unterminated").is_err());
+		assert!(vdf::parse("\"key\" { \"value\" ").is_err());
+		assert!(vdf::parse("}").is_err());
+	}
+
+	#[tokio::test]
+	async fn discover_in() {
+		let cwd = tempdir().unwrap();
+		let steam_dir = cwd.path().join("Steam");
+		let library_dir = cwd.path().join("SteamLibrary");
+		let old_library_dir = cwd.path().join("OldSteamLibrary");
+		let gog_dir = cwd.path().join("GOG Games").join("Exanima");
+		let missing_dir = cwd.path().join("Games").join("Exanima");
+
+		// installed to a second library folder listed in the new format
+		fs::create_dir_all(steam_dir.join(discover::STEAMAPPS_DIR)).unwrap();
+		fs::write(
+			steam_dir
+				.join(discover::STEAMAPPS_DIR)
+				.join(discover::LIBRARY_FOLDERS_VDF),
+			format!(
+				"\"libraryfolders\"\n{{\n\t\"0\"\n\t{{\n\t\t\"path\"\t\t\"{}\"\n\t}}\n\t\"1\"\n\t{{\n\t\t\"path\"\t\t\"{}\"\n\t}}\n}}\n",
+				steam_dir.display(),
+				library_dir.display()
+			),
+		)
+		.unwrap();
+		dummy_app_manifest(&library_dir, "Exanima");
+		let steam_game_dir = library_dir
+			.join(discover::STEAMAPPS_DIR)
+			.join(discover::STEAM_COMMON_DIR)
+			.join("Exanima");
+		dummy_game_dir(&steam_game_dir);
+
+		// app manifest without a game executable is not a candidate
+		let other_steam_dir = cwd.path().join("OtherSteam");
+		fs::create_dir_all(other_steam_dir.join(discover::STEAMAPPS_DIR)).unwrap();
+		fs::write(
+			other_steam_dir
+				.join(discover::STEAMAPPS_DIR)
+				.join(discover::LIBRARY_FOLDERS_VDF),
+			format!(
+				"\"LibraryFolders\"\n{{\n\t\"TimeNextStatsReport\"\t\t\"1\"\n\t\"1\"\t\t\"{}\"\n}}\n",
+				old_library_dir.display()
+			),
+		)
+		.unwrap();
+		dummy_app_manifest(&old_library_dir, "Exanima Beta");
+
+		dummy_game_dir(&gog_dir);
+
+		let candidates = discover::discover_in(
+			&[steam_dir, other_steam_dir, cwd.path().join("NoSteam")],
+			&[gog_dir.clone(), steam_game_dir.clone()],
+			&[missing_dir],
+		)
+		.await;
+		assert_eq!(
+			candidates,
+			vec![
+				Candidate {
+					path: steam_game_dir.canonicalize().unwrap(),
+					source: Source::Steam,
+				},
+				Candidate {
+					path: gog_dir.canonicalize().unwrap(),
+					source: Source::Gog,
+				},
+			]
+		);
+
+		dummy_game_dir(
+			&old_library_dir
+				.join(discover::STEAMAPPS_DIR)
+				.join(discover::STEAM_COMMON_DIR)
+				.join("Exanima Beta"),
+		);
+		let candidates = discover::discover_in(&[cwd.path().join("OtherSteam")], &[], &[]).await;
+		assert_eq!(candidates.len(), 1);
+	}
+}
@@ -0,0 +1,349 @@
+//! Identification of the game build of an instance.
+
+use std::{collections::HashMap, fmt::Write as _, fs::File, io, path::Path, time::UNIX_EPOCH};
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::fs;
+use tracing::{info, instrument, warn};
+
+use crate::{Error, Result, prelude::*};
+
+/// The build of the game found in a game directory.
+///
+/// Game builds are identified by the file version from the PE version resource
+/// of `Instance::BINARY` if there is one, and by the SHA-256 hash of
+/// `Instance::BINARY`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct GameVersion {
+	/// File version from the PE version resource of `Instance::BINARY`, e.g.
+	/// "0.9.1.0"
+	pub file_version: Option<String>,
+	/// SHA-256 hash of `Instance::BINARY` in hexadecimal
+	pub executable_hash: String,
+}
+
+impl GameVersion {
+	/// The name of the file responsible for caching the hash of the game's
+	/// executable so it is only hashed again after changing. This is a child of
+	/// `Instance::CACHE_DIR`.
+	pub const CACHE_RON: &str = "game_version.ron";
+
+	/// The amount of hexadecimal characters of `GameVersion::executable_hash` used
+	/// as a short build ID.
+	pub const BUILD_ID_LENGTH: usize = 12;
+
+	/// Returns a short ID of the build derived from the hash of the executable.
+	pub fn build_id(&self) -> &str {
+		self.executable_hash
+			.get(..Self::BUILD_ID_LENGTH)
+			.unwrap_or(&self.executable_hash)
+	}
+
+	/// Returns the file version if there is one, otherwise the build ID.
+	pub fn name(&self) -> &str {
+		self.file_version.as_deref().unwrap_or(self.build_id())
+	}
+
+	/// Returns true if any of the game versions supported by a mod matches this
+	/// game version. A mod that does not declare any supported versions is
+	/// assumed to support every version.
+	///
+	/// A supported version matches if it is:
+	///
+	/// - `*`
+	/// - the file version or a prefix of it, e.g. "0.9" matches "0.9.1.0"
+	/// - a prefix of the executable hash at least `GameVersion::BUILD_ID_LENGTH`
+	///   characters long
+	pub fn is_supported_by<S: AsRef<str>>(&self, supported_versions: &[S]) -> bool {
+		supported_versions.is_empty()
+			|| supported_versions.iter().any(|supported| {
+				let supported = supported.as_ref().trim();
+				supported == "*"
+					|| self.file_version.as_deref().is_some_and(|file_version| {
+						file_version == supported
+							|| file_version
+								.strip_prefix(supported)
+								.is_some_and(|rest| rest.starts_with('.'))
+					})
+					|| (supported.len() >= Self::BUILD_ID_LENGTH
+						&& self
+							.executable_hash
+							.starts_with(&supported.to_ascii_lowercase()))
+			})
+	}
+
+	/// Returns a result to the game version detected from the executable in
+	/// `game_dir`. The hash cached in `cache_path` is reused if the executable has
+	/// not changed, and the cache is updated afterwards.
+	///
+	/// # Errors
+	///
+	/// Errors may be returned according to:
+	///
+	/// - `std::fs::metadata`
+	/// - `std::fs::File::open`
+	#[instrument(level = "trace")]
+	pub async fn detect(game_dir: &Path, cache_path: Option<&Path>) -> Result<Self> {
+		let mut cache: HashMap<String, CachedHash> = match cache_path {
+			Some(cache_path) if cache_path.is_file() => fs::read_to_string(cache_path)
+				.await
+				.ok()
+				.and_then(|buffer| ron::from_str(&buffer).ok())
+				.unwrap_or_default(),
+			_ => HashMap::new(),
+		};
+
+		let game_dir = game_dir.to_path_buf();
+		let (game_version, cache) = tokio::task::spawn_blocking(move || {
+			let executable_path = game_dir.join(Instance::BINARY);
+			let file_version = file_version(&executable_path)
+				.map_err(|e| warn!("failed to read version resource of game executable: {e}"))
+				.ok()
+				.flatten();
+			let executable_hash = cached_hash(&mut cache, &executable_path, Instance::BINARY)?;
+
+			// forget files hashed by earlier versions
+			cache.retain(|name, _| name == Instance::BINARY);
+
+			Ok::<_, Error>((
+				Self {
+					file_version,
+					executable_hash,
+				},
+				cache,
+			))
+		})
+		.await
+		.map_err(|e| Error::new(anyhow!(e), "failed to detect game version"))??;
+		info!("game version detected \"{}\"", game_version.name());
+
+		if let Some(cache_path) = cache_path {
+			let _ = async {
+				let buffer = ron::ser::to_string_pretty(&cache, ron::ser::PrettyConfig::default())
+					.map_err(Error::msg(
+						"failed to serialize game version cache into buffer",
+					))?;
+				crate::write_atomic(cache_path, buffer)
+					.await
+					.map_err(Error::msg(
+						"failed to write game version cache buffer into file",
+					))
+			}
+			.await
+			.map_err(|e| warn!("{e}"));
+		}
+
+		Ok(game_version)
+	}
+}
+
+/// A hash of a file along with the file's metadata at the time of hashing.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+struct CachedHash {
+	len: u64,
+	/// The file's "date modified" metadata in nanoseconds since the unix epoch
+	modified: u64,
+	hash: String,
+}
+
+/// Returns the hash of the file at `path`, reusing the hash in `cache` under
+/// `name` if the file has not changed.
+#[instrument(level = "trace", skip(cache))]
+fn cached_hash(cache: &mut HashMap<String, CachedHash>, path: &Path, name: &str) -> Result<String> {
+	let metadata =
+		std::fs::metadata(path).map_err(Error::msg("failed to read metadata of game file"))?;
+	let modified = metadata
+		.modified()
+		.map_err(Error::msg(
+			"failed to get modified date time metadata of game file",
+		))?
+		.duration_since(UNIX_EPOCH)
+		.map_err(Error::msg(
+			"failed to get the unix epoch timestamp of the game file's modified date time metadata",
+		))?;
+	let modified = u64::try_from(modified.as_nanos()).unwrap_or(u64::MAX);
+
+	if let Some(cached) = cache.get(name)
+		&& cached.len == metadata.len()
+		&& cached.modified == modified
+	{
+		return Ok(cached.hash.clone());
+	}
+
+	let mut file = File::open(path).map_err(Error::msg("failed to open game file"))?;
+	let mut hasher = Sha256::new();
+	io::copy(&mut file, &mut hasher).map_err(Error::msg("failed to hash game file"))?;
+	let hash = hasher
+		.finalize()
+		.iter()
+		.fold(String::new(), |mut hex, byte| {
+			let _ = write!(hex, "{byte:02x}");
+			hex
+		});
+	info!("game file hashed \"{}\"", name);
+
+	cache.insert(
+		name.to_string(),
+		CachedHash {
+			len: metadata.len(),
+			modified,
+			hash: hash.clone(),
+		},
+	);
+	Ok(hash)
+}
+
+/// Returns the file version from the PE version resource of the executable at
+/// `path`, or `None` if the executable does not have one.
+#[instrument(level = "trace")]
+fn file_version(path: &Path) -> Result<Option<String>> {
+	let bytes = std::fs::read(path).map_err(Error::msg("failed to read game executable"))?;
+	pe_file_version(&bytes)
+}
+
+/// Returns the file version from the PE version resource of the executable in
+/// `bytes`, or `None` if the executable does not have one.
+pub(crate) fn pe_file_version(bytes: &[u8]) -> Result<Option<String>> {
+	let pe = pelite::PeFile::from_bytes(bytes)
+		.map_err(anyhow::Error::from)
+		.map_err(Error::msg("failed to parse game executable"))?;
+	let Ok(resources) = pe.resources() else {
+		return Ok(None);
+	};
+	let Ok(version_info) = resources.version_info() else {
+		return Ok(None);
+	};
+
+	Ok(version_info
+		.fixed()
+		.map(|fixed| fixed.dwFileVersion.to_string()))
+}
+
+impl Instance {
+	/// Returns a result to the game version of the instance, detected from the
+	/// files in the game directory. Hashes are cached in `GameVersion::CACHE_RON`.
+	///
+	/// See [`GameVersion::detect`].
+	#[instrument(level = "trace")]
+	pub async fn detect_game_version(&self) -> Result<GameVersion> {
+		let cache_path = self
+			.cache_dir()
+			.await
+			.map_err(Error::msg("failed to create cache directory"))?
+			.join(GameVersion::CACHE_RON);
+		GameVersion::detect(self.path(), Some(&cache_path)).await
+	}
+
+	/// Returns the enabled mods of the current profile that declare supported game
+	/// versions none of which match the instance's game version, along with the
+	/// versions they support. Returns nothing if the game version is unknown.
+	#[instrument(level = "trace")]
+	pub async fn incompatible_mods(&self) -> Vec<(plugin::Id, Vec<String>)> {
+		let Some(game_version) = self.game_version() else {
+			return Vec::new();
+		};
+
+		let enabled_mods = self
+			.profile()
+			.enabled_mods()
+			.await
+			.map_err(|e| warn!("{e}"))
+			.unwrap_or_default();
+		let mut incompatible_mods = Vec::new();
+		for (id, _, manifest) in enabled_mods {
+			let supported_versions = manifest.plugin.supported_versions;
+			if !game_version.is_supported_by(&supported_versions) {
+				incompatible_mods.push((id, supported_versions));
+			}
+		}
+		incompatible_mods.sort_by_key(|(id, _)| id.to_string());
+
+		incompatible_mods
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fs;
+
+	use pretty_assertions::{assert_eq, assert_ne};
+	use tempfile::tempdir;
+
+	use crate::{
+		game::GameVersion, instance::tests::dummy_instance, prelude::*, profile::LoadOrderEntry,
+	};
+
+	fn game_version(file_version: Option<&str>) -> GameVersion {
+		GameVersion {
+			file_version: file_version.map(String::from),
+			executable_hash: "0123456789abcdef0123456789abcdef".into(),
+		}
+	}
+
+	#[test]
+	fn supported_versions() {
+		let version = game_version(Some("0.9.1.0"));
+		assert!(version.is_supported_by::<&str>(&[]));
+		assert!(version.is_supported_by(&["*"]));
+		assert!(version.is_supported_by(&["0.9.1.0"]));
+		assert!(version.is_supported_by(&["0.8", "0.9"]));
+		assert!(version.is_supported_by(&["0123456789AB"]));
+		assert!(!version.is_supported_by(&["0.9.1.1"]));
+		assert!(!version.is_supported_by(&["0.9.1.01"]));
+		assert!(!version.is_supported_by(&["0123"]));
+
+		let version = game_version(None);
+		assert_eq!(version.name(), "0123456789ab");
+		assert!(!version.is_supported_by(&["0.9"]));
+	}
+
+	#[tokio::test]
+	async fn detect_game_version() {
+		let cwd = tempdir().unwrap();
+		let mut instance = dummy_instance(&cwd).await;
+		fs::write(cwd.path().join(Instance::BINARY), "not a pe file").unwrap();
+
+		let version = instance.detect_game_version().await.unwrap();
+		assert_eq!(version.file_version, None);
+		assert_eq!(version.executable_hash.len(), 64);
+		assert!(
+			instance
+				.cache_dir()
+				.await
+				.unwrap()
+				.join(GameVersion::CACHE_RON)
+				.is_file()
+		);
+		assert_eq!(instance.detect_game_version().await.unwrap(), version);
+
+		fs::write(cwd.path().join(Instance::BINARY), "another game build").unwrap();
+		let other_version = instance.detect_game_version().await.unwrap();
+		assert_ne!(other_version.executable_hash, version.executable_hash);
+
+		let id = plugin::Id::try_from("com.example.mod").unwrap();
+		let mod_dir = instance.mods_dir().await.unwrap().join(id.to_string());
+		fs::create_dir_all(&mod_dir).unwrap();
+		fs::write(
+			mod_dir.join(plugin::Manifest::TOML),
+			"[plugin]\nname = \"Example\"\nversion = \"1.0.0\"\nsupported_versions = [\"0.9\"]\n",
+		)
+		.unwrap();
+		let mut load_order = instance.profile().load_order().clone();
+		load_order.insert(id.clone(), LoadOrderEntry::new(true, 0, None, None));
+		instance
+			.profile_mut()
+			.set_load_order(load_order)
+			.await
+			.unwrap();
+
+		instance.game_version = None;
+		assert!(instance.incompatible_mods().await.is_empty());
+		instance.game_version = Some(other_version);
+		assert_eq!(
+			instance.incompatible_mods().await,
+			vec![(id, vec!["0.9".to_string()])]
+		);
+	}
+}
@@ -0,0 +1,956 @@
+//! Installation of mods from archives into an instance's `Instance::MODS_DIR`.
+
+use std::{
+	collections::HashSet,
+	fs::File,
+	io,
+	path::{Component, Path, PathBuf},
+};
+
+use anyhow::anyhow;
+use emtk_asset::{Entry, deku::reader::Reader, read_table};
+use flate2::read::GzDecoder;
+use tokio::fs;
+use tracing::{error, info, instrument, warn};
+
+use crate::{Error, Result, prelude::*, profile::LoadOrderEntry};
+
+/// The kinds of archives a mod can be installed from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+	Zip,
+	TarGz,
+}
+
+impl ArchiveKind {
+	/// The file extensions of each archive kind, including compound extensions.
+	const EXTENSIONS: [(&str, ArchiveKind); 3] = [
+		(".zip", ArchiveKind::Zip),
+		(".tar.gz", ArchiveKind::TarGz),
+		(".tgz", ArchiveKind::TarGz),
+	];
+
+	/// Returns the kind of archive along with the file name of the archive without
+	/// its extension, or `None` if the extension is not supported.
+	#[instrument(level = "trace")]
+	pub fn from_path(path: &Path) -> Option<(Self, String)> {
+		let file_name = path.file_name()?.to_str()?;
+		Self::EXTENSIONS.iter().find_map(|(extension, kind)| {
+			let stem_len = file_name.len().checked_sub(extension.len())?;
+			file_name
+				.get(stem_len..)
+				.is_some_and(|suffix| suffix.eq_ignore_ascii_case(extension))
+				.then(|| (*kind, file_name[..stem_len].to_string()))
+		})
+	}
+}
+
+/// The outcome of installing a mod.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Installed {
+	pub id: plugin::Id,
+	/// Version of the mod that was installed
+	pub version: String,
+	/// Version of the mod that was replaced, if the mod was already installed.
+	/// The replaced version can be restored with [`Instance::rollback_mod`].
+	pub previous_version: Option<String>,
+}
+
+/// The outcome of uninstalling a mod.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Uninstalled {
+	/// Full paths to the profiles the mod was removed from the load order of
+	pub profiles: Vec<PathBuf>,
+	/// Installed mods that depend on the uninstalled mod and are enabled in at
+	/// least one profile
+	pub dependents: Vec<plugin::Id>,
+}
+
+/// Returns true if every component of `path` is a plain name, i.e. `path` can
+/// not point outside of the directory it is joined onto.
+#[instrument(level = "trace")]
+fn is_enclosed(path: &Path) -> bool {
+	path.components()
+		.all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+}
+
+/// Extracts every entry of the zip archive at `archive_path` into `dst`.
+#[instrument(level = "trace")]
+fn extract_zip(archive_path: &Path, dst: &Path) -> Result<()> {
+	let file = File::open(archive_path).map_err(Error::msg("failed to open zip archive"))?;
+	let mut archive = zip::ZipArchive::new(file)
+		.map_err(anyhow::Error::from)
+		.map_err(Error::msg("failed to read zip archive"))?;
+
+	for i in 0..archive.len() {
+		let mut entry = archive
+			.by_index(i)
+			.map_err(anyhow::Error::from)
+			.map_err(Error::msg("failed to read zip archive entry"))?;
+		let Some(entry_path) = entry.enclosed_name().filter(|path| is_enclosed(path)) else {
+			return Err(Error::new(
+				anyhow!("entry \"{}\" escapes the archive", entry.name()),
+				"failed to extract zip archive",
+			));
+		};
+		if entry.is_symlink() {
+			return Err(Error::new(
+				anyhow!("entry \"{}\" is a symbolic link", entry.name()),
+				"failed to extract zip archive",
+			));
+		}
+
+		let out_path = dst.join(entry_path);
+		if entry.is_dir() {
+			std::fs::create_dir_all(&out_path)
+				.map_err(Error::msg("failed to create directory from zip archive"))?;
+			continue;
+		}
+		if let Some(parent) = out_path.parent() {
+			std::fs::create_dir_all(parent)
+				.map_err(Error::msg("failed to create directory from zip archive"))?;
+		}
+		let mut out_file = File::create(&out_path)
+			.map_err(Error::msg("failed to create file from zip archive"))?;
+		io::copy(&mut entry, &mut out_file)
+			.map_err(Error::msg("failed to write file from zip archive"))?;
+	}
+
+	Ok(())
+}
+
+/// Extracts every entry of the gzip compressed tar archive at `archive_path`
+/// into `dst`.
+#[instrument(level = "trace")]
+fn extract_tar_gz(archive_path: &Path, dst: &Path) -> Result<()> {
+	let file = File::open(archive_path).map_err(Error::msg("failed to open tar archive"))?;
+	let mut archive = tar::Archive::new(GzDecoder::new(file));
+
+	for entry in archive
+		.entries()
+		.map_err(Error::msg("failed to read tar archive"))?
+	{
+		let mut entry = entry.map_err(Error::msg("failed to read tar archive entry"))?;
+		let entry_path = entry
+			.path()
+			.map_err(Error::msg("failed to read path of tar archive entry"))?
+			.into_owned();
+		if !is_enclosed(&entry_path) {
+			return Err(Error::new(
+				anyhow!("entry \"{}\" escapes the archive", entry_path.display()),
+				"failed to extract tar archive",
+			));
+		}
+
+		let out_path = dst.join(&entry_path);
+		match entry.header().entry_type() {
+			// pax headers only hold metadata of the archive or the next entry
+			tar::EntryType::XGlobalHeader | tar::EntryType::XHeader => continue,
+			tar::EntryType::Directory => {
+				std::fs::create_dir_all(&out_path)
+					.map_err(Error::msg("failed to create directory from tar archive"))?;
+			}
+			tar::EntryType::Regular => {
+				if let Some(parent) = out_path.parent() {
+					std::fs::create_dir_all(parent)
+						.map_err(Error::msg("failed to create directory from tar archive"))?;
+				}
+				entry
+					.unpack(&out_path)
+					.map_err(Error::msg("failed to write file from tar archive"))?;
+			}
+			entry_type => {
+				return Err(Error::new(
+					anyhow!(
+						"entry \"{}\" is an unsupported type {:?}",
+						entry_path.display(),
+						entry_type
+					),
+					"failed to extract tar archive",
+				));
+			}
+		}
+	}
+
+	Ok(())
+}
+
+/// Returns the paths to the directories directly containing a
+/// `plugin::Manifest::TOML` file that are the least nested within `dir`.
+#[instrument(level = "trace")]
+fn find_mod_roots(dir: &Path) -> io::Result<Vec<PathBuf>> {
+	let mut level = vec![dir.to_path_buf()];
+	while !level.is_empty() {
+		let roots: Vec<_> = level
+			.iter()
+			.filter(|path| path.join(plugin::Manifest::TOML).is_file())
+			.cloned()
+			.collect();
+		if !roots.is_empty() {
+			return Ok(roots);
+		}
+
+		let mut next_level = Vec::new();
+		for path in level {
+			for entry in std::fs::read_dir(path)? {
+				let entry_path = entry?.path();
+				if entry_path.is_dir() {
+					next_level.push(entry_path);
+				}
+			}
+		}
+		level = next_level;
+	}
+
+	Ok(Vec::new())
+}
+
+/// Unpacks each package file directly inside of `packages_dir`, such as the
+/// `Resource.rpk` built by [`crate::pack::pack_mod`], into a directory of loose
+/// files named after it, such as `Resource`. Nested packages are kept as files.
+#[instrument(level = "trace")]
+fn unpack_packages(packages_dir: &Path) -> Result<()> {
+	for entry in std::fs::read_dir(packages_dir)
+		.map_err(Error::msg("failed to read mod packages directory entries"))?
+	{
+		let package_path = entry
+			.map_err(Error::msg(
+				"failed to read next entry in mod packages directory",
+			))?
+			.path();
+		if !package_path.is_file()
+			|| !package_path
+				.extension()
+				.is_some_and(|extension| extension.eq_ignore_ascii_case("rpk"))
+		{
+			continue;
+		}
+		let invalid = |reason: String| {
+			Error::new(
+				anyhow!("{reason} in \"{}\"", package_path.display()),
+				"failed to unpack package",
+			)
+		};
+
+		let bytes = std::fs::read(&package_path).map_err(Error::msg("failed to read package"))?;
+		let mut cursor = io::Cursor::new(bytes.as_slice());
+		let mut reader = Reader::new(&mut cursor);
+		let table = read_table(&mut reader).map_err(|e| invalid(e.to_string()))?;
+		let table_byte_length = table.table_byte_length();
+		let entries = table
+			.collect::<std::result::Result<Vec<Entry>, _>>()
+			.map_err(|e| invalid(e.to_string()))?;
+
+		let out_dir = package_path.with_extension("");
+		std::fs::create_dir_all(&out_dir).map_err(Error::msg(
+			"failed to create directory for unpacked package",
+		))?;
+		for entry in entries {
+			let mut components = Path::new(&*entry.name).components();
+			if !matches!(
+				(components.next(), components.next()),
+				(Some(Component::Normal(_)), None)
+			) {
+				return Err(invalid(format!(
+					"entry \"{}\" is not a valid file name",
+					entry.name
+				)));
+			}
+			// the magic and table byte length precede the table
+			let asset = (table_byte_length as usize)
+				.checked_add(8 + entry.byte_offset as usize)
+				.and_then(|start| Some((start, start.checked_add(entry.byte_length as usize)?)))
+				.and_then(|(start, end)| bytes.get(start..end))
+				.ok_or_else(|| invalid(format!("entry \"{}\" is out of bounds", entry.name)))?;
+			std::fs::write(out_dir.join(&*entry.name), asset)
+				.map_err(Error::msg("failed to write file from package"))?;
+		}
+		std::fs::remove_file(&package_path)
+			.map_err(Error::msg("failed to remove unpacked package"))?;
+		info!("package unpacked \"{}\"", package_path.display());
+	}
+
+	Ok(())
+}
+
+impl Instance {
+	/// The name of the directory responsible for temporarily storing extracted
+	/// archives during mod installation. This is a child of `Instance::DATA_DIR`.
+	pub const INSTALL_DIR: &str = "install";
+
+	/// The name of the directory responsible for storing the previous version of
+	/// upgraded mods. This is a child of `Instance::DATA_DIR`.
+	pub const ROLLBACK_DIR: &str = "rollback";
+
+	/// The suffix of the directory in `Instance::ROLLBACK_DIR` the installed
+	/// version of a mod is moved to while it is swapped with the previous one.
+	/// Mod ids cannot contain `~`, so it never collides with a kept version.
+	pub const ROLLBACK_SWAP_SUFFIX: &str = "~swap";
+
+	#[instrument(level = "trace")]
+	pub async fn rollback_dir(&self) -> io::Result<PathBuf> {
+		let dir = self.data_dir().await?.join(Self::ROLLBACK_DIR);
+		crate::ensure_dir(&dir).await?;
+		Ok(dir)
+	}
+
+	/// Returns a result to the outcome of installing the mod contained in the zip
+	/// or gzip compressed tar archive at `archive_path`.
+	///
+	/// The mod's root is the least nested directory of the archive containing a
+	/// `plugin::Manifest::TOML` file. The mod's id is the name of that directory
+	/// or, if the manifest is at the top of the archive, the archive's file name
+	/// without its extension. Package files directly inside of the mod's
+	/// `Instance::PACKAGES_DIR`, as prebuilt by [`crate::pack::pack_mod`], are
+	/// unpacked into loose files.
+	///
+	/// If the mod is already installed, the installed version is kept in
+	/// `Instance::ROLLBACK_DIR` and can be restored with
+	/// [`Instance::rollback_mod`]. The mod is added to the current profile's load
+	/// order as enabled if it was not already in the load order.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if:
+	///
+	/// - the archive is not a zip or gzip compressed tar archive
+	/// - any entry of the archive would be extracted outside of the mod's
+	///   directory or is a link
+	/// - the archive contains no manifest, more than one mod or an invalid
+	///   manifest
+	/// - the mod's id is not a valid `plugin::Id`
+	///
+	/// Errors may also be returned according to:
+	///
+	/// - `Profile::set_load_order`
+	/// - `tokio::fs::rename`
+	#[instrument(level = "trace")]
+	pub async fn install_mod<P: AsRef<Path> + std::fmt::Debug>(
+		&mut self,
+		archive_path: P,
+	) -> Result<Installed> {
+		let archive_path = archive_path.as_ref().to_path_buf();
+		let Some((kind, archive_stem)) = ArchiveKind::from_path(&archive_path) else {
+			return Err(Error::new(
+				anyhow!(
+					"\"{}\" is not a zip or tar.gz archive",
+					archive_path.display()
+				),
+				"failed to install mod",
+			));
+		};
+
+		let staging_dir = self
+			.data_dir()
+			.await
+			.map_err(Error::msg("failed to create instance data directory"))?
+			.join(Self::INSTALL_DIR);
+		if staging_dir.exists() {
+			fs::remove_dir_all(&staging_dir)
+				.await
+				.map_err(Error::msg("failed to clear mod installation directory"))?;
+		}
+		crate::ensure_dir(&staging_dir)
+			.await
+			.map_err(Error::msg("failed to create mod installation directory"))?;
+
+		let result = self
+			.install_staged(&archive_path, kind, &archive_stem, &staging_dir)
+			.await;
+		if staging_dir.exists() {
+			let _ = fs::remove_dir_all(&staging_dir)
+				.await
+				.map_err(|e| warn!("failed to remove mod installation directory: {e}"));
+		}
+
+		result
+	}
+
+	#[instrument(level = "trace")]
+	async fn install_staged(
+		&mut self,
+		archive_path: &Path,
+		kind: ArchiveKind,
+		archive_stem: &str,
+		staging_dir: &Path,
+	) -> Result<Installed> {
+		let extract_path = archive_path.to_path_buf();
+		let extract_dir = staging_dir.to_path_buf();
+		tokio::task::spawn_blocking(move || match kind {
+			ArchiveKind::Zip => extract_zip(&extract_path, &extract_dir),
+			ArchiveKind::TarGz => extract_tar_gz(&extract_path, &extract_dir),
+		})
+		.await
+		.map_err(|e| Error::new(anyhow!(e), "failed to extract archive"))??;
+		info!("archive extracted");
+
+		let find_dir = staging_dir.to_path_buf();
+		let mut mod_roots = tokio::task::spawn_blocking(move || find_mod_roots(&find_dir))
+			.await
+			.map_err(|e| Error::new(anyhow!(e), "failed to find mod in archive"))?
+			.map_err(Error::msg("failed to find mod in archive"))?;
+		let mod_root = match (mod_roots.pop(), mod_roots.is_empty()) {
+			(Some(mod_root), true) => mod_root,
+			(Some(_), false) => {
+				return Err(Error::new(
+					anyhow!("archive contains more than one mod"),
+					"failed to install mod",
+				));
+			}
+			(None, _) => {
+				return Err(Error::new(
+					anyhow!("archive does not contain a {}", plugin::Manifest::TOML),
+					"failed to install mod",
+				));
+			}
+		};
+
+		let manifest = plugin::Manifest::read(&mod_root.join(plugin::Manifest::TOML)).await?;
+		info!("mod manifest found in archive");
+
+		let packages_dir = mod_root
+			.join(Instance::ASSETS_DIR)
+			.join(Instance::PACKAGES_DIR);
+		if packages_dir.is_dir() {
+			tokio::task::spawn_blocking(move || unpack_packages(&packages_dir))
+				.await
+				.map_err(|e| Error::new(anyhow!(e), "failed to unpack packages"))??;
+		}
+
+		let id_str = if mod_root == staging_dir {
+			archive_stem.to_string()
+		} else {
+			mod_root
+				.file_name()
+				.map(|name| name.display().to_string())
+				.unwrap_or_default()
+		};
+		let id = plugin::Id::try_from(id_str.as_str())
+			.map_err(Error::msg("failed to derive mod id from archive"))?;
+
+		self.mods_dir()
+			.await
+			.map_err(Error::msg("failed to create mods directory"))?;
+		let mod_dir = self.path().join(id.plugin_dir());
+		let mut previous_version = None;
+		if mod_dir.exists() {
+			previous_version = fs::read_to_string(mod_dir.join(plugin::Manifest::TOML))
+				.await
+				.ok()
+				.and_then(|buffer| toml::from_str::<plugin::Manifest>(&buffer).ok())
+				.map(|manifest| manifest.plugin.version);
+
+			let rollback_path = self
+				.rollback_dir()
+				.await
+				.map_err(Error::msg("failed to create rollback directory"))?
+				.join(id.to_string());
+			if rollback_path.exists() {
+				fs::remove_dir_all(&rollback_path)
+					.await
+					.map_err(Error::msg("failed to remove previous rollback of mod"))?;
+			}
+			fs::rename(&mod_dir, &rollback_path)
+				.await
+				.map_err(Error::msg(
+					"failed to move installed mod into rollback directory",
+				))?;
+			info!("installed mod moved into rollback directory \"{}\"", id);
+		}
+		fs::rename(&mod_root, &mod_dir)
+			.await
+			.map_err(Error::msg("failed to move mod into mods directory"))?;
+		info!("mod installed \"{}\"", id);
+
+		self.add_to_load_order(&id, &manifest).await?;
+
+		Ok(Installed {
+			id,
+			version: manifest.plugin.version,
+			previous_version,
+		})
+	}
+
+	/// Returns a result to the version of the mod restored from
+	/// `Instance::ROLLBACK_DIR`. The version being replaced is kept in its place so
+	/// the rollback can be undone by calling this again. If moving either version
+	/// fails, the moves made so far are undone before returning the error.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if there is no previous version of the
+	/// mod to restore.
+	///
+	/// Errors may also be returned according to:
+	///
+	/// - `Profile::set_load_order`
+	/// - `tokio::fs::remove_dir_all`
+	/// - `tokio::fs::rename`
+	#[instrument(level = "trace")]
+	pub async fn rollback_mod(&mut self, id: &plugin::Id) -> Result<String> {
+		let rollback_dir = self
+			.rollback_dir()
+			.await
+			.map_err(Error::msg("failed to create rollback directory"))?;
+		let rollback_path = rollback_dir.join(id.to_string());
+		if !rollback_path.is_dir() {
+			return Err(Error::new(
+				anyhow!("no previous version of \"{}\" to restore", id),
+				"failed to rollback mod",
+			));
+		}
+
+		let manifest = plugin::Manifest::read(&rollback_path.join(plugin::Manifest::TOML)).await?;
+
+		let mod_dir = self.path().join(id.plugin_dir());
+		let swap_path = rollback_dir.join(format!("{id}{}", Self::ROLLBACK_SWAP_SUFFIX));
+		if swap_path.exists() {
+			if mod_dir.exists() {
+				fs::remove_dir_all(&swap_path).await.map_err(Error::msg(
+					"failed to clear previous rollback swap directory",
+				))?;
+			} else {
+				warn!("restoring \"{}\" left behind by a failed rollback", id);
+				fs::rename(&swap_path, &mod_dir).await.map_err(Error::msg(
+					"failed to restore mod left behind by a failed rollback",
+				))?;
+			}
+		}
+
+		if mod_dir.exists() {
+			let swaps = [
+				(
+					mod_dir.as_path(),
+					swap_path.as_path(),
+					"failed to move installed mod out of mods directory",
+				),
+				(
+					rollback_path.as_path(),
+					mod_dir.as_path(),
+					"failed to move previous version into mods directory",
+				),
+				(
+					swap_path.as_path(),
+					rollback_path.as_path(),
+					"failed to move installed mod into rollback directory",
+				),
+			];
+			for (i, (from, to, msg)) in swaps.iter().enumerate() {
+				if let Err(e) = fs::rename(from, to).await {
+					for (from, to, _) in swaps[..i].iter().rev() {
+						if let Err(e) = fs::rename(to, from).await {
+							error!(
+								"failed to undo moving \"{}\" to \"{}\": {e}",
+								from.display(),
+								to.display()
+							);
+						}
+					}
+					return Err(Error::new(e, *msg));
+				}
+			}
+		} else {
+			fs::rename(&rollback_path, &mod_dir)
+				.await
+				.map_err(Error::msg(
+					"failed to move previous version into mods directory",
+				))?;
+		}
+		info!("mod rolled back \"{}\"", id);
+
+		self.add_to_load_order(id, &manifest).await?;
+
+		Ok(manifest.plugin.version)
+	}
+
+	/// Returns a result to the outcome of uninstalling the mod. The mod's directory
+	/// and any previous version kept for rollback are removed, then the mod is
+	/// removed from the load order of every profile of the instance. The cache
+	/// build of each profile that had the mod in its load order is removed so it
+	/// will be rebuilt on the next launch.
+	///
+	/// Mods that depend on the uninstalled mod are not disabled, instead a warning
+	/// is logged and they are returned in [`Uninstalled::dependents`]. The mod's
+	/// directory is not required to exist so stale load order entries of a mod
+	/// that was removed by hand can also be cleaned up.
+	///
+	/// # Errors
+	///
+	/// Errors may be returned according to:
+	///
+	/// - `Instance::profile_dirs`
+	/// - `Profile::set_load_order`
+	/// - `tokio::fs::remove_dir_all`
+	#[instrument(level = "trace")]
+	pub async fn uninstall_mod(&mut self, id: &plugin::Id) -> Result<Uninstalled> {
+		let mod_dir = self.path().join(id.plugin_dir());
+		if mod_dir.exists() {
+			fs::remove_dir_all(&mod_dir)
+				.await
+				.map_err(Error::msg("failed to remove mod directory"))?;
+			info!("mod directory removed \"{}\"", id);
+		}
+		let rollback_path = self
+			.path()
+			.join(Self::DATA_DIR)
+			.join(Self::ROLLBACK_DIR)
+			.join(id.to_string());
+		if rollback_path.exists() {
+			fs::remove_dir_all(&rollback_path)
+				.await
+				.map_err(Error::msg("failed to remove previous version of mod"))?;
+			info!("previous version of mod removed \"{}\"", id);
+		}
+
+		let mut uninstalled = Uninstalled::default();
+		let mut enabled_mods = HashSet::new();
+		for profile_dir in self
+			.profile_dirs()
+			.await
+			.map_err(Error::msg("failed to read profile directories"))?
+		{
+			let mut profile = Profile::with_path(&profile_dir).await?.build().await?;
+			let mut load_order = profile.load_order().clone();
+			if load_order.remove(id).is_some() {
+				// ensure removal of gaps in load order priority
+				let mut entries: Vec<_> = load_order.values_mut().collect();
+				entries.sort_by_key(|entry| entry.priority);
+				for (i, entry) in entries.into_iter().enumerate() {
+					entry.priority = i as _;
+				}
+				profile.set_load_order(load_order).await?;
+
+				let cache_build_dir = profile
+					.path()
+					.join(Profile::CACHE_DIR)
+					.join(Profile::CACHE_BUILD_DIR);
+				if cache_build_dir.exists() {
+					fs::remove_dir_all(&cache_build_dir)
+						.await
+						.map_err(Error::msg(
+							"failed to remove profile's cache build directory",
+						))?;
+				}
+				info!(
+					"mod removed from profile's load order \"{}\"",
+					profile.path().display()
+				);
+
+				if profile.path() == self.profile().path() {
+					self.profile = profile.clone();
+				}
+				uninstalled.profiles.push(profile.path().clone());
+			}
+
+			enabled_mods.extend(
+				profile
+					.load_order()
+					.iter()
+					.filter(|(_, entry)| entry.enabled)
+					.map(|(id, _)| id.clone()),
+			);
+		}
+
+		for dependent in enabled_mods {
+			let Ok(buffer) = fs::read_to_string(self.path().join(dependent.manifest_file())).await
+			else {
+				continue;
+			};
+			let Ok(manifest) = toml::from_str::<plugin::Manifest>(&buffer) else {
+				continue;
+			};
+			let depends = manifest.dependencies.is_some_and(|dependencies| {
+				dependencies
+					.keys()
+					.any(|dependency| dependency.to_string().eq_ignore_ascii_case(&id.to_string()))
+			});
+			if depends {
+				warn!(
+					"enabled mod \"{}\" depends on uninstalled mod \"{}\"",
+					dependent, id
+				);
+				uninstalled.dependents.push(dependent);
+			}
+		}
+		uninstalled
+			.dependents
+			.sort_by_key(|dependent| dependent.to_string());
+
+		Ok(uninstalled)
+	}
+
+	/// Adds the mod to the end of the current profile's load order as enabled, or
+	/// updates the mod's display name and version if it is already in the load
+	/// order.
+	#[instrument(level = "trace")]
+	async fn add_to_load_order(
+		&mut self,
+		id: &plugin::Id,
+		manifest: &plugin::Manifest,
+	) -> Result<()> {
+		let mut load_order = self.profile.load_order().clone();
+		let priority = load_order.len() as u32;
+		let entry = load_order
+			.entry(id.clone())
+			.or_insert_with(|| LoadOrderEntry::new(true, priority, None, None));
+		entry.display_name = Some(manifest.plugin.name.clone());
+		entry.version = Some(manifest.plugin.version.clone());
+		self.profile.set_load_order(load_order).await?;
+		info!("mod added to load order \"{}\"", id);
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{fs, io::Write, path::Path};
+
+	use flate2::{Compression, write::GzEncoder};
+	use pretty_assertions::assert_eq;
+	use tempfile::tempdir;
+
+	use crate::{instance::tests::dummy_instance, prelude::*};
+
+	fn manifest(version: &str) -> String {
+		format!("[plugin]\nname = \"Example\"\nversion = \"{version}\"\n")
+	}
+
+	fn zip_archive(path: &Path, files: &[(&str, &str)]) {
+		let mut writer = zip::ZipWriter::new(fs::File::create(path).unwrap());
+		for (name, contents) in files {
+			writer
+				.start_file(*name, zip::write::SimpleFileOptions::default())
+				.unwrap();
+			writer.write_all(contents.as_bytes()).unwrap();
+		}
+		writer.finish().unwrap();
+	}
+
+	fn tar_gz_archive(path: &Path, files: &[(&str, &str)]) {
+		let encoder = GzEncoder::new(fs::File::create(path).unwrap(), Compression::default());
+		let mut builder = tar::Builder::new(encoder);
+		// archives written by GNU tar and bsdtar often start with a pax header
+		let pax = b"23 comment=example mod\n";
+		let mut header = tar::Header::new_ustar();
+		header.set_entry_type(tar::EntryType::XGlobalHeader);
+		header.set_size(pax.len() as u64);
+		header.set_cksum();
+		builder
+			.append_data(&mut header, "pax_global_header", &pax[..])
+			.unwrap();
+		for (name, contents) in files {
+			let mut header = tar::Header::new_gnu();
+			header.set_size(contents.len() as u64);
+			header.set_mode(0o644);
+			header.set_cksum();
+			builder
+				.append_data(&mut header, name, contents.as_bytes())
+				.unwrap();
+		}
+		builder.into_inner().unwrap().finish().unwrap();
+	}
+
+	#[tokio::test]
+	async fn install_mod() {
+		let cwd = tempdir().unwrap();
+		let archives = tempdir().unwrap();
+		let mut instance = dummy_instance(&cwd).await;
+		let id = plugin::Id::try_from("com.example.mod").unwrap();
+
+		let archive_path = archives.path().join("Example Mod v1.zip");
+		zip_archive(
+			&archive_path,
+			&[
+				("com.example.mod/manifest.toml", &manifest("1.0.0")),
+				("com.example.mod/assets/file.txt", "v1"),
+			],
+		);
+		let installed = instance.install_mod(&archive_path).await.unwrap();
+		assert_eq!(installed.id, id);
+		assert_eq!(installed.previous_version, None);
+		assert!(
+			instance
+				.path()
+				.join(id.assets_dir())
+				.join("file.txt")
+				.is_file()
+		);
+		let entry = instance.profile().load_order().get(&id).unwrap();
+		assert!(entry.enabled);
+		assert_eq!(entry.version.as_deref(), Some("1.0.0"));
+
+		// manifest at the top of the archive derives the id from the file name
+		let archive_path = archives.path().join("com.example.mod.tar.gz");
+		tar_gz_archive(
+			&archive_path,
+			&[
+				("manifest.toml", &manifest("2.0.0")),
+				("assets/file.txt", "v2"),
+			],
+		);
+		let installed = instance.install_mod(&archive_path).await.unwrap();
+		assert_eq!(installed.version, "2.0.0");
+		assert_eq!(installed.previous_version.as_deref(), Some("1.0.0"));
+		assert_eq!(instance.profile().load_order().len(), 1);
+		assert_eq!(
+			fs::read_to_string(instance.path().join(id.assets_dir()).join("file.txt")).unwrap(),
+			"v2"
+		);
+
+		assert_eq!(instance.rollback_mod(&id).await.unwrap(), "1.0.0");
+		assert_eq!(
+			fs::read_to_string(instance.path().join(id.assets_dir()).join("file.txt")).unwrap(),
+			"v1"
+		);
+		assert_eq!(instance.rollback_mod(&id).await.unwrap(), "2.0.0");
+
+		// a swap directory left behind by a failed rollback is cleared
+		let rollback_dir = instance.rollback_dir().await.unwrap();
+		let swap_path = rollback_dir.join(format!("{id}{}", Instance::ROLLBACK_SWAP_SUFFIX));
+		fs::create_dir_all(swap_path.join("assets")).unwrap();
+		assert_eq!(instance.rollback_mod(&id).await.unwrap(), "1.0.0");
+		assert!(!swap_path.exists());
+		assert_eq!(
+			fs::read_to_string(
+				rollback_dir
+					.join(id.to_string())
+					.join("assets")
+					.join("file.txt")
+			)
+			.unwrap(),
+			"v2"
+		);
+
+		// the installed version left in the swap directory is restored
+		fs::rename(instance.path().join(id.plugin_dir()), &swap_path).unwrap();
+		assert_eq!(instance.rollback_mod(&id).await.unwrap(), "2.0.0");
+		assert!(!swap_path.exists());
+		assert_eq!(
+			fs::read_to_string(instance.path().join(id.assets_dir()).join("file.txt")).unwrap(),
+			"v2"
+		);
+	}
+
+	#[tokio::test]
+	async fn uninstall_mod() {
+		let cwd = tempdir().unwrap();
+		let archives = tempdir().unwrap();
+		let mut instance = dummy_instance(&cwd).await;
+		let first = plugin::Id::try_from("com.example.first").unwrap();
+		let second = plugin::Id::try_from("com.example.second").unwrap();
+
+		let archive_path = archives.path().join("first.zip");
+		zip_archive(
+			&archive_path,
+			&[("com.example.first/manifest.toml", &manifest("1.0.0"))],
+		);
+		instance.install_mod(&archive_path).await.unwrap();
+		let archive_path = archives.path().join("second.zip");
+		zip_archive(
+			&archive_path,
+			&[(
+				"com.example.second/manifest.toml",
+				&format!(
+					"{}[dependencies.\"com.example.first\"]\nVersion = \"1.0.0\"\n",
+					manifest("1.0.0")
+				),
+			)],
+		);
+		instance.install_mod(&archive_path).await.unwrap();
+		let other_profile = instance.create_profile("Other").await.unwrap();
+		assert!(other_profile.load_order().contains_key(&first));
+		let cache_build_dir = instance.profile().cache_build_dir().await.unwrap();
+
+		let uninstalled = instance.uninstall_mod(&first).await.unwrap();
+		assert_eq!(uninstalled.profiles.len(), 2);
+		assert_eq!(uninstalled.dependents, vec![second.clone()]);
+		assert!(!instance.path().join(first.plugin_dir()).exists());
+		assert!(!cache_build_dir.exists());
+		assert!(!instance.profile().load_order().contains_key(&first));
+		assert_eq!(
+			instance
+				.profile()
+				.load_order()
+				.get(&second)
+				.unwrap()
+				.priority,
+			0
+		);
+		let other_profile = Profile::with_path(other_profile.path())
+			.await
+			.unwrap()
+			.build()
+			.await
+			.unwrap();
+		assert!(!other_profile.load_order().contains_key(&first));
+	}
+
+	#[tokio::test]
+	async fn install_mod_refused() {
+		let cwd = tempdir().unwrap();
+		let archives = tempdir().unwrap();
+		let mut instance = dummy_instance(&cwd).await;
+
+		let archive_path = archives.path().join("traversal.zip");
+		zip_archive(
+			&archive_path,
+			&[
+				("com.example.mod/manifest.toml", &manifest("1.0.0")),
+				("../escaped.txt", "escaped"),
+			],
+		);
+		assert!(instance.install_mod(&archive_path).await.is_err());
+		assert!(!cwd.path().join("escaped.txt").exists());
+
+		let archive_path = archives.path().join("no-manifest.zip");
+		zip_archive(&archive_path, &[("com.example.mod/file.txt", "")]);
+		assert!(instance.install_mod(&archive_path).await.is_err());
+
+		let archive_path = archives.path().join("two-mods.zip");
+		zip_archive(
+			&archive_path,
+			&[
+				("com.example.first/manifest.toml", &manifest("1.0.0")),
+				("com.example.second/manifest.toml", &manifest("1.0.0")),
+			],
+		);
+		assert!(instance.install_mod(&archive_path).await.is_err());
+
+		let archive_path = archives.path().join("com.example.mod.tar.gz");
+		let encoder = GzEncoder::new(
+			fs::File::create(&archive_path).unwrap(),
+			Compression::default(),
+		);
+		let mut builder = tar::Builder::new(encoder);
+		let contents = manifest("1.0.0");
+		let mut header = tar::Header::new_gnu();
+		header.set_size(contents.len() as u64);
+		header.set_mode(0o644);
+		header.set_cksum();
+		builder
+			.append_data(&mut header, "manifest.toml", contents.as_bytes())
+			.unwrap();
+		let mut header = tar::Header::new_gnu();
+		header.set_entry_type(tar::EntryType::Symlink);
+		header.set_size(0);
+		builder
+			.append_link(&mut header, "assets/link", "/etc/passwd")
+			.unwrap();
+		builder.into_inner().unwrap().finish().unwrap();
+		assert!(instance.install_mod(&archive_path).await.is_err());
+
+		let archive_path = archives.path().join("com.example.mod.rar");
+		fs::write(&archive_path, "").unwrap();
+		assert!(instance.install_mod(&archive_path).await.is_err());
+
+		assert!(instance.profile().load_order().is_empty());
+		assert!(
+			!instance
+				.path()
+				.join(Instance::MODS_DIR)
+				.join("com.example.mod")
+				.exists()
+		);
+	}
+}
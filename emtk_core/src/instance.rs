@@ -1,27 +1,94 @@
-use std::{fmt, path::PathBuf, process};
-
+use std::{
+	fmt,
+	path::{Component, Path, PathBuf},
+	sync::Arc,
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::anyhow;
 use bon::Builder;
 use getset::{Getters, MutGetters};
 use serde::{Deserialize, Serialize};
 use tokio::{fs, io};
 use tracing::{error, info, instrument, warn};
 
-use crate::{Error, Result, TomlError, prelude::*};
+use crate::{Error, ErrorKind, Result, TomlError, game::GameVersion, lock::Lock, prelude::*};
 
 pub mod prelude {
 	pub use crate::instance::{self, Instance};
 }
 
-pub type InstanceHistory = Vec<PathBuf>;
+/// A record of an instance that was opened, stored in `Instance::HISTORY_CACHE_RON`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+	/// Full path to the instance
+	pub path: PathBuf,
+	/// When the instance was last opened in seconds since the unix epoch
+	#[serde(default)]
+	pub last_opened: u64,
+	/// Display name of the instance cached from `Instance::TOML`
+	#[serde(default)]
+	pub name: Option<String>,
+	/// Pinned entries are listed first and are kept when pruning
+	#[serde(default)]
+	pub pinned: bool,
+	/// Whether `Instance::BINARY` could not be found at the path when the history
+	/// was read
+	#[serde(skip)]
+	pub missing: bool,
+}
+
+impl HistoryEntry {
+	#[instrument(level = "trace")]
+	pub fn new(path: PathBuf, name: Option<String>) -> Self {
+		Self {
+			path,
+			last_opened: unix_now(),
+			name,
+			pinned: false,
+			missing: false,
+		}
+	}
+}
+
+/// History of opened instances ordered from least to most recently opened.
+pub type InstanceHistory = Vec<HistoryEntry>;
 
-/// Return a result to the instance history
 #[instrument(level = "trace")]
-pub async fn history() -> Result<InstanceHistory> {
-	let history_file_path = crate::cache_dir()
+fn unix_now() -> u64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map(|duration| duration.as_secs())
+		.unwrap_or_default()
+}
+
+#[instrument(level = "trace")]
+async fn history_file_path() -> Result<PathBuf> {
+	Ok(crate::cache_dir()
 		.await
 		.map_err(Error::msg("failed to create cache directory"))?
-		.join(Instance::HISTORY_CACHE_RON);
+		.join(Instance::HISTORY_CACHE_RON))
+}
 
+/// Return a result to the instance history
+///
+/// # Errors
+///
+/// Errors may be returned according to:
+///
+/// - `emtk_core::cache_dir`
+/// - `tokio::fs::read_to_string`
+/// - `ron::from_str`
+#[instrument(level = "trace")]
+pub async fn history() -> Result<InstanceHistory> {
+	read_history(&history_file_path().await?).await
+}
+
+/// Returns a result to the instance history in `history_file_path`. A history in
+/// the old format of a list of paths is migrated and written back in the current
+/// format.
+#[instrument(level = "trace")]
+async fn read_history(history_file_path: &Path) -> Result<InstanceHistory> {
 	if !history_file_path.is_file() {
 		fs::File::create_new(&history_file_path)
 			.await
@@ -34,35 +101,189 @@ pub async fn history() -> Result<InstanceHistory> {
 			"failed to read into buffer for instance history",
 		))?;
 	info!("instance history file read into buffer");
-	let instance_history = ron::from_str(&buffer)
-		.map_err(ron::Error::from)
-		.map_err(Error::msg(
-			"failed to deserialize instance history from buffer",
-		))?;
+	let mut instance_history: InstanceHistory = if buffer.trim().is_empty() {
+		Vec::new()
+	} else {
+		match ron::from_str(&buffer) {
+			Ok(instance_history) => instance_history,
+			Err(e) => {
+				let paths: Vec<PathBuf> = ron::from_str(&buffer)
+					.map_err(|_| ron::Error::from(e))
+					.map_err(Error::msg(
+					"failed to deserialize instance history from buffer",
+				))?;
+				let instance_history = migrate_history(paths).await;
+				write_history(history_file_path, &instance_history).await?;
+				info!("instance history migrated from list of paths");
+				instance_history
+			}
+		}
+	};
 	info!("instance history deserialized from buffer");
 
+	for entry in &mut instance_history {
+		entry.missing = !entry.path.join(Instance::BINARY).is_file();
+	}
+
 	Ok(instance_history)
 }
 
+/// Converts the old instance history of paths into entries, reading each display
+/// name from `Instance::TOML`. The order of the paths is kept.
 #[instrument(level = "trace")]
-pub async fn write_instance_history(instance_history: &[PathBuf]) -> Result<()> {
-	let history_file_path = crate::cache_dir()
-		.await
-		.map_err(Error::msg("failed to create cache directory"))?
-		.join(Instance::HISTORY_CACHE_RON);
+async fn migrate_history(paths: Vec<PathBuf>) -> InstanceHistory {
+	let mut instance_history = Vec::with_capacity(paths.len());
+	for path in paths {
+		let name = fs::read_to_string(path.join(Instance::DATA_DIR).join(Instance::TOML))
+			.await
+			.ok()
+			.and_then(|buffer| toml::from_str::<Settings>(&buffer).ok())
+			.and_then(|settings| settings.name);
+		instance_history.push(HistoryEntry {
+			path,
+			last_opened: 0,
+			name,
+			pinned: false,
+			missing: false,
+		});
+	}
+
+	instance_history
+}
+
+/// Returns a result from writing the instance history to
+/// `Instance::HISTORY_CACHE_RON`.
+///
+/// # Errors
+///
+/// Errors may be returned according to:
+///
+/// - `emtk_core::cache_dir`
+/// - `ron::ser::to_string_pretty`
+/// - `tokio::fs::write`
+#[instrument(level = "trace")]
+pub async fn write_instance_history(instance_history: &[HistoryEntry]) -> Result<()> {
+	write_history(&history_file_path().await?, instance_history).await
+}
 
+#[instrument(level = "trace")]
+async fn write_history(history_file_path: &Path, instance_history: &[HistoryEntry]) -> Result<()> {
 	let buffer = ron::ser::to_string_pretty(instance_history, ron::ser::PrettyConfig::default())
 		.map_err(Error::msg(
 			"failed to serialize instance history into buffer",
 		))?;
-	let _ = fs::write(history_file_path, buffer)
+	crate::write_atomic(history_file_path, buffer)
 		.await
-		.map_err(Error::msg("failed to write into instance history file"));
+		.map_err(Error::msg("failed to write into instance history file"))?;
 	info!("instance history recorded to file");
 
 	Ok(())
 }
 
+/// Reads the instance history, applies `f` to it and writes it back.
+#[instrument(level = "trace", skip(f))]
+async fn update_history<T>(f: impl FnOnce(&mut InstanceHistory) -> T) -> Result<T> {
+	let history_file_path = history_file_path().await?;
+	let mut instance_history = read_history(&history_file_path).await?;
+	let output = f(&mut instance_history);
+	write_history(&history_file_path, &instance_history).await?;
+
+	Ok(output)
+}
+
+/// Returns a result to the entry of `path` removed from the instance history, or
+/// `None` if the path is not in the history. The instance itself is untouched.
+///
+/// # Errors
+///
+/// Errors may be returned according to:
+///
+/// - `instance::history`
+/// - `instance::write_instance_history`
+#[instrument(level = "trace")]
+pub async fn remove_from_history(path: &Path) -> Result<Option<HistoryEntry>> {
+	update_history(|instance_history| remove_entry(instance_history, path)).await
+}
+
+/// Returns a result to the entries removed from the instance history because
+/// their instance could no longer be found. Pinned entries are never pruned.
+///
+/// # Errors
+///
+/// Errors may be returned according to:
+///
+/// - `instance::history`
+/// - `instance::write_instance_history`
+#[instrument(level = "trace")]
+pub async fn prune_history() -> Result<Vec<HistoryEntry>> {
+	update_history(prune_entries).await
+}
+
+/// Returns a result from pinning or unpinning the entry of `path` in the
+/// instance history.
+///
+/// # Errors
+///
+/// This function will return an error if `path` is not in the instance history.
+///
+/// Errors may also be returned according to:
+///
+/// - `instance::history`
+/// - `instance::write_instance_history`
+#[instrument(level = "trace")]
+pub async fn pin_in_history(path: &Path, pinned: bool) -> Result<()> {
+	update_history(|instance_history| {
+		let entry = instance_history
+			.iter_mut()
+			.find(|entry| entry.path == path)
+			.ok_or(Error::new(
+				anyhow!("instance is not in the history"),
+				"failed to pin instance",
+			))?;
+		entry.pinned = pinned;
+		Ok(())
+	})
+	.await?
+}
+
+/// Returns the instance history ordered for display, pinned entries first and
+/// then from most to least recently opened.
+#[instrument(level = "trace")]
+pub fn sorted_history(mut instance_history: InstanceHistory) -> InstanceHistory {
+	instance_history.reverse();
+	// stable sort keeps the most recent entries first within each group
+	instance_history.sort_by_key(|entry| !entry.pinned);
+	instance_history
+}
+
+#[instrument(level = "trace")]
+fn remove_entry(instance_history: &mut InstanceHistory, path: &Path) -> Option<HistoryEntry> {
+	let index = instance_history
+		.iter()
+		.position(|entry| entry.path == path)?;
+	Some(instance_history.remove(index))
+}
+
+#[instrument(level = "trace")]
+fn prune_entries(instance_history: &mut InstanceHistory) -> Vec<HistoryEntry> {
+	let (pruned, kept) = instance_history
+		.drain(..)
+		.partition(|entry| entry.missing && !entry.pinned);
+	*instance_history = kept;
+	pruned
+}
+
+/// Moves the entry of `path` to the end of the history as the most recently
+/// opened instance, updating its cached name. Pinned status is kept.
+#[instrument(level = "trace")]
+fn record_entry(instance_history: &mut InstanceHistory, path: &Path, name: Option<String>) {
+	let pinned = remove_entry(instance_history, path).is_some_and(|entry| entry.pinned);
+	instance_history.push(HistoryEntry {
+		pinned,
+		..HistoryEntry::new(path.to_path_buf(), name)
+	});
+}
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Settings {
 	pub name: Option<String>,
@@ -95,6 +316,17 @@ pub struct Instance {
 	#[getset(get = "pub")]
 	#[builder(skip)]
 	settings: Settings,
+
+	/// Game version detected from the game directory, or `None` if detection
+	/// failed
+	#[getset(get = "pub")]
+	#[builder(skip)]
+	pub(crate) game_version: Option<GameVersion>,
+
+	/// Lock of `Instance::LOCK` shared between clones of the instance. The lock
+	/// is released once every clone is dropped.
+	#[builder(skip)]
+	lock: Option<Arc<Lock>>,
 }
 
 impl Instance {
@@ -172,18 +404,28 @@ impl Instance {
 	///
 	/// This function will return an error if:
 	///
-	/// - `path` doesn't exist
-	/// - **Exanima.exe** does not exist within the directory `path` points to
-	/// - another process locked the instance and is currently using it
+	/// - `path` doesn't exist, see [`ErrorKind::InstanceNotFound`]
+	/// - **Exanima.exe** does not exist within the directory `path` points to,
+	///   see [`ErrorKind::ExecutableMissing`]
 	#[instrument(level = "trace")]
 	pub fn with_path<P: Into<PathBuf> + fmt::Debug>(path: P) -> Result<InstanceBuilder> {
 		let path: PathBuf = path.into();
-		let path = path
-			.canonicalize()
-			.map_err(Error::msg("failed to find path for instance"))?;
-		path.join("Exanima.exe")
+		let path = path.canonicalize().map_err(|e| match e.kind() {
+			io::ErrorKind::NotFound => Error::new(
+				ErrorKind::InstanceNotFound { path: path.clone() },
+				"failed to find path for instance",
+			),
+			_ => Error::new(e, "failed to find path for instance"),
+		})?;
+		path.join(Self::BINARY)
 			.canonicalize()
-			.map_err(Error::msg("failed to find game executable file"))?;
+			.map_err(|e| match e.kind() {
+				io::ErrorKind::NotFound => Error::new(
+					ErrorKind::ExecutableMissing { path: path.clone() },
+					"failed to find game executable file",
+				),
+				_ => Error::new(e, "failed to find game executable file"),
+			})?;
 
 		Ok(Self::builder(path))
 	}
@@ -193,10 +435,10 @@ impl Instance {
 	///
 	/// # Examples
 	///
-	/// ```rust
+	/// ```rust,no_run
 	/// use emtk_core::prelude::*;
 	///
-	/// # tokio_test::block_on(async {
+	/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
 	/// let maybe_instance = "C:\\Program Files (x86)\\Steam\\steamapps\\common\\Exanima";
 	/// let mut instance = Instance::with_path(maybe_instance).unwrap().build().await.unwrap();
 	/// let new_profile = Profile::with_path(
@@ -236,9 +478,11 @@ impl Instance {
 			.map_err(Error::msg("failed to create cache directory"))?
 			.join(Self::RECENT_PROFILE_RON);
 
-		fs::write(file_path, buffer).await.map_err(Error::msg(
-			"failed to write buffer containing profile path into cache file",
-		))?;
+		crate::write_atomic(&file_path, buffer)
+			.await
+			.map_err(Error::msg(
+				"failed to write buffer containing profile path into cache file",
+			))?;
 		info!("profile path cached to file");
 
 		Ok(self)
@@ -264,10 +508,12 @@ impl Instance {
 				"failed to serialize instance settings into buffer",
 			))?;
 		info!("instance settings serialized to buffer");
-		fs::write(
-			self.data_dir()
+		crate::write_atomic(
+			&self
+				.data_dir()
 				.await
-				.map_err(Error::msg("could not find instance data directory"))?,
+				.map_err(Error::msg("could not find instance data directory"))?
+				.join(Instance::TOML),
 			buffer,
 		)
 		.await
@@ -276,10 +522,33 @@ impl Instance {
 		))?;
 		info!("finished writing instance settings to file");
 
+		// keep the display name cached in the instance history up to date
+		let _ = update_history(|instance_history| {
+			if let Some(entry) = instance_history
+				.iter_mut()
+				.find(|entry| entry.path == self.path)
+			{
+				entry.name = settings.name.clone();
+			}
+		})
+		.await
+		.map_err(|e| warn!("{e}"));
+
 		self.settings = settings;
 		Ok(self)
 	}
 
+	/// Returns a result from setting the display name of the instance, or
+	/// clearing it with `None`.
+	///
+	/// See [`Instance::set_settings`].
+	#[instrument(level = "trace")]
+	pub async fn rename(&mut self, name: Option<String>) -> Result<&mut Self> {
+		let mut settings = self.settings.clone();
+		settings.name = name;
+		self.set_settings(settings).await
+	}
+
 	#[instrument(level = "trace")]
 	pub async fn data_dir(&self) -> io::Result<PathBuf> {
 		let dir = self.path.join(Self::DATA_DIR);
@@ -337,6 +606,213 @@ impl Instance {
 		}
 		Ok(profile_paths)
 	}
+
+	/// Return a result to the path of a profile named `name` within
+	/// `Instance::PROFILES_DIR`. The profile directory is not required to exist.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if:
+	///
+	/// - `name` is empty or is not a single directory name, e.g. contains a path
+	///   separator, `.` or `..`
+	/// - `Instance::profiles_dir` fails
+	#[instrument(level = "trace")]
+	pub async fn profile_dir(&self, name: &str) -> Result<PathBuf> {
+		let mut components = Path::new(name).components();
+		let is_valid = matches!(
+			(components.next(), components.next()),
+			(Some(Component::Normal(component)), None) if component == name
+		);
+		if !is_valid {
+			return Err(Error::new(
+				anyhow!("\"{name}\" is not a valid profile name"),
+				"failed to get path to profile",
+			));
+		}
+
+		let profiles_dir = self
+			.profiles_dir()
+			.await
+			.map_err(Error::msg("failed to create profiles directory"))?;
+
+		Ok(profiles_dir.join(name))
+	}
+
+	/// Returns a result to a newly built profile named `name` with an empty load
+	/// order. The instance will continue to use its current profile.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if:
+	///
+	/// - `name` is not a valid profile name, see [`Instance::profile_dir`]
+	/// - a profile with the same name already exists
+	///
+	/// Errors may also be returned according to:
+	///
+	/// - `Profile::with_path`
+	/// - `ProfileBuilder::build`
+	#[instrument(level = "trace")]
+	pub async fn create_profile(&self, name: &str) -> Result<Profile> {
+		let profile_dir = self.profile_dir(name).await?;
+		if profile_dir.exists() {
+			return Err(Error::new(
+				anyhow!("profile \"{name}\" already exists"),
+				"failed to create profile",
+			));
+		}
+
+		let profile = Profile::with_path(profile_dir).await?.build().await?;
+		info!("profile created");
+
+		Ok(profile)
+	}
+
+	/// Returns a result to a newly built profile named `name` that is a copy of
+	/// the profile named `source`, including its load order. The caches of
+	/// `source` are not copied. The instance will continue to use its current
+	/// profile.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if:
+	///
+	/// - `source` or `name` is not a valid profile name, see [`Instance::profile_dir`]
+	/// - the profile named `source` does not exist
+	/// - a profile named `name` already exists
+	///
+	/// Errors may also be returned according to:
+	///
+	/// - `emtk_core::copy_dir`
+	/// - `Profile::with_path`
+	/// - `ProfileBuilder::build`
+	#[instrument(level = "trace")]
+	pub async fn clone_profile(&self, source: &str, name: &str) -> Result<Profile> {
+		let source_dir = self.profile_dir(source).await?;
+		if !source_dir.is_dir() {
+			return Err(Error::new(
+				anyhow!("profile \"{source}\" does not exist"),
+				"failed to clone profile",
+			));
+		}
+		let profile_dir = self.profile_dir(name).await?;
+		if profile_dir.exists() {
+			return Err(Error::new(
+				anyhow!("profile \"{name}\" already exists"),
+				"failed to clone profile",
+			));
+		}
+
+		crate::copy_dir(&source_dir, &profile_dir, &[Profile::CACHE_DIR])
+			.await
+			.map_err(Error::msg("failed to copy profile directory"))?;
+		info!("profile directory copied");
+
+		Profile::with_path(profile_dir).await?.build().await
+	}
+
+	/// Returns a result to the profile named `name` after renaming it to
+	/// `new_name`. If the profile is the instance's current profile, the instance
+	/// will switch to the renamed profile and `Instance::RECENT_PROFILE_RON` is
+	/// updated to point to it.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if:
+	///
+	/// - `name` or `new_name` is not a valid profile name, see [`Instance::profile_dir`]
+	/// - `name` is `Instance::DEFAULT_PROFILE_DIR`
+	/// - the profile named `name` does not exist
+	/// - a profile named `new_name` already exists
+	///
+	/// Errors may also be returned according to:
+	///
+	/// - `tokio::fs::rename`
+	/// - `Profile::with_path`
+	/// - `ProfileBuilder::build`
+	/// - `Instance::set_profile`
+	#[instrument(level = "trace")]
+	pub async fn rename_profile(&mut self, name: &str, new_name: &str) -> Result<Profile> {
+		let profile_dir = self.profile_dir(name).await?;
+		if name.eq_ignore_ascii_case(Self::DEFAULT_PROFILE_DIR) {
+			return Err(Error::new(
+				anyhow!("the default profile can not be renamed"),
+				"failed to rename profile",
+			));
+		}
+		if !profile_dir.is_dir() {
+			return Err(Error::new(
+				anyhow!("profile \"{name}\" does not exist"),
+				"failed to rename profile",
+			));
+		}
+		let new_profile_dir = self.profile_dir(new_name).await?;
+		if new_profile_dir.exists() {
+			return Err(Error::new(
+				anyhow!("profile \"{new_name}\" already exists"),
+				"failed to rename profile",
+			));
+		}
+
+		let is_current = self.profile.path() == &profile_dir;
+		fs::rename(&profile_dir, &new_profile_dir)
+			.await
+			.map_err(Error::msg("failed to rename profile directory"))?;
+		info!("profile directory renamed");
+
+		let profile = Profile::with_path(new_profile_dir).await?.build().await?;
+		if is_current {
+			self.set_profile(profile.clone()).await?;
+		}
+
+		Ok(profile)
+	}
+
+	/// Returns a result from attempting to delete the profile named `name` along
+	/// with everything inside of its directory.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if:
+	///
+	/// - `name` is not a valid profile name, see [`Instance::profile_dir`]
+	/// - `name` is `Instance::DEFAULT_PROFILE_DIR`
+	/// - the profile is the instance's current profile
+	/// - the profile named `name` does not exist
+	///
+	/// Errors may also be returned according to:
+	///
+	/// - `tokio::fs::remove_dir_all`
+	#[instrument(level = "trace")]
+	pub async fn delete_profile(&self, name: &str) -> Result<()> {
+		let profile_dir = self.profile_dir(name).await?;
+		if name.eq_ignore_ascii_case(Self::DEFAULT_PROFILE_DIR) {
+			return Err(Error::new(
+				anyhow!("the default profile can not be deleted"),
+				"failed to delete profile",
+			));
+		}
+		if self.profile.path() == &profile_dir {
+			return Err(Error::new(
+				anyhow!("profile \"{name}\" is currently in use by the instance"),
+				"failed to delete profile",
+			));
+		}
+		if !profile_dir.is_dir() {
+			return Err(Error::new(
+				anyhow!("profile \"{name}\" does not exist"),
+				"failed to delete profile",
+			));
+		}
+
+		fs::remove_dir_all(profile_dir)
+			.await
+			.map_err(Error::msg("failed to delete profile directory"))?;
+		info!("profile deleted");
+
+		Ok(())
+	}
 }
 
 impl<S> InstanceBuilder<S>
@@ -356,27 +832,7 @@ where
 	where
 		S: instance_builder::IsComplete,
 	{
-		// TODO: this block works but commenting out for now until all bugs elsewhere are fixed
-		// let lock_path = self.path.join(Instance::DATA_DIR).join(Instance::LOCK);
 		let force = self.force;
-		// if !force && lock_path.is_file() {
-		// 	let file = fs::File::open(lock_path).await.unwrap();
-		// 	let mut reader = io::BufReader::new(file);
-		// 	let mut buffer = String::new();
-		// 	reader.read_line(&mut buffer).await.unwrap();
-		// 	let s = sysinfo::System::new_with_specifics(
-		// 		sysinfo::RefreshKind::nothing()
-		// 			.with_processes(sysinfo::ProcessRefreshKind::nothing()),
-		// 	);
-		// 	if let Ok(pid) = sysinfo::Pid::from_str(buffer.trim())
-		// 		&& s.process(pid).is_some()
-		// 	{
-		// 		return Err(error::Build::Locked(
-		// 			"the instance at the given path is currently being used by another process",
-		// 		));
-		// 	};
-		// }
-
 		let mut instance = self.build_internal();
 		instance.force = force;
 
@@ -385,15 +841,13 @@ where
 			.await
 			.map_err(Error::msg("could not find instance data directory"))?;
 
-		fs::write(
-			data_dir.join(Instance::LOCK),
-			process::id().to_string().as_bytes(),
-		)
-		.await
-		.map_err(Error::msg(
-			"failed to write process ID to instance's lock file",
-		))?;
-		info!("process ID recorded to instance's lock file");
+		// acquiring the lock blocks while waiting on an unreadable lock file
+		let lock_path = data_dir.join(Instance::LOCK);
+		let lock = tokio::task::spawn_blocking(move || Lock::acquire(&lock_path, force))
+			.await
+			.map_err(|e| Error::new(anyhow!(e), "failed to acquire lock"))??;
+		instance.lock = Some(lock);
+		info!("instance's lock acquired");
 
 		let settings_path = data_dir.join(Instance::TOML);
 		instance.settings = if settings_path.is_file() {
@@ -413,6 +867,12 @@ where
 			Settings::default()
 		};
 
+		instance.game_version = instance
+			.detect_game_version()
+			.await
+			.map_err(|e| warn!("{e}"))
+			.ok();
+
 		let cache_dir = instance
 			.cache_dir()
 			.await
@@ -459,37 +919,35 @@ where
 			.map_err(|e| error!("{e}"));
 
 		// attempt to record this instance to a history file
-		{
-			let instance_history = history()
-				.await
-				.map_err(|e| warn!("{e}"))
-				.unwrap_or_default();
-			let mut instance_history = if !instance_history.is_empty() {
-				// deduplicate any old paths
-				instance_history
-					.into_iter()
-					.filter(|history_path| *history_path != instance.path)
-					.collect()
-			} else {
-				instance_history
-			};
-			instance_history.push(instance.path.clone());
-
-			let _ = write_instance_history(&instance_history)
-				.await
-				.map_err(|e| warn!("{e}"));
-		}
+		let path = instance.path.clone();
+		let name = instance.settings.name.clone();
+		let _ = update_history(|instance_history| record_entry(instance_history, &path, name))
+			.await
+			.map_err(|e| warn!("{e}"));
 
 		Ok(instance)
 	}
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
+	use std::{fs, path::PathBuf};
+
+	use pretty_assertions::{assert_eq, assert_ne};
 	use tempfile::{NamedTempFile, TempDir, tempdir};
 
 	use crate::{Error, ErrorKind, prelude::*};
 
+	pub(crate) async fn dummy_instance(tempdir: &TempDir) -> Instance {
+		dummy_exanima_exe(tempdir);
+
+		Instance::with_path(tempdir.path())
+			.unwrap()
+			.build()
+			.await
+			.unwrap()
+	}
+
 	/// Prevent `instance::Builder::Io` error when calling `Instance::with_path()`
 	fn dummy_exanima_exe(tempdir: &TempDir) -> NamedTempFile {
 		tempfile::Builder::new()
@@ -544,16 +1002,25 @@ mod tests {
 		assert!(matches!(
 			Instance::with_path("").err().unwrap(),
 			Error {
-				kind: ErrorKind::Io { .. },
+				kind: ErrorKind::InstanceNotFound { .. },
+				message: _,
+			},
+		));
+		assert!(matches!(
+			Instance::with_path(cwd.path().join("missing"))
+				.err()
+				.unwrap(),
+			Error {
+				kind: ErrorKind::InstanceNotFound { .. },
 				message: _,
 			},
 		));
 		assert!(matches!(
 			Instance::with_path(cwd.path()).err().unwrap(),
 			Error {
-				kind: ErrorKind::Io { .. },
+				kind: ErrorKind::ExecutableMissing { path },
 				message: _,
-			}
+			} if path == cwd.path().canonicalize().unwrap()
 		));
 	}
 
@@ -574,4 +1041,198 @@ mod tests {
 			.await
 			.unwrap();
 	}
+
+	#[tokio::test]
+	async fn create_profile() {
+		let cwd = tempdir().unwrap();
+		let instance = dummy_instance(&cwd).await;
+
+		let profile = instance.create_profile("New").await.unwrap();
+		assert_eq!(
+			profile.path(),
+			&instance.profiles_dir().await.unwrap().join("New")
+		);
+		assert!(profile.path().join(Profile::LOAD_ORDER_TOML).is_file());
+		// the instance keeps using its current profile
+		assert_ne!(instance.profile().path(), profile.path());
+
+		assert!(instance.create_profile("New").await.is_err());
+		assert!(instance.create_profile("").await.is_err());
+		assert!(instance.create_profile("..").await.is_err());
+		assert!(instance.create_profile("a/b").await.is_err());
+	}
+
+	#[tokio::test]
+	async fn clone_profile() {
+		let cwd = tempdir().unwrap();
+		let instance = dummy_instance(&cwd).await;
+
+		let source = instance.create_profile("Source").await.unwrap();
+		fs::write(
+			source.path().join(Profile::LOAD_ORDER_TOML),
+			"[\"com.example.my-mod\"]\nenabled = true\npriority = 0\n",
+		)
+		.unwrap();
+		source.cache_build_dir().await.unwrap();
+
+		let profile = instance.clone_profile("Source", "Copy").await.unwrap();
+		let id = plugin::Id::try_from("com.example.my-mod").unwrap();
+		assert!(profile.load_order().get(&id).unwrap().enabled);
+		assert!(!profile.path().join(Profile::CACHE_DIR).exists());
+
+		assert!(instance.clone_profile("Missing", "Other").await.is_err());
+		assert!(instance.clone_profile("Source", "Copy").await.is_err());
+	}
+
+	#[tokio::test]
+	async fn rename_profile() {
+		let cwd = tempdir().unwrap();
+		let mut instance = dummy_instance(&cwd).await;
+
+		let profile = instance.create_profile("Old").await.unwrap();
+		instance.set_profile(profile).await.unwrap();
+
+		let profile = instance.rename_profile("Old", "New").await.unwrap();
+		let profiles_dir = instance.profiles_dir().await.unwrap();
+		assert!(!profiles_dir.join("Old").exists());
+		assert_eq!(profile.path(), &profiles_dir.join("New"));
+		assert_eq!(instance.profile().path(), profile.path());
+
+		let recent_profile_path = instance
+			.cache_dir()
+			.await
+			.unwrap()
+			.join(Instance::RECENT_PROFILE_RON);
+		let buffer = fs::read_to_string(recent_profile_path).unwrap();
+		assert_eq!(
+			ron::from_str::<String>(&buffer).unwrap(),
+			profile.path().display().to_string()
+		);
+
+		assert!(
+			instance
+				.rename_profile(Instance::DEFAULT_PROFILE_DIR, "Other")
+				.await
+				.is_err()
+		);
+		assert!(instance.rename_profile("Missing", "Other").await.is_err());
+	}
+
+	#[tokio::test]
+	async fn delete_profile() {
+		let cwd = tempdir().unwrap();
+		let mut instance = dummy_instance(&cwd).await;
+
+		assert!(
+			instance
+				.delete_profile(Instance::DEFAULT_PROFILE_DIR)
+				.await
+				.is_err()
+		);
+
+		let profile = instance.create_profile("Active").await.unwrap();
+		instance.set_profile(profile.clone()).await.unwrap();
+		assert!(instance.delete_profile("Active").await.is_err());
+		assert!(profile.path().is_dir());
+
+		let profile = instance.create_profile("Unused").await.unwrap();
+		instance.delete_profile("Unused").await.unwrap();
+		assert!(!profile.path().exists());
+
+		assert!(instance.delete_profile("Unused").await.is_err());
+	}
+
+	#[tokio::test]
+	async fn migrate_history() {
+		let cwd = tempdir().unwrap();
+		let named = tempdir().unwrap();
+		dummy_exanima_exe(&named);
+		fs::create_dir_all(named.path().join(Instance::DATA_DIR)).unwrap();
+		fs::write(
+			named.path().join(Instance::DATA_DIR).join(Instance::TOML),
+			"name = \"Modded\"\n",
+		)
+		.unwrap();
+		let missing = cwd.path().join("missing");
+
+		let history_file_path = cwd.path().join(Instance::HISTORY_CACHE_RON);
+		fs::write(
+			&history_file_path,
+			ron::to_string(&vec![missing.clone(), named.path().to_path_buf()]).unwrap(),
+		)
+		.unwrap();
+
+		let instance_history = super::read_history(&history_file_path).await.unwrap();
+		assert_eq!(
+			instance_history
+				.iter()
+				.map(|entry| (&entry.path, entry.name.as_deref(), entry.missing))
+				.collect::<Vec<_>>(),
+			vec![
+				(&missing, None, true),
+				(&named.path().to_path_buf(), Some("Modded"), false)
+			]
+		);
+		// migrated history is written back in the current format
+		let buffer = fs::read_to_string(&history_file_path).unwrap();
+		assert!(ron::from_str::<super::InstanceHistory>(&buffer).is_ok());
+		assert_eq!(
+			super::read_history(&history_file_path).await.unwrap(),
+			instance_history
+		);
+
+		let empty_file_path = cwd.path().join("empty.ron");
+		assert!(
+			super::read_history(&empty_file_path)
+				.await
+				.unwrap()
+				.is_empty()
+		);
+	}
+
+	#[test]
+	fn history_entries() {
+		let first = PathBuf::from("first");
+		let second = PathBuf::from("second");
+		let third = PathBuf::from("third");
+		let mut instance_history = super::InstanceHistory::new();
+		super::record_entry(&mut instance_history, &first, None);
+		super::record_entry(&mut instance_history, &second, None);
+		super::record_entry(&mut instance_history, &third, Some("Third".into()));
+		instance_history[0].pinned = true;
+		// recording an existing entry moves it to the end and keeps it pinned
+		super::record_entry(&mut instance_history, &first, Some("First".into()));
+		assert_eq!(
+			instance_history
+				.iter()
+				.map(|entry| (&entry.path, entry.name.as_deref(), entry.pinned))
+				.collect::<Vec<_>>(),
+			vec![
+				(&second, None, false),
+				(&third, Some("Third"), false),
+				(&first, Some("First"), true)
+			]
+		);
+
+		super::record_entry(&mut instance_history, &second, None);
+		instance_history[0].pinned = false;
+		instance_history[1].pinned = true;
+		let sorted: Vec<_> = super::sorted_history(instance_history.clone())
+			.into_iter()
+			.map(|entry| entry.path)
+			.collect();
+		assert_eq!(sorted, vec![first.clone(), second.clone(), third.clone()]);
+
+		for entry in &mut instance_history {
+			entry.missing = true;
+		}
+		let pruned = super::prune_entries(&mut instance_history);
+		assert_eq!(pruned.len(), 2);
+		assert_eq!(instance_history.len(), 1);
+		assert_eq!(instance_history[0].path, first);
+
+		assert!(super::remove_entry(&mut instance_history, &third).is_none());
+		assert!(super::remove_entry(&mut instance_history, &first).is_some());
+		assert!(instance_history.is_empty());
+	}
 }
@@ -4,28 +4,48 @@
 #![deny(clippy::expect_used)]
 #![deny(clippy::unwrap_used)]
 
+#[cfg(feature = "blocking")]
+pub mod blocking;
 pub mod cache;
+pub mod conflict;
+pub mod discover;
+pub mod game;
+pub mod install;
 pub mod instance;
+pub mod lifecycle;
+pub mod lock;
+pub mod modpack;
+pub mod pack;
 pub mod plugin;
 pub mod profile;
+pub mod saves;
+pub mod signature;
+pub mod watch;
 
 use std::{
 	backtrace::Backtrace,
 	borrow::Cow,
 	ffi, fmt,
 	path::{Path, PathBuf},
+	sync::atomic::{AtomicU64, Ordering},
 	time::SystemTimeError,
 };
 
-use tokio::{fs, io};
+use tokio::{
+	fs,
+	io::{self, AsyncWriteExt},
+};
 
-pub use instance::{Instance, InstanceHistory};
+pub use instance::{HistoryEntry, Instance, InstanceHistory};
+pub use modpack::Modpack;
 pub use plugin::Plugin;
 pub use profile::Profile;
 use tracing::instrument;
 
 pub mod prelude {
-	pub use crate::{instance::prelude::*, plugin::prelude::*, profile::prelude::*};
+	pub use crate::{
+		instance::prelude::*, modpack::prelude::*, plugin::prelude::*, profile::prelude::*,
+	};
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -73,6 +93,22 @@ impl Error {
 	{
 		move |e| Self::new(e, message)
 	}
+
+	/// Returns the kind of error, which can be matched on to handle specific
+	/// errors such as [`ErrorKind::InstanceLocked`].
+	pub fn kind(&self) -> &ErrorKind {
+		&self.kind
+	}
+
+	/// Returns the message describing what failed.
+	pub fn message(&self) -> &str {
+		&self.message
+	}
+
+	/// Returns the kind of error, consuming the error.
+	pub fn into_kind(self) -> ErrorKind {
+		self.kind
+	}
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -100,10 +136,60 @@ pub enum ErrorKind {
 	},
 	#[error(transparent)]
 	Toml(#[from] TomlError),
+	/// No instance exists at the path.
+	#[error("no instance found at \"{}\"", path.display())]
+	InstanceNotFound { path: PathBuf },
+	/// The game executable, [`Instance::BINARY`], is missing from the instance.
+	#[error("{} is missing from \"{}\"", Instance::BINARY, path.display())]
+	ExecutableMissing { path: PathBuf },
+	/// Another running process holds the lock of the instance.
+	#[error("instance is locked by another process with PID {pid}")]
+	InstanceLocked { pid: u32 },
+	/// An ID is not in reverse domain name notation, see [`plugin::Id`].
+	#[error("id \"{id}\" must be in reverse domain name notation")]
+	InvalidPluginId { id: String },
+	/// A mod's [`plugin::Manifest::TOML`] could not be deserialized.
+	#[error("manifest \"{}\" is invalid: {source}", path.display())]
+	ManifestInvalid { path: PathBuf, source: TomlError },
+	/// A profile's [`Profile::LOAD_ORDER_TOML`] could not be deserialized.
+	#[error("load order \"{}\" is corrupt: {source}", path.display())]
+	LoadOrderCorrupt { path: PathBuf, source: TomlError },
+	/// An enabled mod depends on a mod that is not enabled at the required
+	/// version.
+	#[error(
+		"mod \"{id}\" depends on \"{dependency}\" which is not enabled at the required version"
+	)]
+	DependencyUnmet {
+		id: plugin::Id,
+		dependency: plugin::Id,
+	},
 	#[error(transparent)]
 	Other(#[from] anyhow::Error),
 }
 
+impl From<plugin::Error> for ErrorKind {
+	fn from(value: plugin::Error) -> Self {
+		match value {
+			plugin::Error::InvalidId(id) => Self::InvalidPluginId { id },
+		}
+	}
+}
+
+impl From<lifecycle::Error> for ErrorKind {
+	fn from(value: lifecycle::Error) -> Self {
+		match value {
+			lifecycle::Error::Resolve {
+				id,
+				source:
+					lifecycle::ResolveError::DependencyMissing(dependency)
+					| lifecycle::ResolveError::DependencyFailed(dependency)
+					| lifecycle::ResolveError::DependencyVersion { dependency, .. },
+			} => Self::DependencyUnmet { id, dependency },
+			value => Self::Other(anyhow::Error::new(value)),
+		}
+	}
+}
+
 /// The name of the directory responsible for storing the application's data
 /// such as cache. The directory is created at `%AppData%` on Windows.
 pub const DATA_DIR: &str = "exanima-modding-toolkit";
@@ -125,6 +211,106 @@ pub async fn ensure_dir(dir: &Path) -> io::Result<()> {
 	Ok(())
 }
 
+/// Recursively copies the contents of the directory `src` into `dst`, creating
+/// `dst` if it does not exist. Any directory named in `skip` that is a direct
+/// child of `src` will not be copied.
+#[instrument(level = "trace")]
+pub async fn copy_dir(src: &Path, dst: &Path, skip: &[&str]) -> io::Result<()> {
+	ensure_dir(dst).await?;
+
+	let mut read_dir = fs::read_dir(src).await?;
+	while let Some(entry) = read_dir.next_entry().await? {
+		let entry_path = entry.path();
+		let file_name = entry.file_name();
+		let dst_path = dst.join(&file_name);
+		if entry_path.is_dir() {
+			if skip.iter().any(|name| file_name == *name) {
+				continue;
+			}
+			Box::pin(copy_dir(&entry_path, &dst_path, &[])).await?;
+		} else {
+			fs::copy(&entry_path, &dst_path).await?;
+		}
+	}
+
+	Ok(())
+}
+
+/// Writes `contents` to the file at `path` atomically, so a crash or error
+/// mid-write leaves either the old file or the new file but never a truncated
+/// file.
+///
+/// The contents are written to a temporary file next to `path`, flushed to disk
+/// and then renamed over `path`.
+///
+/// # Errors
+///
+/// The file at `path` is untouched if an error occurs.
+///
+/// Errors may be returned according to:
+///
+/// - `tokio::fs::File::create`
+/// - `tokio::io::AsyncWriteExt::write_all`
+/// - `tokio::fs::File::sync_all`
+/// - `tokio::fs::rename`
+#[instrument(level = "trace", skip(contents))]
+pub async fn write_atomic<C: AsRef<[u8]>>(path: &Path, contents: C) -> io::Result<()> {
+	let temp_path = write_temp(path, contents.as_ref()).await?;
+	if let Err(e) = fs::rename(&temp_path, path).await {
+		let _ = fs::remove_file(&temp_path).await;
+		return Err(e);
+	}
+
+	// persist the rename itself, directories can only be opened on unix
+	#[cfg(unix)]
+	if let Some(parent) = path.parent()
+		&& let Ok(dir) = fs::File::open(parent).await
+	{
+		let _ = dir.sync_all().await;
+	}
+
+	Ok(())
+}
+
+/// Returns a result to the path of a temporary file next to `path` with
+/// `contents` written and flushed to disk. The temporary file is removed if an
+/// error occurs.
+#[instrument(level = "trace", skip(contents))]
+async fn write_temp(path: &Path, contents: &[u8]) -> io::Result<PathBuf> {
+	let temp_path = temp_path(path)?;
+
+	let result = async {
+		let mut file = fs::File::create_new(&temp_path).await?;
+		file.write_all(contents).await?;
+		file.sync_all().await
+	}
+	.await;
+	if let Err(e) = result {
+		let _ = fs::remove_file(&temp_path).await;
+		return Err(e);
+	}
+
+	Ok(temp_path)
+}
+
+/// Returns a result to a path for a temporary file next to `path` that is
+/// unique to the current process and call, such as `.load_order.toml.42.0.tmp`.
+pub(crate) fn temp_path(path: &Path) -> io::Result<PathBuf> {
+	static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+	let file_name = path
+		.file_name()
+		.ok_or(io::Error::from(io::ErrorKind::InvalidInput))?;
+	let mut temp_name = ffi::OsString::from(".");
+	temp_name.push(file_name);
+	temp_name.push(format!(
+		".{}.{}.tmp",
+		std::process::id(),
+		COUNTER.fetch_add(1, Ordering::Relaxed)
+	));
+	Ok(path.with_file_name(temp_name))
+}
+
 /// Returns the path to the application's data directory.
 #[instrument(level = "trace")]
 pub fn data_dir() -> Option<PathBuf> {
@@ -156,3 +342,64 @@ pub async fn log_dir() -> io::Result<PathBuf> {
 
 	Ok(log_dir)
 }
+
+#[cfg(test)]
+mod tests {
+	use std::fs;
+
+	use pretty_assertions::assert_eq;
+	use tempfile::tempdir;
+
+	#[tokio::test]
+	async fn write_atomic() {
+		let cwd = tempdir().unwrap();
+		let path = cwd.path().join("load_order.toml");
+
+		super::write_atomic(&path, "first").await.unwrap();
+		assert_eq!(fs::read_to_string(&path).unwrap(), "first");
+		super::write_atomic(&path, "second").await.unwrap();
+		assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+		// no temporary files are left behind
+		assert_eq!(fs::read_dir(cwd.path()).unwrap().count(), 1);
+	}
+
+	#[tokio::test]
+	async fn write_atomic_interrupted() {
+		let cwd = tempdir().unwrap();
+		let path = cwd.path().join("load_order.toml");
+		fs::write(&path, "original").unwrap();
+
+		// a crash after writing the temporary file but before the rename leaves the
+		// original file intact
+		let temp_path = super::write_temp(&path, b"interrupted").await.unwrap();
+		assert_eq!(fs::read_to_string(&path).unwrap(), "original");
+		assert_eq!(fs::read_to_string(&temp_path).unwrap(), "interrupted");
+
+		// a leftover temporary file from a crash does not affect the next write
+		super::write_atomic(&path, "recovered").await.unwrap();
+		assert_eq!(fs::read_to_string(&path).unwrap(), "recovered");
+		assert_eq!(fs::read_to_string(&temp_path).unwrap(), "interrupted");
+		fs::remove_file(&temp_path).unwrap();
+
+		// concurrent writes within the process use their own temporary files
+		let writes: Vec<_> = (0..8)
+			.map(|i| {
+				let path = path.clone();
+				tokio::spawn(async move { super::write_atomic(&path, format!("write {i}")).await })
+			})
+			.collect();
+		for write in writes {
+			write.await.unwrap().unwrap();
+		}
+		assert!(fs::read_to_string(&path).unwrap().starts_with("write "));
+		assert_eq!(fs::read_dir(cwd.path()).unwrap().count(), 1);
+
+		// a failed rename leaves the destination untouched and cleans up
+		let dir_path = cwd.path().join("profile");
+		fs::create_dir(&dir_path).unwrap();
+		fs::write(dir_path.join("file"), "kept").unwrap();
+		assert!(super::write_atomic(&dir_path, "replaced").await.is_err());
+		assert_eq!(fs::read_to_string(dir_path.join("file")).unwrap(), "kept");
+		assert_eq!(fs::read_dir(cwd.path()).unwrap().count(), 2);
+	}
+}
@@ -0,0 +1,848 @@
+//! The lifecycle of the plugins of the enabled mods of a profile, from being
+//! discovered in the load order to being unloaded from the game.
+//!
+//! ```text
+//! Discovered -> Resolved -> Loaded -> Enabled <-> Disabled -> Unloaded
+//! ```
+//!
+//! Any transition may fail, leaving the plugin in [`State::Failed`] along with
+//! the [`Error`] of the transition. Plugins are loaded after their dependencies
+//! and otherwise by the priority of the load order, and unloaded in reverse.
+//!
+//! Loading a plugin is left to a [`Loader`], so the framework loads libraries
+//! into the game while tests load mock plugins.
+
+use std::{
+	collections::HashMap,
+	fmt::{self, Display, Formatter},
+};
+
+use tracing::{info, instrument, warn};
+
+use crate::{plugin, profile::LoadOrderEntry};
+
+/// The state of a plugin in its lifecycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum State {
+	/// The plugin is enabled in the load order.
+	Discovered,
+	/// The dependencies of the plugin are enabled and ordered before it.
+	Resolved,
+	/// The plugin is loaded by the [`Loader`] but not running.
+	Loaded,
+	/// The plugin is running.
+	Enabled,
+	/// The plugin stopped running but is still loaded.
+	Disabled,
+	/// The plugin is no longer loaded.
+	Unloaded,
+	/// A transition of the plugin failed, see [`Lifecycle::error`].
+	Failed,
+}
+
+impl Display for State {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			State::Discovered => "discovered",
+			State::Resolved => "resolved",
+			State::Loaded => "loaded",
+			State::Enabled => "enabled",
+			State::Disabled => "disabled",
+			State::Unloaded => "unloaded",
+			State::Failed => "failed",
+		})
+	}
+}
+
+/// The reason the dependencies of a plugin could not be resolved.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ResolveError {
+	#[error("dependency \"{0}\" is not enabled")]
+	DependencyMissing(plugin::Id),
+	#[error("dependency \"{dependency}\" has version {found}, expected {required}")]
+	DependencyVersion {
+		dependency: plugin::Id,
+		required: String,
+		found: String,
+	},
+	#[error("dependency \"{0}\" failed")]
+	DependencyFailed(plugin::Id),
+	#[error("dependencies form a cycle")]
+	DependencyCycle,
+}
+
+/// A failed transition of a plugin.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum Error {
+	#[error("failed to resolve \"{id}\": {source}")]
+	Resolve {
+		id: plugin::Id,
+		source: ResolveError,
+	},
+	#[error("failed to load \"{id}\": {reason}")]
+	Load { id: plugin::Id, reason: String },
+	#[error("failed to enable \"{id}\": {reason}")]
+	Enable { id: plugin::Id, reason: String },
+	#[error("failed to disable \"{id}\": {reason}")]
+	Disable { id: plugin::Id, reason: String },
+	#[error("failed to unload \"{id}\": {reason}")]
+	Unload { id: plugin::Id, reason: String },
+	/// The plugin is not in a state the transition can be made from.
+	#[error("cannot {transition} \"{id}\" while it is {state}")]
+	InvalidTransition {
+		id: plugin::Id,
+		state: State,
+		transition: &'static str,
+	},
+	#[error("plugin \"{0}\" was not discovered")]
+	NotFound(plugin::Id),
+}
+
+impl Error {
+	/// Returns the id of the plugin the transition failed for.
+	pub fn id(&self) -> &plugin::Id {
+		match self {
+			Error::Resolve { id, .. }
+			| Error::Load { id, .. }
+			| Error::Enable { id, .. }
+			| Error::Disable { id, .. }
+			| Error::Unload { id, .. }
+			| Error::InvalidTransition { id, .. }
+			| Error::NotFound(id) => id,
+		}
+	}
+}
+
+/// Loads the plugins of mods, such as libraries into the game.
+pub trait Loader {
+	type Module: Module;
+
+	/// Returns a result to the loaded plugin of the mod `id`.
+	fn load(
+		&mut self,
+		id: &plugin::Id,
+		manifest: &plugin::Manifest,
+	) -> anyhow::Result<Self::Module>;
+}
+
+/// A plugin loaded by a [`Loader`].
+pub trait Module {
+	/// Starts running the plugin.
+	fn enable(&mut self) -> anyhow::Result<()>;
+
+	/// Stops running the plugin.
+	fn disable(&mut self) -> anyhow::Result<()>;
+
+	/// Unloads the plugin. By default the plugin is dropped.
+	fn unload(self) -> anyhow::Result<()>
+	where
+		Self: Sized,
+	{
+		Ok(())
+	}
+}
+
+/// A plugin along with its state in the lifecycle.
+#[derive(Debug)]
+struct Entry<M> {
+	id: plugin::Id,
+	load_order_entry: LoadOrderEntry,
+	manifest: plugin::Manifest,
+	state: State,
+	module: Option<M>,
+	error: Option<Error>,
+}
+
+/// The lifecycle of the plugins of a profile.
+#[derive(Debug)]
+pub struct Lifecycle<L: Loader> {
+	loader: L,
+	/// Plugins by priority once discovered, and in loading order once resolved
+	plugins: Vec<Entry<L::Module>>,
+}
+
+impl<L: Loader> Lifecycle<L> {
+	pub fn new(loader: L) -> Self {
+		Self {
+			loader,
+			plugins: Vec::new(),
+		}
+	}
+
+	/// Discovers the plugins of `enabled_mods`, such as from
+	/// [`crate::Profile::enabled_mods`]. Plugins that were already discovered
+	/// are skipped.
+	#[instrument(level = "trace", skip(self, enabled_mods))]
+	pub fn discover<I>(&mut self, enabled_mods: I)
+	where
+		I: IntoIterator<Item = (plugin::Id, LoadOrderEntry, plugin::Manifest)>,
+	{
+		for (id, load_order_entry, manifest) in enabled_mods {
+			if self.position(&id).is_some() {
+				continue;
+			}
+			self.plugins.push(Entry {
+				id,
+				load_order_entry,
+				manifest,
+				state: State::Discovered,
+				module: None,
+				error: None,
+			});
+		}
+		self.plugins
+			.sort_by_key(|entry| entry.load_order_entry.priority);
+		info!("{} plugins discovered", self.plugins.len());
+	}
+
+	/// Resolves the dependencies of the discovered plugins and orders the
+	/// plugins so that dependencies come first, otherwise by priority. A
+	/// dependency must be discovered at the required version, see
+	/// [`plugin::Dependency::is_met_by`].
+	///
+	/// Returns the errors of the plugins that failed to resolve.
+	#[instrument(level = "trace", skip(self))]
+	pub fn resolve(&mut self) -> Vec<Error> {
+		let mut errors = Vec::new();
+		let positions: HashMap<_, _> = self
+			.plugins
+			.iter()
+			.enumerate()
+			.map(|(i, entry)| (entry.id.clone(), i))
+			.collect();
+
+		// the plugins each plugin depends on and the plugins depending on it
+		let mut dependencies: Vec<Vec<usize>> = vec![Vec::new(); self.plugins.len()];
+		let mut dependents: Vec<Vec<usize>> = vec![Vec::new(); self.plugins.len()];
+		for (i, entry) in self.plugins.iter().enumerate() {
+			if entry.state != State::Discovered {
+				continue;
+			}
+			for (dependency, requirement) in entry.manifest.dependencies.iter().flatten() {
+				match positions.get(dependency) {
+					Some(&j) if requirement.is_met_by(&self.plugins[j].manifest) => {
+						dependencies[i].push(j);
+						dependents[j].push(i);
+					}
+					Some(&j) => {
+						errors.push(Error::Resolve {
+							id: entry.id.clone(),
+							source: ResolveError::DependencyVersion {
+								dependency: dependency.clone(),
+								required: requirement.version().to_string(),
+								found: self.plugins[j].manifest.plugin.version.clone(),
+							},
+						});
+					}
+					None => {
+						errors.push(Error::Resolve {
+							id: entry.id.clone(),
+							source: ResolveError::DependencyMissing(dependency.clone()),
+						});
+					}
+				}
+			}
+		}
+		for error in errors.iter() {
+			if let Some(i) = positions.get(error.id()) {
+				self.plugins[*i].state = State::Failed;
+			}
+		}
+
+		// plugins are taken in order of priority once their dependencies are
+		// taken, which leaves the plugins of dependency cycles
+		let mut remaining: Vec<usize> = dependencies.iter().map(Vec::len).collect();
+		let mut ready: Vec<usize> = (0..self.plugins.len())
+			.filter(|&i| remaining[i] == 0)
+			.collect();
+		let mut order = Vec::with_capacity(self.plugins.len());
+		while let Some(position) = ready
+			.iter()
+			.enumerate()
+			.min_by_key(|&(_, &i)| i)
+			.map(|(position, _)| position)
+		{
+			let i = ready.swap_remove(position);
+			order.push(i);
+
+			let failed_dependency = dependencies[i]
+				.iter()
+				.find(|&&j| self.plugins[j].state == State::Failed)
+				.map(|&j| self.plugins[j].id.clone());
+			let entry = &mut self.plugins[i];
+			if entry.state == State::Discovered {
+				if let Some(dependency) = failed_dependency {
+					entry.state = State::Failed;
+					errors.push(Error::Resolve {
+						id: entry.id.clone(),
+						source: ResolveError::DependencyFailed(dependency),
+					});
+				} else {
+					entry.state = State::Resolved;
+				}
+			}
+
+			for &dependent in &dependents[i] {
+				remaining[dependent] -= 1;
+				if remaining[dependent] == 0 {
+					ready.push(dependent);
+				}
+			}
+		}
+		for i in 0..self.plugins.len() {
+			if remaining[i] > 0 {
+				order.push(i);
+				let entry = &mut self.plugins[i];
+				if entry.state == State::Discovered {
+					entry.state = State::Failed;
+					errors.push(Error::Resolve {
+						id: entry.id.clone(),
+						source: ResolveError::DependencyCycle,
+					});
+				}
+			}
+		}
+
+		let mut plugins: Vec<_> = self.plugins.drain(..).map(Some).collect();
+		self.plugins = order
+			.into_iter()
+			.filter_map(|i| plugins[i].take())
+			.collect();
+		self.record(errors)
+	}
+
+	/// Loads the resolved plugins in order. A plugin fails to load if one of its
+	/// dependencies failed.
+	///
+	/// Returns the errors of the plugins that failed to load.
+	#[instrument(level = "trace", skip(self))]
+	pub fn load(&mut self) -> Vec<Error> {
+		let mut errors = Vec::new();
+		for i in 0..self.plugins.len() {
+			if self.plugins[i].state != State::Resolved {
+				continue;
+			}
+			if let Some(dependency) = self.failed_dependency(i) {
+				let entry = &mut self.plugins[i];
+				entry.state = State::Failed;
+				errors.push(Error::Load {
+					id: entry.id.clone(),
+					reason: format!("dependency \"{dependency}\" is not loaded"),
+				});
+				continue;
+			}
+
+			let entry = &mut self.plugins[i];
+			match self.loader.load(&entry.id, &entry.manifest) {
+				Ok(module) => {
+					entry.module = Some(module);
+					entry.state = State::Loaded;
+					info!("plugin \"{}\" loaded", entry.id);
+				}
+				Err(source) => {
+					entry.state = State::Failed;
+					errors.push(Error::Load {
+						id: entry.id.clone(),
+						reason: format!("{source:#}"),
+					});
+				}
+			}
+		}
+
+		self.record(errors)
+	}
+
+	/// Enables the loaded plugins in order.
+	///
+	/// Returns the errors of the plugins that failed to enable.
+	#[instrument(level = "trace", skip(self))]
+	pub fn enable(&mut self) -> Vec<Error> {
+		let ids: Vec<_> = self
+			.plugins
+			.iter()
+			.filter(|entry| entry.state == State::Loaded)
+			.map(|entry| entry.id.clone())
+			.collect();
+		let errors = ids
+			.iter()
+			.filter_map(|id| self.enable_plugin(id).err())
+			.collect();
+
+		self.record(errors)
+	}
+
+	/// Disables then unloads every loaded plugin in reverse order.
+	///
+	/// Returns the errors of the plugins that failed to disable or unload.
+	#[instrument(level = "trace", skip(self))]
+	pub fn unload(&mut self) -> Vec<Error> {
+		let ids: Vec<_> = self
+			.plugins
+			.iter()
+			.rev()
+			.filter(|entry| entry.module.is_some())
+			.map(|entry| entry.id.clone())
+			.collect();
+		let errors = ids
+			.iter()
+			.filter_map(|id| self.unload_plugin(id).err())
+			.collect();
+
+		self.record(errors)
+	}
+
+	/// Returns a result from enabling the loaded or disabled plugin `id`.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if:
+	///
+	/// - the plugin was not discovered
+	/// - the plugin is not loaded or disabled
+	/// - the plugin failed to enable, leaving it failed
+	#[instrument(level = "trace", skip(self))]
+	pub fn enable_plugin(&mut self, id: &plugin::Id) -> Result<(), Error> {
+		let entry = self.entry_in(id, &[State::Loaded, State::Disabled], "enable")?;
+		let Some(module) = entry.module.as_mut() else {
+			return Err(invalid_transition(entry, "enable"));
+		};
+		match module.enable() {
+			Ok(()) => {
+				entry.state = State::Enabled;
+				info!("plugin \"{}\" enabled", id);
+				Ok(())
+			}
+			Err(source) => {
+				let error = Error::Enable {
+					id: id.clone(),
+					reason: format!("{source:#}"),
+				};
+				entry.state = State::Failed;
+				entry.error = Some(error.clone());
+				Err(error)
+			}
+		}
+	}
+
+	/// Returns a result from disabling the enabled plugin `id`.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if:
+	///
+	/// - the plugin was not discovered
+	/// - the plugin is not enabled
+	/// - the plugin failed to disable, leaving it failed
+	#[instrument(level = "trace", skip(self))]
+	pub fn disable_plugin(&mut self, id: &plugin::Id) -> Result<(), Error> {
+		let entry = self.entry_in(id, &[State::Enabled], "disable")?;
+		let Some(module) = entry.module.as_mut() else {
+			return Err(invalid_transition(entry, "disable"));
+		};
+		match module.disable() {
+			Ok(()) => {
+				entry.state = State::Disabled;
+				info!("plugin \"{}\" disabled", id);
+				Ok(())
+			}
+			Err(source) => {
+				let error = Error::Disable {
+					id: id.clone(),
+					reason: format!("{source:#}"),
+				};
+				entry.state = State::Failed;
+				entry.error = Some(error.clone());
+				Err(error)
+			}
+		}
+	}
+
+	/// Returns a result from unloading the plugin `id`, disabling it first if
+	/// it is enabled.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if:
+	///
+	/// - the plugin was not discovered
+	/// - the plugin is not loaded
+	/// - the plugin failed to disable or unload, leaving it failed
+	#[instrument(level = "trace", skip(self))]
+	pub fn unload_plugin(&mut self, id: &plugin::Id) -> Result<(), Error> {
+		if self.state(id) == Some(State::Enabled) {
+			self.disable_plugin(id)?;
+		}
+		let entry = self.entry_in(
+			id,
+			&[State::Loaded, State::Disabled, State::Failed],
+			"unload",
+		)?;
+		let Some(module) = entry.module.take() else {
+			return Err(invalid_transition(entry, "unload"));
+		};
+		match module.unload() {
+			Ok(()) => {
+				entry.state = State::Unloaded;
+				info!("plugin \"{}\" unloaded", id);
+				Ok(())
+			}
+			Err(source) => {
+				let error = Error::Unload {
+					id: id.clone(),
+					reason: format!("{source:#}"),
+				};
+				entry.state = State::Failed;
+				entry.error = Some(error.clone());
+				Err(error)
+			}
+		}
+	}
+
+	/// Returns the state of the plugin `id`.
+	pub fn state(&self, id: &plugin::Id) -> Option<State> {
+		Some(self.plugins[self.position(id)?].state)
+	}
+
+	/// Returns the error of the last failed transition of the plugin `id`.
+	pub fn error(&self, id: &plugin::Id) -> Option<&Error> {
+		self.plugins[self.position(id)?].error.as_ref()
+	}
+
+	/// Returns the loaded plugin `id`.
+	pub fn module(&self, id: &plugin::Id) -> Option<&L::Module> {
+		self.plugins[self.position(id)?].module.as_ref()
+	}
+
+	/// Returns an iterator over the ids and states of the plugins, in loading
+	/// order once resolved.
+	pub fn plugins(&self) -> impl Iterator<Item = (&plugin::Id, State)> {
+		self.plugins.iter().map(|entry| (&entry.id, entry.state))
+	}
+
+	/// Returns an iterator over the ids of the failed plugins along with the
+	/// error of their failed transition.
+	pub fn failed(&self) -> impl Iterator<Item = (&plugin::Id, Option<&Error>)> {
+		self.plugins
+			.iter()
+			.filter(|entry| entry.state == State::Failed)
+			.map(|entry| (&entry.id, entry.error.as_ref()))
+	}
+
+	fn position(&self, id: &plugin::Id) -> Option<usize> {
+		self.plugins.iter().position(|entry| &entry.id == id)
+	}
+
+	/// Returns the id of a failed dependency of the plugin at `i`.
+	fn failed_dependency(&self, i: usize) -> Option<plugin::Id> {
+		self.plugins[i]
+			.manifest
+			.dependencies
+			.iter()
+			.flat_map(|d| d.keys())
+			.find(|dependency| {
+				!matches!(
+					self.state(dependency),
+					Some(State::Loaded | State::Enabled | State::Disabled)
+				)
+			})
+			.cloned()
+	}
+
+	/// Returns a result to the plugin `id` if it is in one of `states`.
+	fn entry_in(
+		&mut self,
+		id: &plugin::Id,
+		states: &[State],
+		transition: &'static str,
+	) -> Result<&mut Entry<L::Module>, Error> {
+		let i = self
+			.position(id)
+			.ok_or_else(|| Error::NotFound(id.clone()))?;
+		let entry = &mut self.plugins[i];
+		if !states.contains(&entry.state) {
+			return Err(invalid_transition(entry, transition));
+		}
+		Ok(entry)
+	}
+
+	/// Logs `errors` and keeps a copy of the error of each failed plugin.
+	fn record(&mut self, errors: Vec<Error>) -> Vec<Error> {
+		for error in errors.iter() {
+			warn!("{}", error);
+			if let Some(i) = self.position(error.id())
+				&& self.plugins[i].state == State::Failed
+			{
+				self.plugins[i].error = Some(error.clone());
+			}
+		}
+		errors
+	}
+}
+
+fn invalid_transition<M>(entry: &Entry<M>, transition: &'static str) -> Error {
+	Error::InvalidTransition {
+		id: entry.id.clone(),
+		state: entry.state,
+		transition,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+	use anyhow::anyhow;
+	use pretty_assertions::assert_eq;
+
+	use super::*;
+
+	/// Events of the mock plugins in the order they happened.
+	type Events = Rc<RefCell<Vec<String>>>;
+
+	#[derive(Debug, Default)]
+	struct MockLoader {
+		events: Events,
+		/// Ids of the plugins failing to load or enable
+		failing: Vec<(&'static str, &'static str)>,
+	}
+
+	#[derive(Debug)]
+	struct MockModule {
+		id: plugin::Id,
+		events: Events,
+		fails_to_enable: bool,
+	}
+
+	impl Loader for MockLoader {
+		type Module = MockModule;
+
+		fn load(
+			&mut self,
+			id: &plugin::Id,
+			_manifest: &plugin::Manifest,
+		) -> anyhow::Result<Self::Module> {
+			let id_str = id.to_string();
+			if self.failing.contains(&("load", id_str.as_str())) {
+				return Err(anyhow!("mock load failure"));
+			}
+			self.events.borrow_mut().push(format!("load {id}"));
+			Ok(MockModule {
+				id: id.clone(),
+				events: Rc::clone(&self.events),
+				fails_to_enable: self.failing.contains(&("enable", id_str.as_str())),
+			})
+		}
+	}
+
+	impl Module for MockModule {
+		fn enable(&mut self) -> anyhow::Result<()> {
+			if self.fails_to_enable {
+				return Err(anyhow!("mock enable failure"));
+			}
+			self.events.borrow_mut().push(format!("enable {}", self.id));
+			Ok(())
+		}
+
+		fn disable(&mut self) -> anyhow::Result<()> {
+			self.events
+				.borrow_mut()
+				.push(format!("disable {}", self.id));
+			Ok(())
+		}
+
+		fn unload(self) -> anyhow::Result<()> {
+			self.events.borrow_mut().push(format!("unload {}", self.id));
+			Ok(())
+		}
+	}
+
+	fn id(id: &str) -> plugin::Id {
+		plugin::Id::try_from(id).unwrap()
+	}
+
+	/// Returns enabled mods of `(id, priority, dependencies)`.
+	fn enabled_mods(
+		mods: &[(&str, u32, &[&str])],
+	) -> Vec<(plugin::Id, LoadOrderEntry, plugin::Manifest)> {
+		mods.iter()
+			.map(|(mod_id, priority, dependencies)| {
+				let dependencies: HashMap<_, _> = dependencies
+					.iter()
+					.map(|dependency| {
+						(id(dependency), plugin::Dependency::Version("*".to_string()))
+					})
+					.collect();
+				(
+					id(mod_id),
+					LoadOrderEntry {
+						enabled: true,
+						priority: *priority,
+						..Default::default()
+					},
+					plugin::Manifest {
+						dependencies: Some(dependencies),
+						..Default::default()
+					},
+				)
+			})
+			.collect()
+	}
+
+	fn states(lifecycle: &Lifecycle<MockLoader>) -> Vec<(String, State)> {
+		lifecycle
+			.plugins()
+			.map(|(id, state)| (id.to_string(), state))
+			.collect()
+	}
+
+	#[test]
+	fn lifecycle_order() {
+		let loader = MockLoader::default();
+		let events = Rc::clone(&loader.events);
+		let mut lifecycle = Lifecycle::new(loader);
+		lifecycle.discover(enabled_mods(&[
+			("com.example.c", 0, &["com.example.b"]),
+			("com.example.a", 2, &[]),
+			("com.example.b", 1, &["com.example.a"]),
+			("com.example.d", 3, &[]),
+		]));
+		assert_eq!(
+			lifecycle.state(&id("com.example.a")),
+			Some(State::Discovered)
+		);
+
+		assert_eq!(lifecycle.resolve(), []);
+		assert_eq!(lifecycle.load(), []);
+		assert_eq!(lifecycle.enable(), []);
+		assert_eq!(
+			states(&lifecycle),
+			[
+				("com.example.a".to_string(), State::Enabled),
+				("com.example.b".to_string(), State::Enabled),
+				("com.example.c".to_string(), State::Enabled),
+				("com.example.d".to_string(), State::Enabled),
+			]
+		);
+
+		lifecycle.disable_plugin(&id("com.example.b")).unwrap();
+		assert_eq!(lifecycle.state(&id("com.example.b")), Some(State::Disabled));
+		assert!(matches!(
+			lifecycle.disable_plugin(&id("com.example.b")),
+			Err(Error::InvalidTransition {
+				state: State::Disabled,
+				..
+			})
+		));
+		lifecycle.enable_plugin(&id("com.example.b")).unwrap();
+
+		assert_eq!(lifecycle.unload(), []);
+		assert_eq!(
+			events.borrow().as_slice(),
+			[
+				"load com.example.a",
+				"load com.example.b",
+				"load com.example.c",
+				"load com.example.d",
+				"enable com.example.a",
+				"enable com.example.b",
+				"enable com.example.c",
+				"enable com.example.d",
+				"disable com.example.b",
+				"enable com.example.b",
+				"disable com.example.d",
+				"unload com.example.d",
+				"disable com.example.c",
+				"unload com.example.c",
+				"disable com.example.b",
+				"unload com.example.b",
+				"disable com.example.a",
+				"unload com.example.a",
+			]
+		);
+		assert!(
+			lifecycle
+				.plugins()
+				.all(|(_, state)| state == State::Unloaded)
+		);
+		assert!(matches!(
+			lifecycle.enable_plugin(&id("com.example.missing")),
+			Err(Error::NotFound(_))
+		));
+	}
+
+	#[test]
+	fn lifecycle_failures() {
+		let loader = MockLoader {
+			failing: vec![("load", "com.example.b"), ("enable", "com.example.e")],
+			..Default::default()
+		};
+		let mut lifecycle = Lifecycle::new(loader);
+		lifecycle.discover(enabled_mods(&[
+			("com.example.a", 0, &["com.example.missing"]),
+			("com.example.b", 1, &[]),
+			("com.example.c", 2, &["com.example.b"]),
+			("com.example.d", 3, &["com.example.a"]),
+			("com.example.e", 4, &[]),
+			("com.example.f", 5, &["com.example.g"]),
+			("com.example.g", 6, &["com.example.f"]),
+		]));
+
+		let errors: Vec<_> = lifecycle
+			.resolve()
+			.iter()
+			.map(ToString::to_string)
+			.collect();
+		assert_eq!(
+			errors,
+			[
+				"failed to resolve \"com.example.a\": dependency \"com.example.missing\" is not enabled",
+				"failed to resolve \"com.example.d\": dependency \"com.example.a\" failed",
+				"failed to resolve \"com.example.f\": dependencies form a cycle",
+				"failed to resolve \"com.example.g\": dependencies form a cycle",
+			]
+		);
+		let errors: Vec<_> = lifecycle.load().iter().map(ToString::to_string).collect();
+		assert_eq!(
+			errors,
+			[
+				"failed to load \"com.example.b\": mock load failure",
+				"failed to load \"com.example.c\": dependency \"com.example.b\" is not loaded",
+			]
+		);
+		let errors: Vec<_> = lifecycle.enable().iter().map(ToString::to_string).collect();
+		assert_eq!(
+			errors,
+			["failed to enable \"com.example.e\": mock enable failure"]
+		);
+
+		let failed: Vec<_> = lifecycle
+			.failed()
+			.map(|(id, error)| (id.to_string(), error.map(Error::id).cloned()))
+			.collect();
+		assert_eq!(failed.len(), 7);
+		assert!(
+			failed
+				.iter()
+				.all(|(i, error)| error.as_ref() == Some(&id(i)))
+		);
+		assert!(lifecycle.module(&id("com.example.e")).is_some());
+
+		let unmet = crate::Error::new(
+			lifecycle.error(&id("com.example.d")).unwrap().clone(),
+			"failed to load plugins",
+		);
+		assert!(matches!(
+			unmet.kind(),
+			crate::ErrorKind::DependencyUnmet { id: i, dependency }
+				if i == &id("com.example.d") && dependency == &id("com.example.a")
+		));
+		let other = crate::Error::new(
+			lifecycle.error(&id("com.example.b")).unwrap().clone(),
+			"failed to load plugins",
+		);
+		assert!(matches!(other.kind(), crate::ErrorKind::Other(_)));
+
+		assert_eq!(lifecycle.unload(), []);
+		assert_eq!(lifecycle.state(&id("com.example.e")), Some(State::Unloaded));
+	}
+}
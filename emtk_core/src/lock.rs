@@ -0,0 +1,326 @@
+//! Cross-process locking of instances so only one process, such as the GUI or
+//! the CLI, manages an instance at a time.
+
+use std::{
+	collections::HashMap,
+	fs, io,
+	path::{Path, PathBuf},
+	process,
+	sync::{Arc, LazyLock, Mutex, PoisonError, Weak},
+	thread,
+	time::Duration,
+};
+
+use anyhow::anyhow;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+use tracing::{info, instrument, warn};
+
+use crate::{Error, ErrorKind, Result};
+
+/// The number of times creating a lock file is attempted before giving up.
+const ATTEMPTS: u32 = 5;
+
+/// The delay before retrying to read a lock file that is empty or unreadable,
+/// doubled after every attempt.
+const BACKOFF: Duration = Duration::from_millis(20);
+
+/// Locks held by the current process. Building the same instance more than once
+/// within a process shares a single lock.
+static HELD: LazyLock<Mutex<HashMap<PathBuf, Weak<Lock>>>> = LazyLock::new(Default::default);
+
+/// The process that wrote a lock file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Owner {
+	pid: u32,
+	/// Start time of the process in seconds since the unix epoch, used to tell
+	/// apart processes reusing the same PID. This is `None` for lock files that
+	/// only contain a PID.
+	start_time: Option<u64>,
+}
+
+impl Owner {
+	#[instrument(level = "trace")]
+	fn current() -> Self {
+		let pid = process::id();
+		Self {
+			pid,
+			start_time: process_start_time(pid),
+		}
+	}
+
+	/// Returns the contents of a lock file written by the process.
+	fn record(&self) -> String {
+		match self.start_time {
+			Some(start_time) => format!("{} {}", self.pid, start_time),
+			None => self.pid.to_string(),
+		}
+	}
+
+	#[instrument(level = "trace")]
+	fn read(path: &Path) -> io::Result<Option<Self>> {
+		let buffer = fs::read_to_string(path)?;
+		Ok(Self::parse(&buffer))
+	}
+
+	/// Returns the owner recorded in the contents of a lock file, or `None` if
+	/// it is empty or unparsable.
+	fn parse(record: &str) -> Option<Self> {
+		let mut parts = record.split_whitespace();
+		let pid = parts.next()?.parse().ok()?;
+		let start_time = parts.next().and_then(|part| part.parse().ok());
+		Some(Self { pid, start_time })
+	}
+
+	/// Returns true if the process that wrote the lock file is still running.
+	#[instrument(level = "trace")]
+	fn is_alive(&self) -> bool {
+		match (process_start_time(self.pid), self.start_time) {
+			(Some(actual), Some(expected)) => actual == expected,
+			(Some(_), None) => true,
+			(None, _) => false,
+		}
+	}
+}
+
+/// Returns the start time of the running process with `pid`, or `None` if no
+/// such process is running.
+#[instrument(level = "trace")]
+fn process_start_time(pid: u32) -> Option<u64> {
+	let pid = Pid::from_u32(pid);
+	let mut system = System::new();
+	system.refresh_processes_specifics(
+		ProcessesToUpdate::Some(&[pid]),
+		true,
+		ProcessRefreshKind::nothing(),
+	);
+	system.process(pid).map(|process| process.start_time())
+}
+
+/// A lock file held by the current process. The lock file is removed once the
+/// last [`Arc`] to the lock is dropped.
+#[derive(Debug)]
+pub struct Lock {
+	path: PathBuf,
+}
+
+impl Lock {
+	/// Returns a result to the lock of the lock file at `path`.
+	///
+	/// If the current process already holds the lock, the same lock is returned.
+	/// A lock file left behind by a process that is no longer running is
+	/// considered stale and is replaced. The lock file is written to a temporary
+	/// file and linked into place, so it is never seen without its owner. An
+	/// empty or unreadable lock file is considered held and is read again after a
+	/// backoff, which blocks the current thread.
+	///
+	/// # Errors
+	///
+	/// This function will return an error of [`ErrorKind::InstanceLocked`] if
+	/// another running process holds the lock and `force` is false.
+	///
+	/// This function will also return an error if the lock file is still
+	/// unreadable after every attempt and `force` is false.
+	///
+	/// Errors may also be returned according to:
+	///
+	/// - `std::fs::hard_link`
+	/// - `std::fs::read_to_string`
+	/// - `std::fs::write`
+	#[instrument(level = "trace")]
+	pub fn acquire(path: &Path, force: bool) -> Result<Arc<Self>> {
+		let mut held = HELD.lock().unwrap_or_else(PoisonError::into_inner);
+		if let Some(lock) = held.get(path).and_then(Weak::upgrade) {
+			info!("lock already held by the current process");
+			return Ok(lock);
+		}
+
+		let current = Owner::current();
+		let temp_path =
+			crate::temp_path(path).map_err(Error::msg("failed to get temporary lock file path"))?;
+		fs::write(&temp_path, current.record())
+			.map_err(Error::msg("failed to write process ID to lock file"))?;
+		let result = Self::link(path, &temp_path, current, force);
+		let _ = fs::remove_file(&temp_path)
+			.map_err(|e| warn!("failed to remove temporary lock file: {e}"));
+		result?;
+		info!("process ID recorded to lock file");
+
+		let lock = Arc::new(Self {
+			path: path.to_path_buf(),
+		});
+		held.insert(path.to_path_buf(), Arc::downgrade(&lock));
+		Ok(lock)
+	}
+
+	/// Returns a result to linking the lock file at `temp_path`, owned by
+	/// `current`, into place at `path`.
+	#[instrument(level = "trace")]
+	fn link(path: &Path, temp_path: &Path, current: Owner, force: bool) -> Result<()> {
+		let mut backoff = BACKOFF;
+		for attempt in 1..=ATTEMPTS {
+			let e = match fs::hard_link(temp_path, path) {
+				Ok(()) => return Ok(()),
+				Err(e) => e,
+			};
+			if e.kind() != io::ErrorKind::AlreadyExists {
+				return Err(Error::new(e, "failed to create lock file"));
+			}
+
+			let record = match fs::read_to_string(path) {
+				Ok(record) => record,
+				// the lock file was released in the meantime
+				Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+				Err(e) => return Err(Error::new(e, "failed to read lock file")),
+			};
+			match Owner::parse(&record) {
+				Some(owner) if owner.pid != current.pid && owner.is_alive() => {
+					if !force {
+						return Err(Error::new(
+							ErrorKind::InstanceLocked { pid: owner.pid },
+							"failed to acquire lock",
+						));
+					}
+					warn!("forcefully taking lock from process with PID {}", owner.pid);
+				}
+				Some(_) => warn!("replacing stale lock file"),
+				None if attempt < ATTEMPTS => {
+					// the lock file may be written by a process that does not link it
+					// into place, give it time to finish
+					thread::sleep(backoff);
+					backoff *= 2;
+					continue;
+				}
+				None if force => warn!("forcefully replacing unreadable lock file"),
+				None => {
+					return Err(Error::new(
+						anyhow!("lock file \"{}\" is unreadable", path.display()),
+						"failed to acquire lock",
+					));
+				}
+			}
+
+			// only remove the lock file that was read, another process may have
+			// replaced it in the meantime
+			if fs::read_to_string(path).is_ok_and(|buffer| buffer == record) {
+				fs::remove_file(path).map_err(Error::msg("failed to remove lock file"))?;
+			}
+		}
+
+		fs::hard_link(temp_path, path).map_err(Error::msg("failed to create lock file"))
+	}
+
+	/// Returns the path to the lock file.
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+}
+
+impl Drop for Lock {
+	fn drop(&mut self) {
+		let mut held = HELD.lock().unwrap_or_else(PoisonError::into_inner);
+		if held
+			.get(&self.path)
+			.is_some_and(|lock| lock.strong_count() > 0)
+		{
+			// the lock was acquired again by the current process
+			return;
+		}
+		held.remove(&self.path);
+
+		if let Ok(Some(owner)) = Owner::read(&self.path)
+			&& owner.pid == process::id()
+		{
+			let _ =
+				fs::remove_file(&self.path).map_err(|e| warn!("failed to remove lock file: {e}"));
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{fs, process};
+
+	use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+	use tempfile::tempdir;
+
+	use super::Lock;
+	use crate::ErrorKind;
+
+	/// Returns the PID of a process other than the current one that is running.
+	fn parent_pid() -> u32 {
+		let pid = Pid::from_u32(process::id());
+		let mut system = System::new();
+		system.refresh_processes_specifics(
+			ProcessesToUpdate::Some(&[pid]),
+			true,
+			ProcessRefreshKind::nothing(),
+		);
+		system.process(pid).unwrap().parent().unwrap().as_u32()
+	}
+
+	#[test]
+	fn acquire_and_release() {
+		let cwd = tempdir().unwrap();
+		let path = cwd.path().join("instance.lock");
+
+		let lock = Lock::acquire(&path, false).unwrap();
+		let buffer = fs::read_to_string(&path).unwrap();
+		assert!(buffer.starts_with(&process::id().to_string()));
+
+		let shared = Lock::acquire(&path, false).unwrap();
+		drop(lock);
+		assert!(path.is_file());
+		drop(shared);
+		assert!(!path.exists());
+	}
+
+	#[test]
+	fn locked_by_other_process() {
+		let cwd = tempdir().unwrap();
+		let path = cwd.path().join("instance.lock");
+		fs::write(&path, parent_pid().to_string()).unwrap();
+
+		assert!(matches!(
+			Lock::acquire(&path, false).unwrap_err().kind(),
+			ErrorKind::InstanceLocked { .. }
+		));
+		let lock = Lock::acquire(&path, true).unwrap();
+		let buffer = fs::read_to_string(&path).unwrap();
+		assert!(buffer.starts_with(&process::id().to_string()));
+		drop(lock);
+		assert!(!path.exists());
+	}
+
+	#[test]
+	fn stale_lock() {
+		let cwd = tempdir().unwrap();
+		let path = cwd.path().join("instance.lock");
+
+		// a PID that is too large to be in use
+		fs::write(&path, u32::MAX.to_string()).unwrap();
+		assert!(Lock::acquire(&path, false).is_ok());
+
+		// the PID of a running process that started at a different time
+		fs::write(&path, format!("{} 1", parent_pid())).unwrap();
+		assert!(Lock::acquire(&path, false).is_ok());
+	}
+
+	#[test]
+	fn unreadable_lock() {
+		let cwd = tempdir().unwrap();
+		let path = cwd.path().join("instance.lock");
+
+		// an empty lock file may still be written by its owner
+		for record in ["", "not a PID"] {
+			fs::write(&path, record).unwrap();
+			assert!(Lock::acquire(&path, false).is_err());
+			assert_eq!(fs::read_to_string(&path).unwrap(), record);
+		}
+
+		let lock = Lock::acquire(&path, true).unwrap();
+		let buffer = fs::read_to_string(&path).unwrap();
+		assert!(buffer.starts_with(&process::id().to_string()));
+		drop(lock);
+		assert_eq!(fs::read_dir(cwd.path()).unwrap().count(), 0);
+	}
+}
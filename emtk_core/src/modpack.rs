@@ -0,0 +1,413 @@
+use std::{fmt, path::Path};
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tracing::{info, instrument, warn};
+
+use crate::{Error, Result, TomlError, prelude::*, profile::LoadOrderEntry};
+
+pub mod prelude {
+	pub use crate::modpack::{self, Modpack};
+}
+
+/// A single mod of a [`Modpack`] along with the state it had in the exported
+/// profile.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Entry {
+	pub id: plugin::Id,
+	pub enabled: bool,
+	pub priority: u32,
+	/// Version of the mod from its manifest at the time of exporting. This is
+	/// `None` if the mod was not installed.
+	#[serde(default)]
+	pub version: Option<String>,
+	/// Settings of the mod at the time of exporting. This is `None` if the mod
+	/// does not have a `plugin::Settings::TOML` file.
+	#[serde(default)]
+	pub settings: Option<plugin::Settings>,
+}
+
+/// A portable snapshot of a profile's load order, mod versions and mod settings
+/// that can be shared with other users and imported into any instance.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Modpack {
+	/// The version of the modpack format, see `Modpack::FORMAT_VERSION`.
+	pub format_version: u32,
+	/// Name of the profile the modpack was exported from
+	pub name: String,
+	/// Mods of the modpack sorted by load order priority
+	#[serde(rename = "mod")]
+	#[serde(default)]
+	pub mods: Vec<Entry>,
+}
+
+impl Modpack {
+	/// The file extension given to exported modpack files.
+	pub const EXTENSION: &str = "emtkpack";
+
+	/// The current version of the modpack format. Modpacks with a newer version
+	/// can not be imported.
+	pub const FORMAT_VERSION: u32 = 1;
+
+	/// Returns a result to a modpack created from the profile's load order along
+	/// with the version and settings of each installed mod.
+	///
+	/// # Errors
+	///
+	/// Errors may be returned according to:
+	///
+	/// - `Profile::game_dir`
+	#[instrument(level = "trace")]
+	pub async fn from_profile(profile: &Profile) -> Result<Self> {
+		let game_dir = profile.game_dir().await?;
+		let name = profile
+			.path()
+			.file_name()
+			.map(|name| name.display().to_string())
+			.unwrap_or_default();
+
+		let mut load_order: Vec<_> = profile.load_order().iter().collect();
+		load_order.sort_by_key(|(_, entry)| entry.priority);
+
+		let mut mods = Vec::with_capacity(load_order.len());
+		for (id, entry) in load_order {
+			let version = match fs::read_to_string(game_dir.join(id.manifest_file())).await {
+				Ok(buffer) => toml::from_str::<plugin::Manifest>(&buffer)
+					.map(|manifest| manifest.plugin.version)
+					.map_err(|e| warn!("failed to deserialize manifest of \"{id}\": {e}"))
+					.ok(),
+				Err(_) => None,
+			};
+			let settings = match fs::read_to_string(game_dir.join(id.settings_file())).await {
+				Ok(buffer) => toml::from_str::<plugin::Settings>(&buffer)
+					.map_err(|e| warn!("failed to deserialize settings of \"{id}\": {e}"))
+					.ok(),
+				Err(_) => None,
+			};
+
+			mods.push(Entry {
+				id: id.clone(),
+				enabled: entry.enabled,
+				priority: entry.priority,
+				version,
+				settings,
+			});
+		}
+		info!("modpack created from profile");
+
+		Ok(Self {
+			format_version: Self::FORMAT_VERSION,
+			name,
+			mods,
+		})
+	}
+
+	/// Returns a result to a modpack deserialized from the file at `path`.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the modpack's format version is newer
+	/// than `Modpack::FORMAT_VERSION`.
+	///
+	/// Errors may also be returned according to:
+	///
+	/// - `tokio::fs::read_to_string`
+	/// - `toml::from_str`
+	#[instrument(level = "trace")]
+	pub async fn read<P: AsRef<Path> + fmt::Debug>(path: P) -> Result<Self> {
+		let buffer = fs::read_to_string(path)
+			.await
+			.map_err(Error::msg("failed to read modpack into buffer"))?;
+		info!("modpack read into buffer");
+		let modpack: Self = toml::from_str(&buffer)
+			.map_err(TomlError::from)
+			.map_err(Error::msg("failed to deserialize modpack from buffer"))?;
+		info!("modpack deserialized from buffer");
+
+		if modpack.format_version > Self::FORMAT_VERSION {
+			return Err(Error::new(
+				anyhow!(
+					"modpack format version {} is newer than the supported version {}",
+					modpack.format_version,
+					Self::FORMAT_VERSION
+				),
+				"failed to read modpack",
+			));
+		}
+
+		Ok(modpack)
+	}
+
+	/// Returns a result from attempting to serialize the modpack into the file at
+	/// `path`.
+	///
+	/// # Errors
+	///
+	/// Errors may be returned according to:
+	///
+	/// - `toml::to_string`
+	/// - `tokio::fs::write`
+	#[instrument(level = "trace")]
+	pub async fn write<P: AsRef<Path> + fmt::Debug>(&self, path: P) -> Result<()> {
+		let buffer = toml::to_string(self)
+			.map_err(TomlError::from)
+			.map_err(Error::msg("failed to serialize modpack into buffer"))?;
+		info!("modpack serialized to buffer");
+		crate::write_atomic(path.as_ref(), buffer)
+			.await
+			.map_err(Error::msg("failed to write modpack buffer into file"))?;
+		info!("finished writing modpack to file");
+
+		Ok(())
+	}
+
+	/// Returns the load order described by the modpack.
+	#[instrument(level = "trace")]
+	pub fn load_order(&self) -> profile::LoadOrder {
+		self.mods
+			.iter()
+			.map(|entry| {
+				(
+					entry.id.clone(),
+					LoadOrderEntry::new(entry.enabled, entry.priority, None, entry.version.clone()),
+				)
+			})
+			.collect()
+	}
+}
+
+/// A mod of an imported [`Modpack`] whose installed version differs from the
+/// version it was exported with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionMismatch {
+	pub id: plugin::Id,
+	pub expected: String,
+	pub found: String,
+}
+
+/// The outcome of importing a [`Modpack`] into an instance.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportReport {
+	/// Mods of the modpack that are not installed in the instance
+	pub missing: Vec<plugin::Id>,
+	/// Mods of the modpack that are installed with a different version
+	pub version_mismatches: Vec<VersionMismatch>,
+	/// Mods whose settings were kept because settings already exist in the
+	/// instance, which every profile shares
+	pub kept_settings: Vec<plugin::Id>,
+	/// Mods whose settings could not be restored along with the reason
+	pub failed_settings: Vec<(plugin::Id, String)>,
+}
+
+impl ImportReport {
+	/// Returns true if every mod of the modpack is installed with the expected
+	/// version and its settings were not lost.
+	pub fn is_complete(&self) -> bool {
+		self.missing.is_empty()
+			&& self.version_mismatches.is_empty()
+			&& self.failed_settings.is_empty()
+	}
+}
+
+impl Instance {
+	/// Returns a result to a modpack exported from the instance's current profile.
+	///
+	/// See [`Modpack::from_profile`].
+	#[instrument(level = "trace")]
+	pub async fn export_modpack(&self) -> Result<Modpack> {
+		Modpack::from_profile(&self.profile).await
+	}
+
+	/// Returns a result to a new profile named `name` recreated from the modpack
+	/// along with a report of mods that are missing or installed with a different
+	/// version. Such mods are left out of the new profile's load order.
+	///
+	/// Settings of a mod are only restored when the installed version matches the
+	/// modpack and the mod does not have settings yet, since settings are shared
+	/// by every profile of the instance. The instance will continue to use its
+	/// current profile.
+	///
+	/// # Errors
+	///
+	/// Errors may be returned according to:
+	///
+	/// - `Instance::create_profile`
+	/// - `Profile::set_load_order`
+	#[instrument(level = "trace")]
+	pub async fn import_modpack(
+		&self,
+		modpack: &Modpack,
+		name: &str,
+	) -> Result<(Profile, ImportReport)> {
+		let mut profile = self.create_profile(name).await?;
+		let mut report = ImportReport::default();
+
+		for entry in &modpack.mods {
+			let manifest_path = self.path().join(entry.id.manifest_file());
+			let Ok(buffer) = fs::read_to_string(manifest_path).await else {
+				warn!("mod from modpack is not installed \"{}\"", entry.id);
+				report.missing.push(entry.id.clone());
+				continue;
+			};
+			let Ok(manifest) = toml::from_str::<plugin::Manifest>(&buffer) else {
+				warn!("failed to deserialize manifest of \"{}\"", entry.id);
+				report.missing.push(entry.id.clone());
+				continue;
+			};
+
+			if let Some(expected) = &entry.version
+				&& *expected != manifest.plugin.version
+			{
+				warn!("mod from modpack has a different version \"{}\"", entry.id);
+				report.version_mismatches.push(VersionMismatch {
+					id: entry.id.clone(),
+					expected: expected.clone(),
+					found: manifest.plugin.version,
+				});
+				continue;
+			}
+
+			let Some(settings) = &entry.settings else {
+				continue;
+			};
+			let settings_path = self.path().join(entry.id.settings_file());
+			if settings_path.exists() {
+				warn!("kept existing settings of \"{}\"", entry.id);
+				report.kept_settings.push(entry.id.clone());
+				continue;
+			}
+			let result = async {
+				let buffer = toml::to_string(settings)
+					.map_err(TomlError::from)
+					.map_err(Error::msg("failed to serialize mod settings into buffer"))?;
+				crate::write_atomic(&settings_path, buffer)
+					.await
+					.map_err(Error::msg("failed to write mod settings buffer into file"))
+			}
+			.await;
+			if let Err(e) = result {
+				warn!("{e}");
+				report
+					.failed_settings
+					.push((entry.id.clone(), e.to_string()));
+			}
+		}
+
+		// merge with any mods that were discovered while building the profile
+		let mut load_order = profile.load_order().clone();
+		let offset = modpack.mods.len() as u32;
+		for entry in load_order.values_mut() {
+			entry.priority += offset;
+		}
+		load_order.extend(modpack.load_order().into_iter().filter(|(id, _)| {
+			!report.missing.contains(id)
+				&& !report
+					.version_mismatches
+					.iter()
+					.any(|mismatch| mismatch.id == *id)
+		}));
+		profile.set_load_order(load_order).await?;
+		let profile = Profile::with_path(profile.path()).await?.build().await?;
+		info!("modpack imported into profile");
+
+		Ok((profile, report))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fs;
+
+	use pretty_assertions::assert_eq;
+	use tempfile::tempdir;
+
+	use crate::{instance::tests::dummy_instance, prelude::*};
+
+	fn dummy_mod(instance: &Instance, id: &str, version: &str) {
+		let mod_dir = instance.path().join(Instance::MODS_DIR).join(id);
+		fs::create_dir_all(&mod_dir).unwrap();
+		fs::write(
+			mod_dir.join(plugin::Manifest::TOML),
+			format!("[plugin]\nname = \"{id}\"\nversion = \"{version}\"\n"),
+		)
+		.unwrap();
+	}
+
+	#[tokio::test]
+	async fn export_import_modpack() {
+		let cwd = tempdir().unwrap();
+		let mut instance = dummy_instance(&cwd).await;
+		dummy_mod(&instance, "com.example.first", "1.0.0");
+		dummy_mod(&instance, "com.example.second", "1.0.0");
+		dummy_mod(&instance, "com.example.third", "1.0.0");
+		dummy_mod(&instance, "com.example.fourth", "1.0.0");
+		for id in ["com.example.first", "com.example.fourth"] {
+			fs::write(
+				instance
+					.path()
+					.join(Instance::MODS_DIR)
+					.join(id)
+					.join(plugin::Settings::TOML),
+				"[[widget]]\nkind = \"checkbox\"\nlabel = \"Enabled\"\nvalue = true\n",
+			)
+			.unwrap();
+		}
+		let profile = instance.create_profile("Exported").await.unwrap();
+		instance.set_profile(profile).await.unwrap();
+		let first = plugin::Id::try_from("com.example.first").unwrap();
+		instance
+			.profile
+			.load_order_mut()
+			.get_mut(&first)
+			.unwrap()
+			.enabled = true;
+
+		let modpack = instance.export_modpack().await.unwrap();
+		let modpack_path = cwd
+			.path()
+			.join("exported")
+			.with_extension(Modpack::EXTENSION);
+		modpack.write(&modpack_path).await.unwrap();
+		let modpack = Modpack::read(&modpack_path).await.unwrap();
+		assert_eq!(modpack.name, "Exported");
+		assert_eq!(modpack.mods.len(), 4);
+		assert!(
+			modpack
+				.mods
+				.iter()
+				.find(|entry| entry.id == first)
+				.unwrap()
+				.settings
+				.is_some()
+		);
+
+		let other_cwd = tempdir().unwrap();
+		let other_instance = dummy_instance(&other_cwd).await;
+		dummy_mod(&other_instance, "com.example.first", "1.0.0");
+		dummy_mod(&other_instance, "com.example.second", "2.0.0");
+		dummy_mod(&other_instance, "com.example.fourth", "1.0.0");
+		let fourth = plugin::Id::try_from("com.example.fourth").unwrap();
+		let fourth_settings = other_instance.path().join(fourth.settings_file());
+		fs::write(&fourth_settings, "").unwrap();
+
+		let (profile, report) = other_instance
+			.import_modpack(&modpack, "Imported")
+			.await
+			.unwrap();
+		assert!(profile.load_order().get(&first).unwrap().enabled);
+		assert_eq!(
+			report.missing,
+			vec![plugin::Id::try_from("com.example.third").unwrap()]
+		);
+		assert_eq!(report.version_mismatches.len(), 1);
+		assert_eq!(report.version_mismatches[0].expected, "1.0.0");
+		assert_eq!(report.version_mismatches[0].found, "2.0.0");
+		assert!(other_instance.path().join(first.settings_file()).is_file());
+		assert_eq!(report.kept_settings, vec![fourth]);
+		assert_eq!(fs::read_to_string(fourth_settings).unwrap(), "");
+		assert!(report.failed_settings.is_empty());
+		let third = plugin::Id::try_from("com.example.third").unwrap();
+		assert!(profile.load_order().get(&third).is_none());
+	}
+}
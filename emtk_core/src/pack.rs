@@ -0,0 +1,504 @@
+//! Packing of a mod directory into a distributable archive for mod authors.
+
+use std::{
+	fmt::Write as _,
+	fs::File,
+	io::Write,
+	path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+use emtk_asset::Package;
+use sha2::{Digest, Sha256};
+use tokio::fs;
+use tracing::{info, instrument};
+
+use crate::{Error, Result, TomlError, install::ArchiveKind, prelude::*};
+
+/// The name of the file in a packed mod listing the SHA-256 checksum of every
+/// other file in the mod. The format is compatible with `sha256sum --check`.
+pub const CHECKSUMS: &str = "checksums.sha256";
+
+/// The outcome of packing a mod.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Packed {
+	pub id: plugin::Id,
+	/// Version of the mod from its manifest
+	pub version: String,
+	/// Paths of the packed files relative to the mod's directory, excluding
+	/// [`CHECKSUMS`]
+	pub files: Vec<String>,
+}
+
+/// Returns a result to the id and manifest of the mod at `mod_dir` after
+/// validating the mod is ready to be distributed.
+///
+/// # Errors
+///
+/// This function will return an error if:
+///
+/// - the name of `mod_dir` is not a valid `plugin::Id`
+/// - the mod does not have a `plugin::Manifest::TOML` file or it is invalid
+/// - the manifest's name or version is empty
+/// - an id of a dependency or conflict in the manifest is not a valid
+///   `plugin::Id`
+/// - the mod has a `plugin::Settings::TOML` file that is invalid
+#[instrument(level = "trace")]
+pub async fn validate_mod(mod_dir: &Path) -> Result<(plugin::Id, plugin::Manifest)> {
+	let invalid = |reason: String| Error::new(anyhow!(reason), "failed to validate mod");
+
+	let dir_name = mod_dir
+		.file_name()
+		.map(|name| name.display().to_string())
+		.unwrap_or_default();
+	let id = plugin::Id::try_from(dir_name.as_str())
+		.map_err(Error::msg("directory name of mod is not a valid id"))?;
+
+	let manifest_path = mod_dir.join(plugin::Manifest::TOML);
+	if !manifest_path.is_file() {
+		return Err(invalid(format!(
+			"mod is missing {}",
+			plugin::Manifest::TOML
+		)));
+	}
+	let manifest = plugin::Manifest::read(&manifest_path).await?;
+	if manifest.plugin.name.trim().is_empty() {
+		return Err(invalid("manifest's plugin name is empty".into()));
+	}
+	if manifest.plugin.version.trim().is_empty() {
+		return Err(invalid("manifest's plugin version is empty".into()));
+	}
+	let related_ids = manifest
+		.dependencies
+		.iter()
+		.flat_map(|dependencies| dependencies.keys())
+		.chain(
+			manifest
+				.conflicts
+				.iter()
+				.flat_map(|conflicts| conflicts.keys()),
+		);
+	for related_id in related_ids {
+		let related_id = related_id.to_string();
+		if !plugin::Id::is_valid(&related_id) {
+			return Err(invalid(format!(
+				"manifest references an invalid id \"{related_id}\""
+			)));
+		}
+	}
+	info!("mod manifest is valid");
+
+	let settings_path = mod_dir.join(plugin::Settings::TOML);
+	if settings_path.is_file() {
+		let buffer = fs::read_to_string(&settings_path)
+			.await
+			.map_err(Error::msg("failed to read mod settings into buffer"))?;
+		toml::from_str::<plugin::Settings>(&buffer)
+			.map_err(TomlError::from)
+			.map_err(Error::msg("failed to deserialize mod settings from buffer"))?;
+		info!("mod settings are valid");
+	}
+
+	Ok((id, manifest))
+}
+
+/// Returns a result to the entries of `dir` along with their names, sorted by
+/// path.
+fn read_entries(dir: &Path) -> Result<Vec<(PathBuf, String)>> {
+	let mut entries = std::fs::read_dir(dir)
+		.map_err(Error::msg("failed to read mod directory entries"))?
+		.map(|entry| entry.map(|entry| entry.path()))
+		.collect::<std::io::Result<Vec<PathBuf>>>()
+		.map_err(Error::msg("failed to read next entry in mod directory"))?;
+	entries.sort();
+
+	entries
+		.into_iter()
+		.map(|entry_path| {
+			let Some(name) = entry_path.file_name().and_then(|name| name.to_str()) else {
+				return Err(Error::new(
+					anyhow!("\"{}\" is not valid UTF-8", entry_path.display()),
+					"failed to pack mod",
+				));
+			};
+			let name = name.to_string();
+			Ok((entry_path, name))
+		})
+		.collect()
+}
+
+/// Returns a result to the bytes of the package built from the loose files in
+/// `dir`.
+fn build_package(dir: &Path, relative: &str) -> Result<Vec<u8>> {
+	let mut bytes = Vec::new();
+	Package::write_dir(dir, &mut bytes)
+		.map_err(|e| Error::new(anyhow!(e.to_string()), "failed to build package"))?;
+	info!("loose files built into package \"{}\"", relative);
+
+	Ok(bytes)
+}
+
+/// Reads the packages inside of `packages_dir` into `files` with the path
+/// relative to the mod's directory. If `prebuild` is true, each directory of
+/// loose files for a game package, such as `Resource`, is packed into a package
+/// file named after it, such as `Resource.rpk`.
+#[instrument(level = "trace", skip(files))]
+fn collect_packages(
+	packages_dir: &Path,
+	relative: &str,
+	prebuild: bool,
+	files: &mut Vec<(String, Vec<u8>)>,
+) -> Result<()> {
+	if !prebuild {
+		return collect_dir(packages_dir, relative, files);
+	}
+
+	for (entry_path, name) in read_entries(packages_dir)? {
+		if entry_path.is_dir() {
+			let name = if is_package(&entry_path) {
+				name
+			} else {
+				format!("{name}.rpk")
+			};
+			let entry_relative = format!("{relative}/{name}");
+			let bytes = build_package(&entry_path, &entry_relative)?;
+			files.push((entry_relative, bytes));
+		} else {
+			let bytes =
+				std::fs::read(&entry_path).map_err(Error::msg("failed to read mod file"))?;
+			files.push((format!("{relative}/{name}"), bytes));
+		}
+	}
+
+	Ok(())
+}
+
+/// Returns true if `path` has an `.rpk` extension.
+fn is_package(path: &Path) -> bool {
+	path.extension()
+		.is_some_and(|extension| extension.eq_ignore_ascii_case("rpk"))
+}
+
+/// Recursively reads every file inside of `dir` into `files` with the path
+/// relative to the mod's directory.
+#[instrument(level = "trace", skip(files))]
+fn collect_dir(dir: &Path, relative: &str, files: &mut Vec<(String, Vec<u8>)>) -> Result<()> {
+	for (entry_path, name) in read_entries(dir)? {
+		let entry_relative = format!("{relative}/{name}");
+
+		if entry_path.is_dir() {
+			collect_dir(&entry_path, &entry_relative, files)?;
+		} else {
+			let bytes =
+				std::fs::read(&entry_path).map_err(Error::msg("failed to read mod file"))?;
+			files.push((entry_relative, bytes));
+		}
+	}
+
+	Ok(())
+}
+
+/// Returns the hexadecimal SHA-256 checksum of `bytes`.
+#[instrument(level = "trace", skip(bytes))]
+pub(crate) fn sha256(bytes: &[u8]) -> String {
+	Sha256::digest(bytes)
+		.iter()
+		.fold(String::new(), |mut hex, byte| {
+			let _ = write!(hex, "{byte:02x}");
+			hex
+		})
+}
+
+/// Writes `files` into a zip archive at `archive_path` under a directory named
+/// after `id`. The archive is written to a temporary file next to
+/// `archive_path` and renamed over it once finished, so a failure leaves a
+/// previous archive at `archive_path` untouched.
+#[instrument(level = "trace", skip(files))]
+fn write_zip(archive_path: &Path, id: &plugin::Id, files: &[(String, Vec<u8>)]) -> Result<()> {
+	let temp_path =
+		crate::temp_path(archive_path).map_err(Error::msg("failed to create archive"))?;
+	let result = write_zip_file(&temp_path, id, files).and_then(|_| {
+		std::fs::rename(&temp_path, archive_path)
+			.map_err(Error::msg("failed to move archive into place"))
+	});
+	if result.is_err() {
+		let _ = std::fs::remove_file(&temp_path);
+	}
+
+	result
+}
+
+#[instrument(level = "trace", skip(files))]
+fn write_zip_file(archive_path: &Path, id: &plugin::Id, files: &[(String, Vec<u8>)]) -> Result<()> {
+	let file = File::create_new(archive_path).map_err(Error::msg("failed to create archive"))?;
+	let mut writer = zip::ZipWriter::new(file);
+	let options = zip::write::SimpleFileOptions::default()
+		.compression_method(zip::CompressionMethod::Deflated);
+
+	for (path, bytes) in files {
+		writer
+			.start_file(format!("{id}/{path}"), options)
+			.map_err(anyhow::Error::from)
+			.map_err(Error::msg("failed to add file to archive"))?;
+		writer
+			.write_all(bytes)
+			.map_err(Error::msg("failed to write file into archive"))?;
+	}
+	writer
+		.finish()
+		.map_err(anyhow::Error::from)
+		.map_err(Error::msg("failed to finish writing archive"))?
+		.sync_all()
+		.map_err(Error::msg("failed to flush archive to disk"))?;
+
+	Ok(())
+}
+
+/// Returns a result to the outcome of packing the mod at `mod_dir` into a zip
+/// archive at `archive_path` that can be installed with
+/// [`Instance::install_mod`].
+///
+/// The mod is validated with [`validate_mod`] first. Only the manifest,
+/// settings, [`plugin::README`], [`plugin::CHANGELOG`], [`plugin::LICENSE`] and
+/// the `Instance::PACKAGES_DIR` tree of the mod are packed, along with a
+/// [`CHECKSUMS`] file. If `prebuild` is true, the loose files of each game
+/// package, such as `assets/packages/Resource`, are built into a package file,
+/// such as `assets/packages/Resource.rpk`, along with the nested packages inside
+/// of it. [`Instance::install_mod`] unpacks these into loose files again.
+///
+/// # Errors
+///
+/// This function will return an error if `archive_path` does not have a `.zip`
+/// extension.
+///
+/// Errors may also be returned according to:
+///
+/// - `pack::validate_mod`
+/// - `emtk_asset::Package::write_dir`
+/// - `zip::ZipWriter::start_file`
+#[instrument(level = "trace")]
+pub async fn pack_mod(mod_dir: &Path, archive_path: &Path, prebuild: bool) -> Result<Packed> {
+	if !matches!(
+		ArchiveKind::from_path(archive_path),
+		Some((ArchiveKind::Zip, _))
+	) {
+		return Err(Error::new(
+			anyhow!("\"{}\" is not a zip archive", archive_path.display()),
+			"failed to pack mod",
+		));
+	}
+
+	let (id, manifest) = validate_mod(mod_dir).await?;
+
+	let mod_dir = mod_dir.to_path_buf();
+	let archive_path = archive_path.to_path_buf();
+	let archive_id = id.clone();
+	let files = tokio::task::spawn_blocking(move || {
+		let mut files = Vec::new();
+		for name in [
+			plugin::Manifest::TOML,
+			plugin::Settings::TOML,
+			plugin::README,
+			plugin::CHANGELOG,
+			plugin::LICENSE,
+		] {
+			let path = mod_dir.join(name);
+			if path.is_file() {
+				let bytes = std::fs::read(&path).map_err(Error::msg("failed to read mod file"))?;
+				files.push((name.to_string(), bytes));
+			}
+		}
+		let packages_dir = mod_dir
+			.join(Instance::ASSETS_DIR)
+			.join(Instance::PACKAGES_DIR);
+		if packages_dir.is_dir() {
+			let relative = format!("{}/{}", Instance::ASSETS_DIR, Instance::PACKAGES_DIR);
+			collect_packages(&packages_dir, &relative, prebuild, &mut files)?;
+		}
+		info!("mod files collected");
+
+		let checksums = files
+			.iter()
+			.fold(String::new(), |mut checksums, (path, bytes)| {
+				let _ = writeln!(checksums, "{}  {}", sha256(bytes), path);
+				checksums
+			});
+		let mut archive_files = files.clone();
+		archive_files.push((CHECKSUMS.to_string(), checksums.into_bytes()));
+		write_zip(&archive_path, &archive_id, &archive_files)?;
+		info!("mod packed into archive");
+
+		Ok::<_, Error>(files)
+	})
+	.await
+	.map_err(|e| Error::new(anyhow!(e), "failed to pack mod"))??;
+
+	Ok(Packed {
+		id,
+		version: manifest.plugin.version,
+		files: files.into_iter().map(|(path, _)| path).collect(),
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{fs, io::Read};
+
+	use emtk_asset::Package;
+	use pretty_assertions::assert_eq;
+	use tempfile::tempdir;
+
+	use crate::{instance::tests::dummy_instance, pack, prelude::*};
+
+	#[tokio::test]
+	async fn pack_mod() {
+		let cwd = tempdir().unwrap();
+		let mod_dir = cwd.path().join("com.example.mod");
+		let packages_dir = mod_dir.join("assets").join("packages").join("Resource");
+		fs::create_dir_all(packages_dir.join("actors.rpk")).unwrap();
+		fs::write(
+			mod_dir.join(plugin::Manifest::TOML),
+			"[plugin]\nname = \"Example\"\nversion = \"1.0.0\"\n",
+		)
+		.unwrap();
+		fs::write(mod_dir.join(plugin::README), "# Example").unwrap();
+		fs::write(mod_dir.join("notes.txt"), "not packed").unwrap();
+		fs::write(packages_dir.join("loose.txt"), "loose").unwrap();
+		fs::write(packages_dir.join("actors.rpk").join("actor"), "actor").unwrap();
+
+		let archive_path = cwd.path().join("com.example.mod.zip");
+		let packed = pack::pack_mod(&mod_dir, &archive_path, true).await.unwrap();
+		assert_eq!(packed.version, "1.0.0");
+		assert_eq!(
+			packed.files,
+			vec!["manifest.toml", "README.md", "assets/packages/Resource.rpk",]
+		);
+		// the archive is written to a temporary file that is renamed into place
+		assert_eq!(
+			fs::read_dir(cwd.path())
+				.unwrap()
+				.filter(|entry| {
+					entry
+						.as_ref()
+						.unwrap()
+						.path()
+						.extension()
+						.unwrap_or_default()
+						== "tmp"
+				})
+				.count(),
+			0
+		);
+
+		let mut archive = zip::ZipArchive::new(fs::File::open(&archive_path).unwrap()).unwrap();
+		assert!(archive.by_name("com.example.mod/notes.txt").is_err());
+		let mut checksums = String::new();
+		archive
+			.by_name(&format!("com.example.mod/{}", pack::CHECKSUMS))
+			.unwrap()
+			.read_to_string(&mut checksums)
+			.unwrap();
+		assert_eq!(checksums.lines().count(), 3);
+		let manifest = fs::read(mod_dir.join(plugin::Manifest::TOML)).unwrap();
+		assert!(checksums.contains(&format!("{}  manifest.toml", pack::sha256(&manifest))));
+
+		let mut package = Vec::new();
+		archive
+			.by_name("com.example.mod/assets/packages/Resource.rpk")
+			.unwrap()
+			.read_to_end(&mut package)
+			.unwrap();
+		let package_path = cwd.path().join("Resource.rpk");
+		fs::write(&package_path, package).unwrap();
+		let package = Package::new(&package_path).unwrap();
+		let names: Vec<_> = package
+			.entries()
+			.unwrap()
+			.into_iter()
+			.map(|entry| entry.name.to_string())
+			.collect();
+		assert_eq!(names, vec!["actors.rpk", "loose.txt"]);
+		assert_eq!(
+			Package::new(&package_path)
+				.unwrap()
+				.load("loose.txt")
+				.unwrap(),
+			b"loose"
+		);
+
+		// the packed mod can be installed and its packages read
+		let instance_dir = tempdir().unwrap();
+		let mut instance = dummy_instance(&instance_dir).await;
+		let installed = instance.install_mod(&archive_path).await.unwrap();
+		assert_eq!(installed.id, packed.id);
+		let resource_dir = instance
+			.path()
+			.join(installed.id.packages_dir())
+			.join("Resource");
+		assert_eq!(fs::read(resource_dir.join("loose.txt")).unwrap(), b"loose");
+		let package = Package::new(resource_dir.join("actors.rpk")).unwrap();
+		assert_eq!(package.load("actor").unwrap(), b"actor");
+		assert!(
+			!instance
+				.path()
+				.join(installed.id.packages_dir())
+				.join("Resource.rpk")
+				.exists()
+		);
+
+		// without prebuilding the loose files are packed as they are
+		let packed = pack::pack_mod(&mod_dir, &archive_path, false)
+			.await
+			.unwrap();
+		assert_eq!(
+			packed.files,
+			vec![
+				"manifest.toml",
+				"README.md",
+				"assets/packages/Resource/actors.rpk/actor",
+				"assets/packages/Resource/loose.txt",
+			]
+		);
+	}
+
+	#[tokio::test]
+	async fn pack_mod_invalid() {
+		let cwd = tempdir().unwrap();
+		let archive_path = cwd.path().join("mod.zip");
+
+		let mod_dir = cwd.path().join("com.example.missing");
+		fs::create_dir_all(&mod_dir).unwrap();
+		assert!(
+			pack::pack_mod(&mod_dir, &archive_path, false)
+				.await
+				.is_err()
+		);
+
+		let mod_dir = cwd.path().join("not a valid id");
+		fs::create_dir_all(&mod_dir).unwrap();
+		fs::write(
+			mod_dir.join(plugin::Manifest::TOML),
+			"[plugin]\nname = \"Example\"\nversion = \"1.0.0\"\n",
+		)
+		.unwrap();
+		assert!(
+			pack::pack_mod(&mod_dir, &archive_path, false)
+				.await
+				.is_err()
+		);
+
+		let mod_dir = cwd.path().join("com.example.empty-version");
+		fs::create_dir_all(&mod_dir).unwrap();
+		fs::write(
+			mod_dir.join(plugin::Manifest::TOML),
+			"[plugin]\nname = \"Example\"\nversion = \"\"\n",
+		)
+		.unwrap();
+		assert!(
+			pack::pack_mod(&mod_dir, &archive_path, false)
+				.await
+				.is_err()
+		);
+		assert!(!archive_path.exists());
+	}
+}
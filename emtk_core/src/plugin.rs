@@ -1,12 +1,16 @@
 use std::{
 	collections::HashMap,
 	fmt::{Display, Formatter},
-	path::PathBuf,
+	path::{Path, PathBuf},
 };
 
+use emtk_asset::overlay::Edits;
 use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tracing::instrument;
 
 use super::Instance;
+use crate::TomlError;
 
 pub mod prelude {
 	pub use crate::plugin::{self, Plugin};
@@ -79,18 +83,10 @@ impl Id {
 	/// - Is empty
 	/// - Starts or ends with '-' or '.'
 	/// - Not alphanumeric (exceptions: '-', '.')
+	///
+	/// See [`emtk_plugin_id::is_valid`].
 	pub fn is_valid(id: &str) -> bool {
-		if id.is_empty()
-			|| id.starts_with(['-', '.'])
-			|| id.ends_with(['-', '.'])
-			|| !id
-				.chars()
-				.all(|chr| chr.is_alphanumeric() || chr == '-' || chr == '.')
-		{
-			return false;
-		};
-
-		true
+		emtk_plugin_id::is_valid(id)
 	}
 
 	/// Helper that returns a path to this plugin's directory
@@ -108,6 +104,11 @@ impl Id {
 		self.assets_dir().join(Instance::PACKAGES_DIR)
 	}
 
+	/// Helper that returns a path to this plugin's [`Manifest::TOML`] file.
+	pub fn manifest_file(&self) -> PathBuf {
+		self.plugin_dir().join(Manifest::TOML)
+	}
+
 	/// Helper that returns a path to this plugin's [`README`] file.
 	pub fn readme_file(&self) -> PathBuf {
 		self.plugin_dir().join(README)
@@ -160,6 +161,14 @@ pub struct Plugin {
 	/// The version of the plugin. Semantic versioning will be best practice in the
 	/// format major, minor, patch, a.k.a. v0.1.0
 	pub version: String,
+	/// The game versions the plugin supports, matched against the file version or
+	/// build ID of the game. Every game version is supported if this is empty.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub supported_versions: Vec<String>,
+	/// The library of the plugin loaded into the game, relative to the mod's
+	/// directory. Mods without one only provide assets.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub executable: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -167,6 +176,23 @@ pub enum Dependency {
 	Version(String),
 }
 
+impl Dependency {
+	/// Returns the version of the mod required by the dependency, `*` for any
+	/// version.
+	pub fn version(&self) -> &str {
+		match self {
+			Dependency::Version(version) => version,
+		}
+	}
+
+	/// Returns true if the mod with `manifest` satisfies the dependency, which is
+	/// when the required version is `*` or exactly the mod's version.
+	pub fn is_met_by(&self, manifest: &Manifest) -> bool {
+		let version = self.version().trim();
+		version == "*" || version == manifest.plugin.version
+	}
+}
+
 // #[derive(Debug, Deserialize, Serialize)]
 // pub struct Dependency {
 // 	version: String,
@@ -184,12 +210,40 @@ pub struct Manifest {
 	pub conflicts: Option<HashMap<Id, Conflicts>>,
 	#[serde(default)]
 	pub dependencies: Option<HashMap<Id, Dependency>>,
+	/// Removals and renames of vanilla entries, keyed by the name of the package
+	/// without its extension, such as `Resource`.
+	#[serde(default, skip_serializing_if = "HashMap::is_empty")]
+	pub packages: HashMap<String, Edits>,
 }
 
 impl Manifest {
 	/// The name of the file responsible for storing information about the plugin
 	/// such as display name, version, dependencies, etc.
 	pub const TOML: &str = "manifest.toml";
+
+	/// Returns a result to the manifest deserialized from the file at `path`.
+	///
+	/// # Errors
+	///
+	/// Errors may be returned according to:
+	///
+	/// - `tokio::fs::read_to_string`
+	/// - `toml::from_str`, as [`crate::ErrorKind::ManifestInvalid`]
+	#[instrument(level = "trace")]
+	pub async fn read(path: &Path) -> crate::Result<Self> {
+		let buffer = fs::read_to_string(path).await.map_err(crate::Error::msg(
+			"failed to read plugin manifest into buffer",
+		))?;
+		toml::from_str(&buffer).map_err(|e| {
+			crate::Error::new(
+				crate::ErrorKind::ManifestInvalid {
+					path: path.to_path_buf(),
+					source: TomlError::from(e),
+				},
+				"failed to deserialize plugin manifest from buffer",
+			)
+		})
+	}
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -301,9 +355,12 @@ pub struct TextInput {
 
 #[cfg(test)]
 mod tests {
+	use std::fs;
+
 	use pretty_assertions::{assert_eq, assert_ne};
+	use tempfile::tempdir;
 
-	use crate::prelude::*;
+	use crate::{Error, ErrorKind, prelude::*};
 
 	#[test]
 	fn id_is_equal() {
@@ -344,5 +401,50 @@ mod tests {
 		assert!(!plugin::Id::is_valid("."));
 		assert!(!plugin::Id::is_valid("-com.example.my-mod")); // begins with -
 		assert!(!plugin::Id::is_valid("-"));
+
+		let invalid_id = plugin::Id::try_from("not an id")
+			.map_err(Error::msg("failed to parse id"))
+			.unwrap_err();
+		assert!(matches!(
+			invalid_id.kind(),
+			ErrorKind::InvalidPluginId { id } if id == "not an id"
+		));
+	}
+
+	#[tokio::test]
+	async fn manifest_is_not_valid() {
+		let cwd = tempdir().unwrap();
+		let manifest_path = cwd.path().join(plugin::Manifest::TOML);
+		fs::write(&manifest_path, "[plugin\n").unwrap();
+
+		let invalid_manifest = plugin::Manifest::read(&manifest_path).await.unwrap_err();
+		assert!(matches!(
+			invalid_manifest.kind(),
+			ErrorKind::ManifestInvalid { .. }
+		));
+	}
+
+	#[test]
+	fn manifest_package_edits() {
+		let manifest: plugin::Manifest = toml::from_str(
+			r#"
+			[plugin]
+			name = "Example"
+			version = "1.0.0"
+
+			[packages.Resource]
+			remove = ["actors.rpk/opponent03"]
+			rename = { "actors.rpk/opponent05" = "opponent06" }
+			"#,
+		)
+		.unwrap();
+		let edits = &manifest.packages["Resource"];
+		assert!(edits.remove.contains("actors.rpk/opponent03"));
+		assert_eq!(edits.rename["actors.rpk/opponent05"], "opponent06");
+
+		let manifest: plugin::Manifest =
+			toml::from_str("[plugin]\nname = \"Example\"\nversion = \"1.0.0\"\n").unwrap();
+		assert!(manifest.packages.is_empty());
+		assert!(!toml::to_string(&manifest).unwrap().contains("packages"));
 	}
 }
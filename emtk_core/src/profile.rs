@@ -1,17 +1,19 @@
 use std::{
-	collections::HashMap,
+	collections::{BTreeMap, HashMap},
 	fmt,
 	path::{Path, PathBuf},
 	time::UNIX_EPOCH,
 };
 
+use anyhow::anyhow;
 use bon::Builder;
+use emtk_asset::overlay::{Edits, Plan, write_merged};
 use getset::{Getters, MutGetters};
 use serde::{Deserialize, Serialize};
 use tokio::{fs, io};
 use tracing::{info, instrument, warn};
 
-use crate::{Error, Result, TomlError, prelude::*};
+use crate::{Error, ErrorKind, Result, TomlError, conflict::Ownership, prelude::*};
 
 pub mod prelude {
 	pub use crate::profile::{self, Profile};
@@ -113,6 +115,36 @@ impl Profile {
 		Ok(profile_builder)
 	}
 
+	/// Returns a result to the existing profile at `path` with its load order as
+	/// it is on disk. Unlike [`Profile::with_path`] and [`ProfileBuilder::build`],
+	/// nothing is created and mods are not discovered, so the load order file is
+	/// never written. A profile without a load order file has an empty load order.
+	///
+	/// # Errors
+	///
+	/// Errors may be returned according to:
+	///
+	/// - `std::path::Path::canonicalize`
+	/// - `Profile::read_load_order`
+	#[instrument(level = "trace")]
+	pub async fn read<P: Into<PathBuf> + fmt::Debug>(path: P) -> Result<Profile> {
+		let path = path
+			.into()
+			.canonicalize()
+			.map_err(Error::msg("failed to canonicalize path to profile"))?;
+
+		let mut profile = Self {
+			path,
+			load_order: LoadOrder::default(),
+		};
+		if profile.path.join(Self::LOAD_ORDER_TOML).is_file() {
+			profile.load_order = profile.read_load_order().await?;
+		}
+
+		info!("profile read");
+		Ok(profile)
+	}
+
 	/// Returns a result from attempting to serialize the given load order to
 	/// `Profile::LOAD_ORDER_TOML` and then mutating `Profile::load_order`.
 	///
@@ -132,7 +164,7 @@ impl Profile {
 				"failed to serialize profile's load order into buffer",
 			))?;
 		info!("profile's load order serialized to buffer");
-		fs::write(self.path.join(Self::LOAD_ORDER_TOML), buffer)
+		crate::write_atomic(&self.path.join(Self::LOAD_ORDER_TOML), buffer)
 			.await
 			.map_err(Error::msg(
 				"failed to write profile's load order buffer into file",
@@ -336,7 +368,7 @@ impl Profile {
 	///
 	/// - `tokio::fs::File::create_new`
 	/// - `tokio::fs::read_to_string`
-	/// - `toml::from_str`
+	/// - `toml::from_str`, as [`ErrorKind::LoadOrderCorrupt`]
 	#[instrument(level = "trace")]
 	pub async fn read_load_order(&self) -> Result<LoadOrder> {
 		let load_order_path = self.path.join(Profile::LOAD_ORDER_TOML);
@@ -346,14 +378,21 @@ impl Profile {
 				.map_err(Error::msg("failed to create new load order file"))?;
 			info!("load order file created")
 		}
-		let buffer = fs::read_to_string(load_order_path)
+		let buffer = fs::read_to_string(&load_order_path)
 			.await
 			.map_err(Error::msg("failed to read into buffer for load order"))?;
 		info!("load order read into buffer");
 
 		let mut load_order: Vec<_> = toml::from_str::<LoadOrder>(&buffer)
-			.map_err(TomlError::from)
-			.map_err(Error::msg("failed to deserialize load order"))?
+			.map_err(|e| {
+				Error::new(
+					ErrorKind::LoadOrderCorrupt {
+						path: load_order_path.clone(),
+						source: TomlError::from(e),
+					},
+					"failed to deserialize load order",
+				)
+			})?
 			.into_iter()
 			.collect();
 		info!("load order deserialized from buffer");
@@ -371,6 +410,182 @@ impl Profile {
 
 		Ok(load_order)
 	}
+
+	/// Returns a result to the enabled mods of the load order along with their
+	/// manifests, ordered by priority. Mods without a readable manifest are
+	/// skipped.
+	///
+	/// # Errors
+	///
+	/// Errors may be returned according to:
+	///
+	/// - `Profile::game_dir`
+	#[instrument(level = "trace")]
+	pub async fn enabled_mods(
+		&self,
+	) -> Result<Vec<(plugin::Id, LoadOrderEntry, plugin::Manifest)>> {
+		let game_dir = self.game_dir().await?;
+
+		let mut load_order: Vec<_> = self
+			.load_order
+			.iter()
+			.filter(|(_, entry)| entry.enabled)
+			.collect();
+		load_order.sort_by_key(|(_, entry)| entry.priority);
+
+		let mut enabled_mods = Vec::with_capacity(load_order.len());
+		for (id, entry) in load_order {
+			match plugin::Manifest::read(&game_dir.join(id.manifest_file())).await {
+				Ok(manifest) => enabled_mods.push((id.clone(), entry.clone(), manifest)),
+				Err(e) => warn!("skipping enabled mod \"{}\": {}", id, e),
+			}
+		}
+
+		Ok(enabled_mods)
+	}
+
+	/// Returns a result from checking that every dependency of the enabled mods
+	/// is also enabled in the load order at the required version. Ids and
+	/// versions are matched exactly, as when resolving the plugins of the mods
+	/// with [`crate::lifecycle::Lifecycle::resolve`].
+	///
+	/// # Errors
+	///
+	/// This function will return an error of [`ErrorKind::DependencyUnmet`] for
+	/// the first enabled mod, by priority, with a dependency that is not enabled
+	/// or has a version other than the required one.
+	///
+	/// Errors may also be returned according to:
+	///
+	/// - `Profile::enabled_mods`
+	#[instrument(level = "trace")]
+	pub async fn check_dependencies(&self) -> Result<()> {
+		let enabled_mods = self.enabled_mods().await?;
+		for (id, _, manifest) in &enabled_mods {
+			for (dependency, requirement) in manifest.dependencies.iter().flatten() {
+				let is_met = enabled_mods
+					.iter()
+					.any(|(enabled_id, _, enabled_manifest)| {
+						enabled_id == dependency && requirement.is_met_by(enabled_manifest)
+					});
+				if !is_met {
+					return Err(Error::new(
+						ErrorKind::DependencyUnmet {
+							id: id.clone(),
+							dependency: dependency.clone(),
+						},
+						"failed to check dependencies of enabled mods",
+					));
+				}
+			}
+		}
+		info!("dependencies of enabled mods are met");
+
+		Ok(())
+	}
+
+	/// Returns a result to the paths of the game's packages built into
+	/// `Profile::cache_build_dir` with the assets and package edits of the
+	/// enabled mods physically merged into them, the same way as the overlay of
+	/// the framework. Packages without assets or edits from the enabled mods are
+	/// not built.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if a game package could not be read
+	/// or planned, or a built package could not be written.
+	///
+	/// Errors may also be returned according to:
+	///
+	/// - `Profile::game_dir`
+	/// - `Profile::enabled_mods`
+	/// - `Profile::asset_ownership`
+	/// - `Profile::cache_build_dir`
+	#[instrument(level = "trace")]
+	pub async fn build_packages(&self) -> Result<Vec<PathBuf>> {
+		let game_dir = self.game_dir().await?;
+		let mut package_edits: HashMap<String, Edits> = HashMap::new();
+		for (_, _, manifest) in self.enabled_mods().await? {
+			for (name, edits) in manifest.packages {
+				package_edits.entry(name).or_default().extend(edits);
+			}
+		}
+		let ownership = self.asset_ownership().await?;
+		let cache_build_dir = self.cache_build_dir().await?;
+
+		tokio::task::spawn_blocking(move || {
+			build_packages(&game_dir, &cache_build_dir, &ownership, &package_edits)
+		})
+		.await
+		.map_err(|e| Error::new(anyhow!(e), "failed to build packages"))?
+	}
+}
+
+/// Returns a result to the paths of the packages of the game at `game_dir`
+/// built into `build_dir` with the assets of `ownership` and `package_edits`
+/// merged into them.
+fn build_packages(
+	game_dir: &Path,
+	build_dir: &Path,
+	ownership: &Ownership,
+	package_edits: &HashMap<String, Edits>,
+) -> Result<Vec<PathBuf>> {
+	let mut package_paths = std::fs::read_dir(game_dir)
+		.map_err(Error::msg("failed to read game directory entries"))?
+		.map(|entry| entry.map(|entry| entry.path()))
+		.collect::<std::io::Result<Vec<PathBuf>>>()
+		.map_err(Error::msg("failed to read next entry in game directory"))?;
+	package_paths.retain(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "rpk"));
+	package_paths.sort();
+
+	let mut built = Vec::new();
+	for package_path in package_paths {
+		let (Some(name), Some(file_name)) = (
+			package_path.file_stem().and_then(|name| name.to_str()),
+			package_path.file_name(),
+		) else {
+			continue;
+		};
+		let overrides: HashMap<String, PathBuf> = ownership
+			.winners(name)
+			.map(|(path, owner)| (path.to_string(), owner.path.clone()))
+			.collect();
+		let edits = package_edits.get(name).cloned().unwrap_or_default();
+		if overrides.is_empty() && edits.is_empty() {
+			continue;
+		}
+
+		let mut byte_lengths = BTreeMap::new();
+		for (path, file) in &overrides {
+			let byte_length = std::fs::metadata(file)
+				.map_err(Error::msg("failed to read metadata of mod asset"))?
+				.len();
+			let byte_length = u32::try_from(byte_length)
+				.map_err(|e| Error::new(anyhow!(e), "mod asset is too large for a package"))?;
+			byte_lengths.insert(path.clone(), byte_length);
+		}
+		let mut original = std::fs::File::open(&package_path)
+			.map_err(Error::msg("failed to open game package"))?;
+		let plan = Plan::read(&mut original, &byte_lengths, &edits)
+			.map_err(|e| Error::new(anyhow!(e.to_string()), "failed to plan package build"))?;
+
+		let build_path = build_dir.join(file_name);
+		let temp_path =
+			crate::temp_path(&build_path).map_err(Error::msg("failed to build package"))?;
+		let result = std::fs::File::create_new(&temp_path).and_then(|file| {
+			let mut writer = std::io::BufWriter::new(file);
+			write_merged(&plan, original, &overrides, &mut writer)?;
+			writer.into_inner()?.sync_all()
+		});
+		if let Err(e) = result.and_then(|_| std::fs::rename(&temp_path, &build_path)) {
+			let _ = std::fs::remove_file(&temp_path);
+			return Err(Error::new(e, "failed to write built package"));
+		}
+		info!("package built \"{}\"", build_path.display());
+		built.push(build_path);
+	}
+
+	Ok(built)
 }
 
 impl<S> ProfileBuilder<S>
@@ -495,8 +710,7 @@ where
 				.map_err(Error::msg("failed to serialize load order into buffer"))?;
 			info!("load order serialized to buffer");
 
-			// TODO: write to temp file and perform move operation to overwrite load order file
-			fs::write(profile.path.join(Profile::LOAD_ORDER_TOML), buffer)
+			crate::write_atomic(&profile.path.join(Profile::LOAD_ORDER_TOML), buffer)
 				.await
 				.map_err(Error::msg("failed to write load order buffer into file"))?;
 			info!("finished writing update to load order file");
@@ -506,6 +720,150 @@ where
 	}
 }
 
+#[cfg(test)]
+mod tests {
+	use std::fs;
+
+	use pretty_assertions::assert_eq;
+	use tempfile::tempdir;
+
+	use crate::{ErrorKind, instance::tests::dummy_instance, prelude::*};
+
+	fn write_manifest(mods_dir: &std::path::Path, id: &str, manifest: &str) {
+		let mod_dir = mods_dir.join(id);
+		fs::create_dir_all(&mod_dir).unwrap();
+		fs::write(mod_dir.join(plugin::Manifest::TOML), manifest).unwrap();
+	}
+
+	#[tokio::test]
+	async fn check_dependencies() {
+		let cwd = tempdir().unwrap();
+		let instance = dummy_instance(&cwd).await;
+		let mods_dir = instance.mods_dir().await.unwrap();
+		let profile_path = instance.profile().path();
+		write_manifest(
+			&mods_dir,
+			"com.example.first",
+			"[plugin]\nname = \"First\"\nversion = \"1.0.0\"\n\n[dependencies.\"com.example.second\"]\nVersion = \"1.0.0\"\n",
+		);
+		fs::write(
+			profile_path.join(Profile::LOAD_ORDER_TOML),
+			"[\"com.example.first\"]\nenabled = true\npriority = 0\n",
+		)
+		.unwrap();
+
+		let profile = Profile::with_path(profile_path)
+			.await
+			.unwrap()
+			.build()
+			.await
+			.unwrap();
+		let dependency_unmet = profile.check_dependencies().await.unwrap_err();
+		assert!(matches!(
+			dependency_unmet.kind(),
+			ErrorKind::DependencyUnmet { dependency, .. } if dependency.to_string() == "com.example.second"
+		));
+
+		// enabled at another version
+		write_manifest(
+			&mods_dir,
+			"com.example.second",
+			"[plugin]\nname = \"Second\"\nversion = \"2.0.0\"\n",
+		);
+		fs::write(
+			profile_path.join(Profile::LOAD_ORDER_TOML),
+			"[\"com.example.first\"]\nenabled = true\npriority = 0\n\n[\"com.example.second\"]\nenabled = true\npriority = 1\n",
+		)
+		.unwrap();
+		let profile = Profile::with_path(profile_path)
+			.await
+			.unwrap()
+			.build()
+			.await
+			.unwrap();
+		assert!(matches!(
+			profile.check_dependencies().await.unwrap_err().kind(),
+			ErrorKind::DependencyUnmet { .. }
+		));
+
+		// enabled at the required version
+		write_manifest(
+			&mods_dir,
+			"com.example.second",
+			"[plugin]\nname = \"Second\"\nversion = \"1.0.0\"\n",
+		);
+		profile.check_dependencies().await.unwrap();
+	}
+
+	#[tokio::test]
+	async fn build_packages() {
+		let cwd = tempdir().unwrap();
+		let instance = dummy_instance(&cwd).await;
+		let mods_dir = instance.mods_dir().await.unwrap();
+		let profile_path = instance.profile().path();
+
+		let vanilla_dir = tempdir().unwrap();
+		fs::write(vanilla_dir.path().join("a.txt"), "a").unwrap();
+		fs::write(vanilla_dir.path().join("b.txt"), "b").unwrap();
+		let mut vanilla = fs::File::create(cwd.path().join("Resource.rpk")).unwrap();
+		emtk_asset::Package::write_dir(vanilla_dir.path(), &mut vanilla).unwrap();
+		let mut vanilla = fs::File::create(cwd.path().join("Textures.rpk")).unwrap();
+		emtk_asset::Package::write_dir(vanilla_dir.path(), &mut vanilla).unwrap();
+
+		write_manifest(
+			&mods_dir,
+			"com.example.first",
+			"[plugin]\nname = \"First\"\nversion = \"1.0.0\"\n\n[packages.Resource]\nremove = [\"a.txt\"]\n",
+		);
+		let id = plugin::Id::try_from("com.example.first").unwrap();
+		let package_dir = cwd.path().join(id.packages_dir()).join("Resource");
+		fs::create_dir_all(&package_dir).unwrap();
+		fs::write(package_dir.join("b.txt"), "B").unwrap();
+		fs::write(package_dir.join("c.txt"), "c").unwrap();
+		fs::write(
+			profile_path.join(Profile::LOAD_ORDER_TOML),
+			"[\"com.example.first\"]\nenabled = true\npriority = 0\n",
+		)
+		.unwrap();
+
+		let profile = Profile::with_path(profile_path)
+			.await
+			.unwrap()
+			.build()
+			.await
+			.unwrap();
+		let built = profile.build_packages().await.unwrap();
+		let cache_build_dir = profile.cache_build_dir().await.unwrap();
+		assert_eq!(built, vec![cache_build_dir.join("Resource.rpk")]);
+
+		let expected_dir = tempdir().unwrap();
+		fs::write(expected_dir.path().join("b.txt"), "B").unwrap();
+		fs::write(expected_dir.path().join("c.txt"), "c").unwrap();
+		let mut expected = Vec::new();
+		emtk_asset::Package::write_dir(expected_dir.path(), &mut expected).unwrap();
+		assert_eq!(fs::read(&built[0]).unwrap(), expected);
+		// no temporary files are left behind
+		assert_eq!(
+			fs::read_dir(&cache_build_dir)
+				.unwrap()
+				.filter(|entry| entry.as_ref().unwrap().path().extension().unwrap() == "tmp")
+				.count(),
+			0
+		);
+	}
+
+	#[tokio::test]
+	async fn load_order_corrupt() {
+		let cwd = tempdir().unwrap();
+		let instance = dummy_instance(&cwd).await;
+		let profile = instance.profile();
+
+		fs::write(profile.path().join(Profile::LOAD_ORDER_TOML), "enabled =").unwrap();
+		let corrupt = profile.read_load_order().await.unwrap_err();
+		assert!(matches!(corrupt.kind(), ErrorKind::LoadOrderCorrupt { .. }));
+	}
+}
+
 // #[cfg(test)]
 // mod tests {
 // 	use std::{
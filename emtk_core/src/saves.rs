@@ -0,0 +1,496 @@
+//! Isolation of game saves and settings between profiles. The game always
+//! reads its saves from a single directory, so the saves of a profile are
+//! swapped into that directory before launching the game and synced back into
+//! the profile afterwards. Any saves that do not belong to a profile, e.g. from
+//! playing without mods, are stashed away while a profile is swapped in.
+
+use std::{
+	collections::{BTreeSet, HashMap},
+	path::{Path, PathBuf},
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::{fs, io};
+use tracing::{info, instrument, warn};
+
+use crate::{Error, Result, prelude::*};
+
+/// The name of the directory the game stores its saves and settings in. This is
+/// a child of `%AppData%` on Windows.
+pub const GAME_SAVES_DIR: &str = "Exanima";
+
+/// The file extensions of game saves, checkpoints and settings files that are
+/// isolated per profile.
+pub const EXTENSIONS: [&str; 3] = ["rsg", "rcp", "set"];
+
+/// The name of the directory responsible for storing the state of swapped in
+/// saves. This is a child of the game's saves directory.
+pub const DATA_DIR: &str = ".emtk";
+
+/// The name of the file responsible for tracking which profile's saves are
+/// currently swapped in. This is a child of `saves::DATA_DIR`.
+pub const SESSION_RON: &str = "session.ron";
+
+/// The name of the directory responsible for storing saves that don't belong to
+/// any profile while a profile is swapped in. This is a child of
+/// `saves::DATA_DIR`.
+pub const STASH_DIR: &str = "stash";
+
+/// The suffix appended to a save's file name, after a timestamp, when both the
+/// game and the profile changed the save while it was swapped in.
+pub const CONFLICT_SUFFIX: &str = "conflict";
+
+/// Returns the path to the directory the game stores its saves and settings in.
+#[instrument(level = "trace")]
+pub fn game_saves_dir() -> Option<PathBuf> {
+	dirs::data_dir().map(|p| p.join(GAME_SAVES_DIR))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+struct FileState {
+	len: u64,
+	/// The file's "date modified" metadata in unix timestamp nanoseconds
+	modified: u64,
+}
+
+/// A HashMap with the key as the file name of a save and the value as the state
+/// of the file on disk.
+type Snapshot = HashMap<String, FileState>;
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Session {
+	/// Full path to the profile that is swapped in. `PathBuf` can not be
+	/// deserialized from ron so the path is stored as a `String`.
+	profile: String,
+	/// State of the saves in the game's saves directory right after swapping in
+	game: Snapshot,
+	/// State of the saves in the profile's saves directory right after swapping in
+	profile_saves: Snapshot,
+}
+
+/// The outcome of syncing saves from the game's saves directory back into a
+/// profile.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncReport {
+	/// Full path to the profile the saves were synced into
+	pub profile: PathBuf,
+	/// Saves that were created or changed by the game
+	pub copied: Vec<String>,
+	/// Saves that were deleted by the game
+	pub removed: Vec<String>,
+	/// Saves that changed in both the game and the profile
+	pub conflicts: Vec<Conflict>,
+}
+
+/// A save that changed in both the game and the profile while it was swapped in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+	/// File name of the save
+	pub name: String,
+	/// File name the game's version was kept as next to the profile's version,
+	/// or `None` if the game deleted the save
+	pub kept_as: Option<String>,
+}
+
+/// Returns a file name for the game's version of the conflicting save `name`
+/// that is not taken in `dir`, such as `Modded.rsg.1700000000.conflict`.
+#[instrument(level = "trace")]
+fn conflict_name(dir: &Path, name: &str) -> String {
+	let timestamp = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map(|duration| duration.as_secs())
+		.unwrap_or_default();
+	let mut conflict_name = format!("{name}.{timestamp}.{CONFLICT_SUFFIX}");
+	let mut i = 1;
+	while dir.join(&conflict_name).exists() {
+		conflict_name = format!("{name}.{timestamp}-{i}.{CONFLICT_SUFFIX}");
+		i += 1;
+	}
+
+	conflict_name
+}
+
+#[instrument(level = "trace")]
+fn is_save(path: &Path) -> bool {
+	path.is_file()
+		&& path
+			.extension()
+			.and_then(|extension| extension.to_str())
+			.is_some_and(|extension| {
+				EXTENSIONS
+					.iter()
+					.any(|save_extension| extension.eq_ignore_ascii_case(save_extension))
+			})
+}
+
+/// Returns the file names of all saves directly inside of `dir`.
+#[instrument(level = "trace")]
+async fn save_names(dir: &Path) -> io::Result<Vec<String>> {
+	let mut names = Vec::new();
+	if !dir.is_dir() {
+		return Ok(names);
+	}
+
+	let mut read_dir = fs::read_dir(dir).await?;
+	while let Some(entry) = read_dir.next_entry().await? {
+		let entry_path = entry.path();
+		if !is_save(&entry_path) {
+			continue;
+		}
+		let Some(name) = entry.file_name().to_str().map(String::from) else {
+			warn!("save file name is not valid UTF-8, skipped");
+			continue;
+		};
+		names.push(name);
+	}
+
+	Ok(names)
+}
+
+#[instrument(level = "trace")]
+async fn snapshot(dir: &Path) -> Result<Snapshot> {
+	let mut snapshot = Snapshot::new();
+	for name in save_names(dir)
+		.await
+		.map_err(Error::msg("failed to read saves directory entries"))?
+	{
+		let metadata = fs::metadata(dir.join(&name))
+			.await
+			.map_err(Error::msg("failed to read metadata of save"))?;
+		let modified = metadata
+			.modified()
+			.map_err(Error::msg(
+				"failed to get modified date time metadata of save",
+			))?
+			.duration_since(UNIX_EPOCH)
+			.map_err(Error::msg(
+				"failed to get the unix epoch timestamp of the save's modified date time metadata",
+			))?;
+		let modified = u64::try_from(modified.as_nanos()).unwrap_or(u64::MAX);
+		snapshot.insert(
+			name,
+			FileState {
+				len: metadata.len(),
+				modified,
+			},
+		);
+	}
+
+	Ok(snapshot)
+}
+
+#[instrument(level = "trace")]
+async fn read_session(game_saves_dir: &Path) -> Result<Option<Session>> {
+	let session_path = game_saves_dir.join(DATA_DIR).join(SESSION_RON);
+	if !session_path.is_file() {
+		return Ok(None);
+	}
+
+	let buffer = fs::read_to_string(session_path)
+		.await
+		.map_err(Error::msg("failed to read saves session into buffer"))?;
+	info!("saves session read into buffer");
+	let session = ron::from_str(&buffer)
+		.map_err(ron::Error::from)
+		.map_err(Error::msg(
+			"failed to deserialize saves session from buffer",
+		))?;
+	info!("saves session deserialized from buffer");
+
+	Ok(Some(session))
+}
+
+/// Returns a result to the full path of the profile whose saves are currently
+/// swapped into `game_saves_dir`, if any.
+#[instrument(level = "trace")]
+pub async fn swapped_in_profile(game_saves_dir: &Path) -> Result<Option<PathBuf>> {
+	Ok(read_session(game_saves_dir)
+		.await?
+		.map(|session| PathBuf::from(session.profile)))
+}
+
+/// Syncs the saves of the profile that is currently swapped into
+/// `game_saves_dir` back into the profile, then restores the saves that don't
+/// belong to any profile. Returns `None` if no profile is swapped in.
+///
+/// A save is only synced back if the game changed it. If the save in the profile
+/// also changed in the meantime, the save is reported as a conflict and the
+/// game's version is kept next to it instead of overwriting it.
+///
+/// # Errors
+///
+/// Errors may be returned according to:
+///
+/// - `tokio::fs::copy`
+/// - `tokio::fs::remove_file`
+/// - `tokio::fs::rename`
+#[instrument(level = "trace")]
+pub async fn sync_back(game_saves_dir: &Path) -> Result<Option<SyncReport>> {
+	let Some(session) = read_session(game_saves_dir).await? else {
+		return Ok(None);
+	};
+
+	let profile_path = PathBuf::from(&session.profile);
+	let profile_saves_dir = profile_path.join(Profile::SAVES_DIR);
+	crate::ensure_dir(&profile_saves_dir)
+		.await
+		.map_err(Error::msg("failed to create profile's saves directory"))?;
+
+	let mut report = SyncReport {
+		profile: profile_path,
+		..Default::default()
+	};
+	let game = snapshot(game_saves_dir).await?;
+	let profile_saves = snapshot(&profile_saves_dir).await?;
+	let names: BTreeSet<_> = game.keys().chain(session.game.keys()).cloned().collect();
+	for name in names {
+		if game.get(&name) == session.game.get(&name) {
+			continue;
+		}
+
+		if profile_saves.get(&name) != session.profile_saves.get(&name) {
+			let kept_as = if game.contains_key(&name) {
+				let kept_as = conflict_name(&profile_saves_dir, &name);
+				fs::copy(game_saves_dir.join(&name), profile_saves_dir.join(&kept_as))
+					.await
+					.map_err(Error::msg("failed to copy conflicting save into profile"))?;
+				Some(kept_as)
+			} else {
+				None
+			};
+			warn!("save changed in both the game and the profile \"{}\"", name);
+			report.conflicts.push(Conflict { name, kept_as });
+		} else if game.contains_key(&name) {
+			fs::copy(game_saves_dir.join(&name), profile_saves_dir.join(&name))
+				.await
+				.map_err(Error::msg("failed to copy save into profile"))?;
+			report.copied.push(name);
+		} else {
+			if profile_saves.contains_key(&name) {
+				fs::remove_file(profile_saves_dir.join(&name))
+					.await
+					.map_err(Error::msg("failed to remove save from profile"))?;
+			}
+			report.removed.push(name);
+		}
+	}
+	info!("saves synced back into profile");
+
+	for name in game.keys() {
+		fs::remove_file(game_saves_dir.join(name))
+			.await
+			.map_err(Error::msg(
+				"failed to remove save from game's saves directory",
+			))?;
+	}
+
+	let data_dir = game_saves_dir.join(DATA_DIR);
+	let stash_dir = data_dir.join(STASH_DIR);
+	for name in save_names(&stash_dir)
+		.await
+		.map_err(Error::msg("failed to read stashed saves"))?
+	{
+		fs::rename(stash_dir.join(&name), game_saves_dir.join(&name))
+			.await
+			.map_err(Error::msg("failed to restore stashed save"))?;
+	}
+	fs::remove_dir_all(data_dir)
+		.await
+		.map_err(Error::msg("failed to remove saves session"))?;
+	info!("stashed saves restored");
+
+	Ok(Some(report))
+}
+
+impl Profile {
+	/// The name of the directory responsible for storing the profile's game saves
+	/// and settings. This is a child of `Profile { ... }.path`.
+	pub const SAVES_DIR: &str = "saves";
+
+	#[instrument(level = "trace")]
+	pub async fn saves_dir(&self) -> Result<PathBuf> {
+		let dir = self.path().join(Self::SAVES_DIR);
+		crate::ensure_dir(&dir)
+			.await
+			.map_err(|e| Error::new(e, "failed to create profile's saves directory"))?;
+		Ok(dir)
+	}
+
+	/// Swaps the profile's saves into `game_saves_dir` so the game will only see
+	/// the saves of this profile. Saves already in `game_saves_dir` that don't
+	/// belong to any profile are stashed until [`saves::sync_back`] is called.
+	///
+	/// If another profile's saves are still swapped in, e.g. the game was
+	/// launched with another profile, those saves are synced back first and the
+	/// report of that is returned.
+	///
+	/// # Errors
+	///
+	/// Errors may be returned according to:
+	///
+	/// - `saves::sync_back`
+	/// - `tokio::fs::copy`
+	/// - `tokio::fs::rename`
+	/// - `tokio::fs::write`
+	#[instrument(level = "trace")]
+	pub async fn swap_in_saves(&self, game_saves_dir: &Path) -> Result<Option<SyncReport>> {
+		let report = sync_back(game_saves_dir).await?;
+
+		let stash_dir = game_saves_dir.join(DATA_DIR).join(STASH_DIR);
+		crate::ensure_dir(&stash_dir)
+			.await
+			.map_err(Error::msg("failed to create stash directory for saves"))?;
+		for name in save_names(game_saves_dir)
+			.await
+			.map_err(Error::msg("failed to read game's saves directory entries"))?
+		{
+			fs::rename(game_saves_dir.join(&name), stash_dir.join(&name))
+				.await
+				.map_err(Error::msg("failed to stash save"))?;
+		}
+		info!("saves stashed");
+
+		let saves_dir = self.saves_dir().await?;
+		for name in save_names(&saves_dir).await.map_err(Error::msg(
+			"failed to read profile's saves directory entries",
+		))? {
+			fs::copy(saves_dir.join(&name), game_saves_dir.join(&name))
+				.await
+				.map_err(Error::msg(
+					"failed to copy save into game's saves directory",
+				))?;
+		}
+
+		let session = Session {
+			profile: self.path().display().to_string(),
+			game: snapshot(game_saves_dir).await?,
+			profile_saves: snapshot(&saves_dir).await?,
+		};
+		let buffer = ron::ser::to_string_pretty(&session, ron::ser::PrettyConfig::default())
+			.map_err(Error::msg("failed to serialize saves session into buffer"))?;
+		crate::write_atomic(&game_saves_dir.join(DATA_DIR).join(SESSION_RON), buffer)
+			.await
+			.map_err(Error::msg("failed to write saves session buffer into file"))?;
+		info!("profile's saves swapped in");
+
+		Ok(report)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fs;
+
+	use pretty_assertions::assert_eq;
+	use tempfile::tempdir;
+
+	use crate::{instance::tests::dummy_instance, saves};
+
+	#[tokio::test]
+	async fn swap_in_and_sync_back() {
+		let cwd = tempdir().unwrap();
+		let game_saves_dir = tempdir().unwrap();
+		let game_saves_dir = game_saves_dir.path();
+		let instance = dummy_instance(&cwd).await;
+		let profile = instance.create_profile("Modded").await.unwrap();
+		let saves_dir = profile.saves_dir().await.unwrap();
+		fs::write(game_saves_dir.join("Vanilla.rsg"), "vanilla").unwrap();
+		fs::write(saves_dir.join("Modded.rsg"), "modded").unwrap();
+		fs::write(saves_dir.join("Deleted.rcp"), "deleted").unwrap();
+
+		assert!(
+			profile
+				.swap_in_saves(game_saves_dir)
+				.await
+				.unwrap()
+				.is_none()
+		);
+		assert!(!game_saves_dir.join("Vanilla.rsg").exists());
+		assert_eq!(
+			fs::read_to_string(game_saves_dir.join("Modded.rsg")).unwrap(),
+			"modded"
+		);
+		assert_eq!(
+			saves::swapped_in_profile(game_saves_dir).await.unwrap(),
+			Some(profile.path().clone())
+		);
+
+		// simulate the game writing to its saves directory
+		fs::write(game_saves_dir.join("Modded.rsg"), "modded progress").unwrap();
+		fs::write(game_saves_dir.join("New.rsg"), "new").unwrap();
+		fs::remove_file(game_saves_dir.join("Deleted.rcp")).unwrap();
+
+		let report = saves::sync_back(game_saves_dir).await.unwrap().unwrap();
+		assert_eq!(report.copied, vec!["Modded.rsg", "New.rsg"]);
+		assert_eq!(report.removed, vec!["Deleted.rcp"]);
+		assert!(report.conflicts.is_empty());
+		assert_eq!(
+			fs::read_to_string(saves_dir.join("Modded.rsg")).unwrap(),
+			"modded progress"
+		);
+		assert!(!saves_dir.join("Deleted.rcp").exists());
+
+		// the saves without a profile are restored
+		assert_eq!(
+			fs::read_to_string(game_saves_dir.join("Vanilla.rsg")).unwrap(),
+			"vanilla"
+		);
+		assert!(!game_saves_dir.join("Modded.rsg").exists());
+		assert!(!game_saves_dir.join(saves::DATA_DIR).exists());
+		assert!(saves::sync_back(game_saves_dir).await.unwrap().is_none());
+	}
+
+	#[tokio::test]
+	async fn sync_back_conflict() {
+		let cwd = tempdir().unwrap();
+		let game_saves_dir = tempdir().unwrap();
+		let game_saves_dir = game_saves_dir.path();
+		let instance = dummy_instance(&cwd).await;
+		let profile = instance.create_profile("Modded").await.unwrap();
+		let saves_dir = profile.saves_dir().await.unwrap();
+		fs::write(saves_dir.join("Modded.rsg"), "modded").unwrap();
+
+		profile.swap_in_saves(game_saves_dir).await.unwrap();
+		fs::write(game_saves_dir.join("Modded.rsg"), "from game").unwrap();
+		fs::write(saves_dir.join("Modded.rsg"), "from profile").unwrap();
+
+		// swapping in again syncs back the previous session first
+		let report = profile
+			.swap_in_saves(game_saves_dir)
+			.await
+			.unwrap()
+			.unwrap();
+		assert_eq!(report.conflicts.len(), 1);
+		assert_eq!(report.conflicts[0].name, "Modded.rsg");
+		assert_eq!(
+			fs::read_to_string(saves_dir.join("Modded.rsg")).unwrap(),
+			"from profile"
+		);
+		let first_copy = report.conflicts[0].kept_as.clone().unwrap();
+		assert_eq!(
+			fs::read_to_string(saves_dir.join(&first_copy)).unwrap(),
+			"from game"
+		);
+
+		// a second conflict does not overwrite the first copy
+		fs::write(game_saves_dir.join("Modded.rsg"), "from game again").unwrap();
+		fs::write(saves_dir.join("Modded.rsg"), "from profile again").unwrap();
+		let report = profile
+			.swap_in_saves(game_saves_dir)
+			.await
+			.unwrap()
+			.unwrap();
+		let second_copy = report.conflicts[0].kept_as.clone().unwrap();
+		assert_ne!(first_copy, second_copy);
+		assert!(second_copy.ends_with(saves::CONFLICT_SUFFIX));
+		assert_eq!(
+			fs::read_to_string(saves_dir.join(&first_copy)).unwrap(),
+			"from game"
+		);
+		assert_eq!(
+			fs::read_to_string(saves_dir.join(&second_copy)).unwrap(),
+			"from game again"
+		);
+	}
+}
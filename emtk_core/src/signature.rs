@@ -0,0 +1,528 @@
+//! Scanning of byte signatures in the game's executable without running the
+//! game, so that signatures can be verified against a new build of the game
+//! on any platform.
+//!
+//! Signatures are IDA-style patterns of hexadecimal bytes separated by
+//! whitespace, where `?` or `??` matches any byte, e.g. `48 8B ?? ?? 89`.
+//!
+//! Named signatures are shared between plugins through a [`Signatures::TOML`]
+//! registry so that a signature broken by a game update is fixed once for every
+//! plugin using it:
+//!
+//! ```toml
+//! [[load_package]]
+//! pattern = "48 89 5C 24 ?? 57 48 83 EC 20"
+//! versions = ["0.9"]
+//!
+//! [[load_package]]
+//! pattern = "E8 ?? ?? ?? ?? 48 8B D8"
+//! # the address is the call instruction's target, 1 byte into the match
+//! offset = 1
+//! versions = ["0.8"]
+//! ```
+
+use std::{
+	collections::BTreeMap,
+	fmt::{self, Display, Formatter},
+	path::Path,
+	str::FromStr,
+};
+
+use anyhow::anyhow;
+use pelite::image::IMAGE_SCN_CNT_CODE;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tracing::{info, instrument, warn};
+
+use crate::{Error, Result, TomlError, game::GameVersion};
+
+/// A parsed IDA-style signature where `None` matches any byte.
+///
+/// # Examples
+///
+/// ```
+/// use emtk_core::signature::Pattern;
+///
+/// let pattern: Pattern = "8B ?? 89".parse().unwrap();
+/// assert_eq!(pattern.find(&[0x00, 0x8B, 0x45, 0x89]), Some(1));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern(Vec<Option<u8>>);
+
+impl Pattern {
+	/// Returns the amount of bytes matched by the pattern.
+	pub fn len(&self) -> usize {
+		self.0.len()
+	}
+
+	/// Returns true if the pattern does not match any bytes.
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+
+	/// Returns true if `bytes` starts with bytes matching the pattern.
+	pub fn matches(&self, bytes: &[u8]) -> bool {
+		bytes.len() >= self.0.len()
+			&& self
+				.0
+				.iter()
+				.zip(bytes)
+				.all(|(expected, byte)| expected.is_none_or(|expected| expected == *byte))
+	}
+
+	/// Returns the offset of the first match of the pattern in `haystack`.
+	pub fn find(&self, haystack: &[u8]) -> Option<usize> {
+		self.find_iter(haystack).next()
+	}
+
+	/// Returns an iterator over the offsets of every match of the pattern in
+	/// `haystack`, including overlapping matches.
+	pub fn find_iter<'a>(&'a self, haystack: &'a [u8]) -> impl Iterator<Item = usize> + 'a {
+		let end = (haystack.len() + 1).saturating_sub(self.0.len());
+		(0..end).filter(move |&offset| self.matches(&haystack[offset..]))
+	}
+}
+
+impl FromStr for Pattern {
+	type Err = Error;
+
+	/// # Errors
+	///
+	/// This function will return an error if:
+	///
+	/// - the signature is empty
+	/// - a byte of the signature is neither a wildcard nor two hexadecimal
+	///   digits
+	fn from_str(signature: &str) -> Result<Self> {
+		let bytes = signature
+			.split_whitespace()
+			.map(|token| match token {
+				"?" | "??" => Ok(None),
+				_ if token.len() == 2 => u8::from_str_radix(token, 16).map(Some).map_err(|e| {
+					Error::new(
+						anyhow!("\"{token}\" is not a byte: {e}"),
+						"failed to parse signature",
+					)
+				}),
+				_ => Err(Error::new(
+					anyhow!("\"{token}\" is not a byte"),
+					"failed to parse signature",
+				)),
+			})
+			.collect::<Result<Vec<_>>>()?;
+		if bytes.is_empty() {
+			return Err(Error::new(
+				anyhow!("signature is empty"),
+				"failed to parse signature",
+			));
+		}
+
+		Ok(Self(bytes))
+	}
+}
+
+impl Display for Pattern {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		for (i, byte) in self.0.iter().enumerate() {
+			if i > 0 {
+				f.write_str(" ")?;
+			}
+			match byte {
+				Some(byte) => write!(f, "{byte:02X}")?,
+				None => f.write_str("??")?,
+			}
+		}
+		Ok(())
+	}
+}
+
+/// A PE file read from disk, such as `Instance::BINARY`.
+#[derive(Debug, Clone)]
+pub struct Image {
+	bytes: Vec<u8>,
+}
+
+impl Image {
+	/// Returns a result to the image of the PE file in `bytes`.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if `bytes` is not a valid PE file.
+	pub fn new(bytes: Vec<u8>) -> Result<Self> {
+		pelite::PeFile::from_bytes(&bytes)
+			.map_err(anyhow::Error::from)
+			.map_err(Error::msg("failed to parse PE file"))?;
+
+		Ok(Self { bytes })
+	}
+
+	/// Returns a result to the image of the PE file at `path`.
+	///
+	/// # Errors
+	///
+	/// Errors may be returned according to:
+	///
+	/// - `tokio::fs::read`
+	/// - [`Image::new`]
+	#[instrument(level = "trace")]
+	pub async fn read(path: &Path) -> Result<Self> {
+		let bytes = fs::read(path)
+			.await
+			.map_err(Error::msg("failed to read PE file"))?;
+		info!("PE file read into buffer");
+
+		Self::new(bytes)
+	}
+
+	/// Returns a result to the relative virtual addresses of every match of
+	/// `pattern` in the code sections of the image, in ascending order.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the bytes of a code section are
+	/// outside of the file.
+	#[instrument(level = "trace", skip(self))]
+	pub fn scan(&self, pattern: &Pattern) -> Result<Vec<u32>> {
+		let pe = pelite::PeFile::from_bytes(&self.bytes)
+			.map_err(anyhow::Error::from)
+			.map_err(Error::msg("failed to parse PE file"))?;
+
+		let mut rvas = Vec::new();
+		for section in pe.section_headers().iter() {
+			if section.Characteristics & IMAGE_SCN_CNT_CODE == 0 {
+				continue;
+			}
+			let bytes = pe
+				.get_section_bytes(section)
+				.map_err(anyhow::Error::from)
+				.map_err(Error::msg("failed to read bytes of section"))?;
+			// the raw data of a section is padded to the file alignment
+			let bytes = match section.VirtualSize as usize {
+				0 => bytes,
+				virtual_size => &bytes[..bytes.len().min(virtual_size)],
+			};
+			rvas.extend(
+				pattern
+					.find_iter(bytes)
+					.map(|offset| section.VirtualAddress + offset as u32),
+			);
+		}
+		rvas.sort_unstable();
+
+		Ok(rvas)
+	}
+
+	/// Returns a result to the relative virtual address of the first match of
+	/// `pattern` in the code sections of the image.
+	///
+	/// See [`Image::scan`].
+	pub fn scan_first(&self, pattern: &Pattern) -> Result<Option<u32>> {
+		Ok(self.scan(pattern)?.first().copied())
+	}
+
+	/// Returns the game version of the image, identified by its version
+	/// resource and hash.
+	pub fn game_version(&self) -> GameVersion {
+		let file_version = crate::game::pe_file_version(&self.bytes)
+			.map_err(|e| warn!("failed to read version resource of PE file: {e}"))
+			.ok()
+			.flatten();
+
+		GameVersion {
+			file_version,
+			executable_hash: crate::pack::sha256(&self.bytes),
+		}
+	}
+}
+
+/// A signature of the registry along with the game versions it was verified
+/// against.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Signature {
+	/// IDA-style pattern, see [`Pattern`]
+	pub pattern: String,
+	/// Amount of bytes from the start of a match to the address of the
+	/// signature
+	#[serde(default, skip_serializing_if = "is_zero")]
+	pub offset: i64,
+	/// Game versions the pattern matches, see [`GameVersion::is_supported_by`].
+	/// A signature without versions is assumed to match every version.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub versions: Vec<String>,
+}
+
+fn is_zero(offset: &i64) -> bool {
+	*offset == 0
+}
+
+impl Signature {
+	/// Returns a result to the parsed pattern of the signature.
+	///
+	/// See [`Pattern::from_str`].
+	pub fn parse(&self) -> Result<Pattern> {
+		self.pattern.parse()
+	}
+
+	/// Returns the relative virtual address of the signature for a match at
+	/// `rva`, or `None` if the offset moves it out of the address space.
+	pub fn offset_rva(&self, rva: u32) -> Option<u32> {
+		u32::try_from(i64::from(rva).checked_add(self.offset)?).ok()
+	}
+}
+
+/// A registry of named signatures, where each name holds the signatures for
+/// different game versions in order of preference.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Signatures(BTreeMap<String, Vec<Signature>>);
+
+impl Signatures {
+	/// The name of the file of the registry shared by every plugin of an
+	/// instance. This is a child of `Instance::DATA_DIR`.
+	pub const TOML: &str = "signatures.toml";
+
+	/// Returns a result to the registry deserialized from the file at `path`.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if:
+	///
+	/// - the file could not be read
+	/// - the registry could not be deserialized
+	#[instrument(level = "trace")]
+	pub async fn read(path: &Path) -> Result<Self> {
+		let buffer = fs::read_to_string(path)
+			.await
+			.map_err(Error::msg("failed to read signatures file"))?;
+		let signatures = toml::from_str(&buffer)
+			.map_err(TomlError::from)
+			.map_err(Error::msg("failed to deserialize signatures"))?;
+		info!("signatures read from file");
+
+		Ok(signatures)
+	}
+
+	/// Returns an iterator over the names of the signatures in the registry.
+	pub fn names(&self) -> impl Iterator<Item = &str> {
+		self.0.keys().map(String::as_str)
+	}
+
+	/// Returns the first signature named `name` that matches `game_version`.
+	pub fn get(&self, name: &str, game_version: &GameVersion) -> Option<&Signature> {
+		self.0
+			.get(name)?
+			.iter()
+			.find(|signature| game_version.is_supported_by(&signature.versions))
+	}
+
+	/// Returns a result to the relative virtual address of the signature named
+	/// `name` in `image`, built as `game_version`.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if:
+	///
+	/// - there is no signature named `name` for `game_version`
+	/// - the pattern of the signature is invalid
+	/// - the pattern does not match exactly once in the code of `image`
+	/// - the offset moves the address out of the address space
+	#[instrument(level = "trace", skip(image))]
+	pub fn resolve(&self, name: &str, image: &Image, game_version: &GameVersion) -> Result<u32> {
+		let failed = |reason: String| {
+			Error::new(
+				anyhow!(reason),
+				format!("failed to resolve signature \"{name}\""),
+			)
+		};
+
+		let signature = self.get(name, game_version).ok_or_else(|| {
+			failed(format!(
+				"no signature for game version \"{}\"",
+				game_version.name()
+			))
+		})?;
+		let rvas = image.scan(&signature.parse()?)?;
+		let rva = match rvas.as_slice() {
+			[rva] => *rva,
+			[] => return Err(failed("pattern does not match".to_string())),
+			_ => return Err(failed(format!("pattern matches {} times", rvas.len()))),
+		};
+
+		signature
+			.offset_rva(rva)
+			.ok_or_else(|| failed(format!("offset {} is out of bounds", signature.offset)))
+	}
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+	use pretty_assertions::assert_eq;
+
+	use super::*;
+
+	/// Returns a minimal 64-bit PE file with a code section holding `code` at
+	/// `CODE_RVA` and a data section holding `data`.
+	pub(crate) fn pe_file(code: &[u8], data: &[u8]) -> Vec<u8> {
+		const FILE_ALIGNMENT: usize = 0x200;
+		let align = |size: usize| size.div_ceil(FILE_ALIGNMENT).max(1) * FILE_ALIGNMENT;
+
+		let mut bytes = vec![0u8; FILE_ALIGNMENT];
+		bytes[..2].copy_from_slice(b"MZ");
+		bytes[0x3C..0x40].copy_from_slice(&0x40u32.to_le_bytes());
+
+		let mut headers = Vec::new();
+		headers.extend(b"PE\0\0");
+		// file header
+		headers.extend(0x8664u16.to_le_bytes());
+		headers.extend(2u16.to_le_bytes());
+		headers.extend([0; 12]);
+		headers.extend(240u16.to_le_bytes());
+		headers.extend(0x22u16.to_le_bytes());
+		// optional header
+		let size_of_image = CODE_RVA as usize + 2 * 0x1000;
+		let mut optional = vec![0u8; 240];
+		optional[..2].copy_from_slice(&0x20Bu16.to_le_bytes());
+		optional[24..32].copy_from_slice(&0x1_4000_0000u64.to_le_bytes());
+		optional[32..36].copy_from_slice(&0x1000u32.to_le_bytes());
+		optional[36..40].copy_from_slice(&(FILE_ALIGNMENT as u32).to_le_bytes());
+		optional[56..60].copy_from_slice(&(size_of_image as u32).to_le_bytes());
+		optional[60..64].copy_from_slice(&(FILE_ALIGNMENT as u32).to_le_bytes());
+		optional[68..70].copy_from_slice(&3u16.to_le_bytes());
+		optional[108..112].copy_from_slice(&16u32.to_le_bytes());
+		headers.extend(optional);
+
+		let mut raw_offset = FILE_ALIGNMENT;
+		for (i, (name, section, characteristics)) in [
+			(*b".text\0\0\0", code, 0x6000_0020u32),
+			(*b".data\0\0\0", data, 0xC000_0040u32),
+		]
+		.into_iter()
+		.enumerate()
+		{
+			headers.extend(name);
+			headers.extend((section.len() as u32).to_le_bytes());
+			headers.extend((CODE_RVA + i as u32 * 0x1000).to_le_bytes());
+			headers.extend((align(section.len()) as u32).to_le_bytes());
+			headers.extend((raw_offset as u32).to_le_bytes());
+			headers.extend([0; 12]);
+			headers.extend(characteristics.to_le_bytes());
+			raw_offset += align(section.len());
+		}
+		bytes[0x40..0x40 + headers.len()].copy_from_slice(&headers);
+
+		for section in [code, data] {
+			let start = bytes.len();
+			bytes.extend(section);
+			bytes.resize(start + align(section.len()), 0);
+		}
+		bytes
+	}
+
+	pub(crate) const CODE_RVA: u32 = 0x1000;
+
+	#[test]
+	fn parse_pattern() {
+		let pattern: Pattern = " 48 8b ?  ??\t0F ".parse().unwrap();
+		assert_eq!(
+			pattern,
+			Pattern(vec![Some(0x48), Some(0x8B), None, None, Some(0x0F)])
+		);
+		assert_eq!(pattern.to_string(), "48 8B ?? ?? 0F");
+		assert_eq!(pattern.len(), 5);
+
+		for invalid in ["", "  ", "4", "488B", "GG", "?? 4 8B", "???"] {
+			assert!(invalid.parse::<Pattern>().is_err(), "{invalid:?}");
+		}
+	}
+
+	#[test]
+	fn find_pattern() {
+		let pattern: Pattern = "AA ?? AA".parse().unwrap();
+		let haystack = [0xAA, 0x00, 0xAA, 0x01, 0xAA, 0xAA];
+		assert_eq!(pattern.find(&haystack), Some(0));
+		assert_eq!(pattern.find_iter(&haystack).collect::<Vec<_>>(), [0, 2]);
+		assert_eq!(pattern.find(&haystack[3..]), None);
+		assert_eq!(pattern.find(&[]), None);
+	}
+
+	#[test]
+	fn scan_image() {
+		let code = [0x90, 0x48, 0x8B, 0x05, 0x10, 0x90, 0x48, 0x8B, 0x05, 0x20];
+		let data = [0x48, 0x8B, 0x05, 0x30];
+		let image = Image::new(pe_file(&code, &data)).unwrap();
+
+		let pattern: Pattern = "48 8B 05 ??".parse().unwrap();
+		assert_eq!(image.scan(&pattern).unwrap(), [CODE_RVA + 1, CODE_RVA + 6]);
+		assert_eq!(image.scan_first(&pattern).unwrap(), Some(CODE_RVA + 1));
+		let missing: Pattern = "05 30".parse().unwrap();
+		assert_eq!(image.scan_first(&missing).unwrap(), None);
+		let missing: Pattern = "20 00".parse().unwrap();
+		assert_eq!(image.scan_first(&missing).unwrap(), None);
+
+		assert!(Image::new(vec![0; 64]).is_err());
+	}
+
+	#[test]
+	fn resolve_signatures() {
+		let code = [0x90, 0xE8, 0x10, 0x00, 0x00, 0x00, 0x48, 0x8B, 0xD8, 0xC3];
+		let image = Image::new(pe_file(&code, &[])).unwrap();
+		let game_version = image.game_version();
+		assert_eq!(game_version.file_version, None);
+
+		let signatures: Signatures = toml::from_str(&format!(
+			r#"
+			[[call]]
+			pattern = "FF FF"
+			versions = ["0.9"]
+
+			[[call]]
+			pattern = "E8 ?? ?? ?? ?? 48 8B D8"
+			offset = 1
+			versions = ["{}"]
+
+			[[ambiguous]]
+			pattern = "??"
+
+			[[missing]]
+			pattern = "FF FF"
+
+			[[invalid]]
+			pattern = "FFF"
+			"#,
+			game_version.build_id()
+		))
+		.unwrap();
+		assert_eq!(
+			signatures.names().collect::<Vec<_>>(),
+			["ambiguous", "call", "invalid", "missing"]
+		);
+		assert_eq!(
+			signatures.get("call", &game_version).unwrap().pattern,
+			"E8 ?? ?? ?? ?? 48 8B D8"
+		);
+		assert_eq!(
+			signatures.resolve("call", &image, &game_version).unwrap(),
+			CODE_RVA + 2
+		);
+		for name in ["ambiguous", "missing", "invalid", "unknown"] {
+			assert!(
+				signatures.resolve(name, &image, &game_version).is_err(),
+				"{name}"
+			);
+		}
+
+		let other_version = GameVersion {
+			file_version: Some("0.9.1.0".to_string()),
+			executable_hash: String::new(),
+		};
+		assert_eq!(
+			signatures.get("call", &other_version).unwrap().pattern,
+			"FF FF"
+		);
+		let other_version = GameVersion {
+			file_version: Some("0.8.0.0".to_string()),
+			..other_version
+		};
+		assert!(signatures.get("call", &other_version).is_none());
+	}
+}
@@ -0,0 +1,363 @@
+//! Watching an instance's mods directory and active profile directory for
+//! changes made outside of the toolkit, such as mods being copied in by hand or
+//! a load order being edited in a text editor.
+//!
+//! Raw filesystem events are classified into [`WatchEvent`]s so that callers
+//! only need to know what changed, not how the platform reported it.
+
+use std::{
+	path::{Path, PathBuf},
+	pin::Pin,
+	task::{Context, Poll},
+};
+
+use anyhow::anyhow;
+use futures_core::Stream;
+use notify::{
+	EventKind, RecursiveMode, Watcher,
+	event::{ModifyKind, RenameMode},
+};
+use tokio::sync::mpsc;
+use tracing::{info, instrument, warn};
+
+use crate::{Error, Instance, Profile, Result, plugin};
+
+/// A change to the mods or active profile of an instance.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum WatchEvent {
+	/// A mod directory was added to the mods directory.
+	ModAdded(plugin::Id),
+	/// A mod directory was removed from the mods directory.
+	ModRemoved(plugin::Id),
+	/// A file within a mod directory, other than its manifest, was changed.
+	ModChanged(plugin::Id),
+	/// The [`plugin::Manifest::TOML`] of a mod was changed.
+	ManifestChanged(plugin::Id),
+	/// The [`Profile::LOAD_ORDER_TOML`] of the active profile was changed.
+	LoadOrderChanged,
+}
+
+/// The kind of change made to a single path, reduced from the platform
+/// specific events of `notify`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Change {
+	Created,
+	Removed,
+	Modified,
+}
+
+/// A stream of [`WatchEvent`]s, created with [`Instance::watch`].
+///
+/// Events stop being emitted once this is dropped.
+#[derive(Debug)]
+pub struct Watch {
+	// Kept alive for as long as events should be emitted.
+	_watcher: notify::RecommendedWatcher,
+	receiver: mpsc::UnboundedReceiver<WatchEvent>,
+}
+
+impl Watch {
+	/// Returns a result to a stream of changes made to the mods in `mods_dir`
+	/// and to the load order of the profile in `profile_dir`.
+	///
+	/// See [`Instance::watch`] to watch the mods and active profile of an
+	/// instance.
+	///
+	/// # Errors
+	///
+	/// Errors may be returned according to:
+	///
+	/// - `notify::recommended_watcher`
+	/// - `notify::Watcher::watch`
+	#[instrument(level = "trace")]
+	pub fn new(mods_dir: &Path, profile_dir: &Path) -> Result<Self> {
+		let (sender, receiver) = mpsc::unbounded_channel();
+
+		let mut watcher = {
+			let mods_dir = mods_dir.to_owned();
+			let profile_dir = profile_dir.to_owned();
+			notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+				let event = match event {
+					Ok(event) => event,
+					Err(e) => {
+						warn!("failed to receive filesystem event: {e}");
+						return;
+					}
+				};
+				for event in classify_event(&mods_dir, &profile_dir, &event) {
+					// the receiver being dropped means nobody is listening anymore
+					let _ = sender.send(event);
+				}
+			})
+			.map_err(|e| Error::new(anyhow!(e), "failed to create watcher"))?
+		};
+		watcher
+			.watch(mods_dir, RecursiveMode::Recursive)
+			.map_err(|e| Error::new(anyhow!(e), "failed to watch mods directory"))?;
+		info!("watching mods directory at {}", mods_dir.display());
+		watcher
+			.watch(profile_dir, RecursiveMode::NonRecursive)
+			.map_err(|e| Error::new(anyhow!(e), "failed to watch profile directory"))?;
+		info!("watching profile directory at {}", profile_dir.display());
+
+		Ok(Self {
+			_watcher: watcher,
+			receiver,
+		})
+	}
+
+	/// Returns the next event, or `None` if the watcher has stopped.
+	pub async fn recv(&mut self) -> Option<WatchEvent> {
+		self.receiver.recv().await
+	}
+
+	/// Returns the next event if one is already pending, which can be used to
+	/// drain a burst of events such as a mod being copied in.
+	pub fn try_recv(&mut self) -> Option<WatchEvent> {
+		self.receiver.try_recv().ok()
+	}
+}
+
+impl Stream for Watch {
+	type Item = WatchEvent;
+
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+		self.receiver.poll_recv(cx)
+	}
+}
+
+impl Instance {
+	/// Returns a result to a stream of changes made to the mods directory and
+	/// the directory of the active profile.
+	///
+	/// # Errors
+	///
+	/// Errors may be returned according to:
+	///
+	/// - [`Instance::mods_dir`]
+	/// - [`Watch::new`]
+	#[instrument(level = "trace")]
+	pub async fn watch(&self) -> Result<Watch> {
+		let mods_dir = self
+			.mods_dir()
+			.await
+			.map_err(Error::msg("failed to get mods directory"))?;
+
+		Watch::new(&mods_dir, self.profile.path())
+	}
+}
+
+/// Returns the events described by a raw `notify` event.
+fn classify_event(mods_dir: &Path, profile_dir: &Path, event: &notify::Event) -> Vec<WatchEvent> {
+	let changes: Vec<(&PathBuf, Change)> = match event.kind {
+		EventKind::Create(_) => event.paths.iter().map(|p| (p, Change::Created)).collect(),
+		EventKind::Remove(_) => event.paths.iter().map(|p| (p, Change::Removed)).collect(),
+		EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+			event.paths.iter().map(|p| (p, Change::Removed)).collect()
+		}
+		EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+			event.paths.iter().map(|p| (p, Change::Created)).collect()
+		}
+		EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => event
+			.paths
+			.iter()
+			.zip([Change::Removed, Change::Created])
+			.collect(),
+		EventKind::Modify(ModifyKind::Name(_)) => event
+			.paths
+			.iter()
+			.map(|p| {
+				if p.exists() {
+					(p, Change::Created)
+				} else {
+					(p, Change::Removed)
+				}
+			})
+			.collect(),
+		EventKind::Modify(_) => event.paths.iter().map(|p| (p, Change::Modified)).collect(),
+		EventKind::Access(_) | EventKind::Any | EventKind::Other => Vec::new(),
+	};
+
+	let mut events = Vec::new();
+	for (path, change) in changes {
+		if let Some(event) = classify(mods_dir, profile_dir, path, change)
+			&& !events.contains(&event)
+		{
+			events.push(event);
+		}
+	}
+
+	events
+}
+
+/// Returns the event for a single changed path, or `None` if the change is not
+/// relevant, such as temporary files written next to the load order.
+fn classify(
+	mods_dir: &Path,
+	profile_dir: &Path,
+	path: &Path,
+	change: Change,
+) -> Option<WatchEvent> {
+	if let Ok(relative) = path.strip_prefix(profile_dir) {
+		return (relative == Path::new(Profile::LOAD_ORDER_TOML))
+			.then_some(WatchEvent::LoadOrderChanged);
+	}
+
+	let relative = path.strip_prefix(mods_dir).ok()?;
+	let mut components = relative.components();
+	let id = components.next()?.as_os_str().to_str()?;
+	let id = plugin::Id::try_from(id).ok()?;
+	match components.next() {
+		None => match change {
+			Change::Created => Some(WatchEvent::ModAdded(id)),
+			Change::Removed => Some(WatchEvent::ModRemoved(id)),
+			Change::Modified => Some(WatchEvent::ModChanged(id)),
+		},
+		Some(name) if components.next().is_none() && name.as_os_str() == plugin::Manifest::TOML => {
+			Some(WatchEvent::ManifestChanged(id))
+		}
+		Some(_) => Some(WatchEvent::ModChanged(id)),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		fs,
+		path::{Path, PathBuf},
+		time::Duration,
+	};
+
+	use notify::{
+		EventKind,
+		event::{CreateKind, DataChange, ModifyKind, RemoveKind, RenameMode},
+	};
+	use pretty_assertions::assert_eq;
+	use tempfile::tempdir;
+	use tokio::time;
+
+	use super::{WatchEvent, classify_event};
+	use crate::prelude::*;
+
+	#[test]
+	fn classify_events() {
+		let mods_dir = Path::new("/game/mods");
+		let profile_dir = Path::new("/game/.emtk/profiles/Default");
+		let id = |id: &str| plugin::Id::try_from(id).unwrap();
+		let event = |kind: EventKind, paths: &[&str]| {
+			let mut event = notify::Event::new(kind);
+			for path in paths {
+				event = event.add_path(PathBuf::from(path));
+			}
+			classify_event(mods_dir, profile_dir, &event)
+		};
+
+		assert_eq!(
+			event(
+				EventKind::Create(CreateKind::Folder),
+				&["/game/mods/com.example.mod"]
+			),
+			vec![WatchEvent::ModAdded(id("com.example.mod"))]
+		);
+		assert_eq!(
+			event(
+				EventKind::Remove(RemoveKind::Folder),
+				&["/game/mods/com.example.mod"]
+			),
+			vec![WatchEvent::ModRemoved(id("com.example.mod"))]
+		);
+		assert_eq!(
+			event(
+				EventKind::Modify(ModifyKind::Data(DataChange::Content)),
+				&["/game/mods/com.example.mod/manifest.toml"]
+			),
+			vec![WatchEvent::ManifestChanged(id("com.example.mod"))]
+		);
+		assert_eq!(
+			event(
+				EventKind::Create(CreateKind::File),
+				&["/game/mods/com.example.mod/assets/manifest.toml"]
+			),
+			vec![WatchEvent::ModChanged(id("com.example.mod"))]
+		);
+		assert_eq!(
+			event(
+				EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
+				&["/game/mods/com.example.old", "/game/mods/com.example.new"]
+			),
+			vec![
+				WatchEvent::ModRemoved(id("com.example.old")),
+				WatchEvent::ModAdded(id("com.example.new"))
+			]
+		);
+		assert_eq!(
+			event(
+				EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
+				&[
+					"/game/.emtk/profiles/Default/.load_order.toml.1.tmp",
+					"/game/.emtk/profiles/Default/load_order.toml"
+				]
+			),
+			vec![WatchEvent::LoadOrderChanged]
+		);
+		assert_eq!(
+			event(
+				EventKind::Create(CreateKind::File),
+				&["/game/.emtk/profiles/Default/.load_order.toml.1.tmp"]
+			),
+			vec![]
+		);
+		assert_eq!(
+			event(
+				EventKind::Create(CreateKind::Folder),
+				&["/game/mods/not a valid id"]
+			),
+			vec![]
+		);
+		assert_eq!(
+			event(
+				EventKind::Access(notify::event::AccessKind::Any),
+				&["/game/mods/com.example.mod/manifest.toml"]
+			),
+			vec![]
+		);
+	}
+
+	#[tokio::test]
+	async fn watch_instance() {
+		let cwd = tempdir().unwrap();
+		fs::write(cwd.path().join(Instance::BINARY), "").unwrap();
+		let instance = Instance::with_path(cwd.path())
+			.unwrap()
+			.build()
+			.await
+			.unwrap();
+		let mut watch = instance.watch().await.unwrap();
+
+		let mod_dir = instance.mods_dir().await.unwrap().join("com.example.mod");
+		fs::create_dir(&mod_dir).unwrap();
+		let event = time::timeout(Duration::from_secs(5), watch.recv())
+			.await
+			.unwrap();
+		assert_eq!(
+			event,
+			Some(WatchEvent::ModAdded(
+				plugin::Id::try_from("com.example.mod").unwrap()
+			))
+		);
+
+		let mut profile = instance.profile().clone();
+		let load_order = profile.load_order().clone();
+		profile.set_load_order(load_order).await.unwrap();
+		let event = time::timeout(Duration::from_secs(5), async {
+			loop {
+				match watch.recv().await {
+					Some(WatchEvent::LoadOrderChanged) | None => break,
+					Some(_) => continue,
+				}
+			}
+		})
+		.await;
+		assert!(event.is_ok());
+	}
+}
@@ -1,10 +1,14 @@
 use std::ffi::c_void;
 
 use safer_ffi::{ffi_export, prelude::repr_c};
+use tracing::error;
 use winapi::um::{memoryapi::WriteProcessMemory, processthreadsapi::GetCurrentProcess};
 
 use crate::{
-	internal::memory::sigscanner::{SigScanner, SigScannerResult},
+	internal::memory::{
+		signatures,
+		sigscanner::{SigScanner, SigScannerResult},
+	},
 	plugins::manager::{PluginManager, PluginMessage},
 };
 
@@ -22,6 +26,17 @@ pub unsafe extern "C" fn scan_memory(signature: repr_c::String) -> *mut c_void {
 	}
 }
 
+#[ffi_export]
+pub unsafe extern "C" fn resolve_signature(signature_name: repr_c::String) -> *mut c_void {
+	match unsafe { signatures::resolve(&signature_name) } {
+		Ok(ptr) => ptr as _,
+		Err(e) => {
+			error!("{:?}", e);
+			std::ptr::null_mut()
+		}
+	}
+}
+
 #[ffi_export]
 pub unsafe extern "C" fn read_bytes(pointer: *const c_void, length: usize) -> repr_c::Vec<u8> {
 	let buffer = unsafe { std::slice::from_raw_parts(pointer as *const u8, length).to_vec() };
@@ -54,7 +69,5 @@ pub unsafe extern "C" fn send_message(
 	let target = target.to_string();
 	let message = message.to_string();
 
-	PluginManager::send_message(target.as_str(), PluginMessage::Message(sender, message));
-
-	true
+	PluginManager::send_message(target.as_str(), PluginMessage::Message(sender, message))
 }
@@ -11,7 +11,10 @@ use winapi::{shared::ntdef::NTSTATUS, um::processthreadsapi::GetCurrentProcess};
 
 use crate::{
 	framework::api::location_is_readwrite,
-	internal::{memory::sigscanner::SigScanner, utils::ntdll::NtStatus},
+	internal::{
+		memory::{signatures, sigscanner::SigScanner},
+		utils::ntdll::NtStatus,
+	},
 };
 
 pub trait Hookable {
@@ -66,6 +69,17 @@ impl Hook {
 		}
 	}
 
+	/// Get a pointer from a named signature of the signature registry, and use
+	/// that to create a new hook.
+	pub unsafe fn from_signature_name(
+		hook_name: String,
+		signature_name: &str,
+		replacement_fn_ptr: *mut c_void,
+	) -> Result<Self> {
+		let ptr = unsafe { signatures::resolve(signature_name)? };
+		Ok(Self::new(hook_name, ptr as _, replacement_fn_ptr))
+	}
+
 	/// Offset the target function pointer by a given amount of bytes.
 	pub unsafe fn offset_pointer(&mut self, offset: isize) -> *mut *mut c_void {
 		self.target_fn_ptr = unsafe { self.target_fn_ptr.byte_offset(offset) };
@@ -180,6 +194,25 @@ pub unsafe extern "C" fn hook_from_signature(
 	}
 }
 
+#[ffi_export]
+/// Get a pointer from a named signature of the signature registry, and use that
+/// to create a new hook.
+pub unsafe extern "C" fn hook_from_signature_name(
+	hook_name: repr_c::String,
+	signature_name: repr_c::String,
+	replacement_fn_ptr: *mut c_void,
+) -> Option<repr_c::Box<Hook>> {
+	unsafe {
+		match Hook::from_signature_name(hook_name.into(), &signature_name, replacement_fn_ptr) {
+			Ok(hook) => Some(Box::new(hook).into()),
+			Err(e) => {
+				error!("{:?}", e);
+				None
+			}
+		}
+	}
+}
+
 #[ffi_export]
 /// Offset the target function pointer by the given offset.
 pub unsafe extern "C" fn hook_offset_pointer(hook: &mut Hook, offset: isize) -> *mut *mut c_void {
@@ -219,3 +252,9 @@ pub unsafe extern "C" fn hook_revert(hook: &mut Hook) -> bool {
 pub unsafe extern "C" fn hook_is_applied(hook: &Hook) -> bool {
 	unsafe { hook.is_applied() }
 }
+
+#[ffi_export]
+/// Free the hook without reverting it.
+pub extern "C" fn hook_free(hook: repr_c::Box<Hook>) {
+	drop(hook);
+}
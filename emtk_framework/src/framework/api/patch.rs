@@ -9,7 +9,10 @@ use winapi::um::{
 };
 
 use super::location_is_readwrite;
-use crate::internal::memory::sigscanner::{SigScanner, SigScannerResult};
+use crate::internal::memory::{
+	signatures,
+	sigscanner::{SigScanner, SigScannerResult},
+};
 
 pub trait Patchable<T> {
 	unsafe fn apply(&mut self) -> Result<()>;
@@ -51,6 +54,13 @@ impl Patch {
 		}
 	}
 
+	/// Get a pointer from a named signature of the signature registry, and use
+	/// that to create a new byte patch.
+	pub unsafe fn from_signature_name(signature_name: &str, data: Vec<u8>) -> Result<Self> {
+		let ptr = unsafe { signatures::resolve(signature_name)? };
+		Ok(Self::new(ptr as _, data))
+	}
+
 	/// Offset the patch address by a given amount of bytes.
 	///
 	/// Returns the new address.
@@ -197,6 +207,24 @@ pub unsafe extern "C" fn patch_from_signature(
 	}
 }
 
+#[ffi_export]
+/// Get a pointer from a named signature of the signature registry, and use that
+/// to create a new byte patch.
+pub unsafe extern "C" fn patch_from_signature_name(
+	signature_name: repr_c::String,
+	data: repr_c::Vec<u8>,
+) -> Option<repr_c::Box<Patch>> {
+	unsafe {
+		match Patch::from_signature_name(&signature_name, data.into()) {
+			std::result::Result::Ok(patch) => Some(Box::new(patch).into()),
+			Err(e) => {
+				error!("{:?}", e);
+				None
+			}
+		}
+	}
+}
+
 #[ffi_export]
 /// Offset the patch destination address by a given amount of bytes.
 pub unsafe extern "C" fn patch_offset_pointer(patch: &mut Patch, offset: isize) -> *const u8 {
@@ -222,6 +250,12 @@ pub unsafe extern "C" fn patch_is_applied(patch: &Patch) -> bool {
 	unsafe { patch.is_applied() }
 }
 
+#[ffi_export]
+/// Free the patch without reverting it.
+pub extern "C" fn patch_free(patch: repr_c::Box<Patch>) {
+	drop(patch);
+}
+
 #[ffi_export]
 /// Read the current bytes at the memory location.
 pub unsafe extern "C" fn patch_read_current(patch: &Patch) -> Option<repr_c::Vec<u8>> {
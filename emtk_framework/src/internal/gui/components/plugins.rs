@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use crate::internal::gui::Widget;
 use hudhook::*;
@@ -7,9 +7,18 @@ use super::Plugin;
 
 pub struct Plugins {
 	plugins: HashMap<String, Plugin>,
+	/// Ids of the plugins that failed along with the reason they failed
+	failed: BTreeMap<String, String>,
 	selected: Option<String>,
 }
 
+impl Plugins {
+	pub fn with_failed(mut self, failed: BTreeMap<String, String>) -> Self {
+		self.failed = failed;
+		self
+	}
+}
+
 impl From<Vec<Plugin>> for Plugins {
 	fn from(plugins: Vec<Plugin>) -> Self {
 		let selected = None;
@@ -22,7 +31,11 @@ impl From<Vec<Plugin>> for Plugins {
 			})
 			.collect();
 
-		Self { plugins, selected }
+		Self {
+			plugins,
+			failed: BTreeMap::new(),
+			selected,
+		}
 	}
 }
 
@@ -61,6 +74,17 @@ impl Widget for Plugins {
 						ui.tooltip_text(&plugin.config.plugin.name)
 					}
 				}
+
+				if !self.failed.is_empty() {
+					ui.separator();
+					ui.text_disabled("Failed plugins");
+					for (id, reason) in self.failed.iter() {
+						ui.text_colored([255., 0., 0., 255.], id);
+						if ui.is_item_hovered() {
+							ui.tooltip_text(reason)
+						}
+					}
+				}
 			});
 
 		ui.same_line();
@@ -112,13 +112,12 @@ impl ImguiRenderLoop for RenderLoop {
 
 		let plugins: Vec<components::Plugin> = plugin_ids
 			.into_iter()
-			.map(|id| {
-				let config = PluginManager::get_info_for(&id).unwrap();
-				components::Plugin::from(config)
-			})
+			.filter(|id| !PluginManager::is_failed(id))
+			.filter_map(|id| PluginManager::get_info_for(&id))
+			.map(components::Plugin::from)
 			.collect();
 
-		let mut plugins = components::Plugins::from(plugins);
+		let mut plugins = components::Plugins::from(plugins).with_failed(PluginManager::failed());
 		plugins.initialize(ctx, render_context);
 		self.components.push(Box::new(plugins));
 
@@ -1,3 +1,4 @@
+pub(crate) mod signatures;
 pub(crate) mod sigscanner;
 
 #[allow(unused)]
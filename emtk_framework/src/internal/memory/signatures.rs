@@ -0,0 +1,64 @@
+//! Runtime resolution of the named signatures of the instance's
+//! `signatures.toml` registry, shared by every plugin.
+
+use std::{
+	collections::HashMap,
+	env, fs,
+	sync::{LazyLock, Mutex},
+};
+
+use anyhow::anyhow;
+use emtk_core::{game::GameVersion, signature::Image};
+use tracing::{error, info};
+
+use crate::{SIGNATURES, internal::utils::pe64::PE64};
+
+/// The executable of the running game, read from disk once a signature is
+/// resolved so that it is scanned the same way as by `emtk signature verify`.
+static EXECUTABLE: LazyLock<Option<Image>> = LazyLock::new(|| {
+	env::current_exe()
+		.map_err(anyhow::Error::from)
+		.and_then(|path| Ok(fs::read(path)?))
+		.and_then(|bytes| Ok(Image::new(bytes)?))
+		.map_err(|e| error!("failed to read game executable: {}", e))
+		.ok()
+});
+
+/// The version of the running game, only detected once a signature is resolved.
+static GAME_VERSION: LazyLock<Option<GameVersion>> = LazyLock::new(|| {
+	let version = EXECUTABLE.as_ref()?.game_version();
+	info!("game version detected \"{}\"", version.name());
+	Some(version)
+});
+
+/// Addresses of the signatures resolved so far by name.
+static RESOLVED: LazyLock<Mutex<HashMap<String, usize>>> =
+	LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Returns the address of the signature named `name` in the running game.
+/// Addresses are resolved once and reused for every plugin.
+pub(crate) unsafe fn resolve(name: &str) -> anyhow::Result<usize> {
+	let mut resolved = RESOLVED
+		.lock()
+		.map_err(|_| anyhow!("signatures poisoned"))?;
+	if let Some(address) = resolved.get(name) {
+		return Ok(*address);
+	}
+
+	let signatures = SIGNATURES
+		.get()
+		.ok_or_else(|| anyhow!("signatures are not loaded"))?;
+	let game_version = GAME_VERSION
+		.as_ref()
+		.ok_or_else(|| anyhow!("game version is unknown"))?;
+	let executable = EXECUTABLE
+		.as_ref()
+		.ok_or_else(|| anyhow!("game executable is unknown"))?;
+	// the pattern must match exactly once in the code of the executable
+	let rva = signatures.resolve(name, executable, game_version)?;
+	let address = unsafe { PE64::get_base_address() } + rva as usize;
+	info!("signature \"{}\" resolved to {:#X}", name, address);
+
+	resolved.insert(name.to_string(), address);
+	Ok(address)
+}
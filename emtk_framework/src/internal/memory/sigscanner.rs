@@ -1,5 +1,9 @@
+use std::slice;
+
+use emtk_core::signature::Pattern;
 use pelite::pe::Pe;
 use serde::{Deserialize, Serialize};
+use tracing::error;
 
 use crate::internal::utils::pe64::PE64;
 
@@ -42,11 +46,18 @@ pub struct SigScanner {
 #[allow(unused)]
 impl SigScanner {
 	pub unsafe fn exec(&self) -> SigScannerResult {
-		unsafe {
-			match libmem::sig_scan(&self.signature, self.search_start as _, self.search_length) {
-				Some(ptr) => SigScannerResult::Found(ptr),
-				None => SigScannerResult::NotFound,
+		let pattern = match self.signature.parse::<Pattern>() {
+			Ok(pattern) => pattern,
+			Err(e) => {
+				error!("{}", e);
+				return SigScannerResult::NotFound;
 			}
+		};
+		let haystack =
+			unsafe { slice::from_raw_parts(self.search_start as *const u8, self.search_length) };
+		match pattern.find(haystack) {
+			Some(offset) => SigScannerResult::Found(self.search_start + offset),
+			None => SigScannerResult::NotFound,
 		}
 	}
 
@@ -24,7 +24,6 @@ use super::ntdll::{NtCreateSection, NtMapViewOfSection, NtUnmapViewOfSection};
 pub struct PE64;
 
 impl PE64 {
-	#[allow(unused)]
 	pub unsafe fn get_base_address() -> usize {
 		unsafe { GetModuleHandleA(null_mut()) as _ }
 	}
@@ -7,9 +7,9 @@ use std::{
 	env,
 	ffi::c_void,
 	fs,
-	io::{self, Read, Write},
+	io::{self, Write},
 	mem,
-	path::PathBuf,
+	path::{Path, PathBuf},
 	sync::OnceLock,
 };
 
@@ -17,10 +17,11 @@ use detours_sys::{
 	DetourAttach, DetourIsHelperProcess, DetourRestoreAfterWith, DetourTransactionBegin,
 	DetourTransactionCommit,
 };
-use emtk_core::{plugin, profile};
+use emtk_asset::overlay::Edits;
+use emtk_core::{Instance, plugin, profile, signature::Signatures};
 use internal::utils::rpk_intercept;
 use pelite::pe::Pe;
-use tracing::{error, info, instrument};
+use tracing::{error, info, instrument, warn};
 use tracing_subscriber::{Layer, fmt, layer::SubscriberExt, util::SubscriberInitExt};
 use winapi::{
 	shared::minwindef::{BOOL, DWORD, HINSTANCE, LPVOID},
@@ -38,6 +39,11 @@ pub(crate) static LOAD_ORDER: OnceLock<Vec<(plugin::Id, profile::LoadOrderEntry)
 	OnceLock::new();
 pub(crate) static MOD_ENTRIES: OnceLock<HashMap<String, HashMap<String, PathBuf>>> =
 	OnceLock::new();
+/// Removals and renames of vanilla entries declared by the enabled mods, keyed
+/// by package name.
+pub(crate) static MOD_EDITS: OnceLock<HashMap<String, Edits>> = OnceLock::new();
+/// Signatures of the instance's registry, shared by every plugin.
+pub(crate) static SIGNATURES: OnceLock<Signatures> = OnceLock::new();
 
 /// When tracing is initialized for logging, the guard to the log file is stored
 /// here to ensure tracing keeps writing to the log file.
@@ -119,34 +125,24 @@ unsafe extern "C" fn main() {
 	let load_order_path = env::var("EMTK_LOAD_ORDER_PATH")
 		.map_err(|e| error!("{}", e))
 		.expect("EMTK_LOAD_ORDER_PATH must be set");
-	let mut file = fs::File::open(&load_order_path)
+	let profile_dir = Path::new(&load_order_path)
+		.parent()
+		.expect("load order file must be inside a profile directory");
+	let profile = emtk_core::blocking::read_profile(profile_dir)
 		.map_err(|e| error!("{}", e))
-		.expect("load order file must be available on disk");
-	let mut buffer = String::new();
-	file.read_to_string(&mut buffer)
+		.expect("profile must be available on disk");
+	let mut enabled_mods = emtk_core::blocking::enabled_mods(&profile)
 		.map_err(|e| error!("{}", e))
-		.expect("load order contents must be valid UTF-8");
-	let load_order: Vec<_> = toml::from_str::<profile::LoadOrder>(&buffer)
-		.expect("load order contents must be valid toml and structure")
-		.into_iter()
-		.filter(|(id, entry)| {
-			if !entry.enabled {
-				return false;
-			}
-			let Ok(mut file) =
-				fs::File::open(cwd.join(id.plugin_dir().join(plugin::Manifest::TOML)))
-			else {
-				return false;
-			};
-			let mut buffer = String::new();
-			let Ok(_) = file.read_to_string(&mut buffer) else {
-				return false;
-			};
-			let Ok(_) = toml::from_str::<plugin::Manifest>(&buffer) else {
-				return false;
-			};
-			true
-		})
+		.expect("enabled mods of the load order must be readable");
+	let mut package_edits: HashMap<String, Edits> = HashMap::new();
+	for (_, _, manifest) in enabled_mods.iter_mut() {
+		for (name, edits) in mem::take(&mut manifest.packages) {
+			package_edits.entry(name).or_default().extend(edits);
+		}
+	}
+	let load_order: Vec<_> = enabled_mods
+		.iter()
+		.map(|(id, entry, _)| (id.clone(), entry.clone()))
 		.collect();
 
 	let native_packages: Vec<_> = cwd
@@ -163,24 +159,35 @@ unsafe extern "C" fn main() {
 			}
 		})
 		.collect();
-	let mut custom_packages: HashMap<String, HashMap<String, PathBuf>> = HashMap::new();
-	for name in native_packages {
-		let mut mod_entries: HashMap<String, PathBuf> = HashMap::new();
-		for (id, _) in load_order.iter() {
-			let loose_files_path = id.packages_dir().join(&name);
-			if let Ok(foreign_dir) = fs::read_dir(cwd.join(loose_files_path)) {
-				let entries: Vec<_> = foreign_dir.filter_map(Result::ok).collect();
-				entries.iter().for_each(|entry| {
-					mod_entries.insert(entry.file_name().display().to_string(), entry.path());
-				});
-			} else {
-				continue;
-			}
-		}
-		custom_packages.insert(name, mod_entries);
+	let ownership = emtk_core::blocking::asset_ownership(&profile)
+		.map_err(|e| error!("{}", e))
+		.expect("assets of the enabled mods must be readable");
+	for conflict in ownership.conflicts() {
+		warn!("{}", conflict);
 	}
+	let custom_packages: HashMap<String, HashMap<String, PathBuf>> = native_packages
+		.into_iter()
+		.map(|name| {
+			let mod_entries = ownership
+				.winners(&name)
+				.map(|(path, owner)| (path.to_string(), owner.path.clone()))
+				.collect();
+			(name, mod_entries)
+		})
+		.collect();
+
+	let signatures_path = cwd.join(Instance::DATA_DIR).join(Signatures::TOML);
+	let signatures = if signatures_path.is_file() {
+		emtk_core::blocking::read_signatures(&signatures_path)
+			.map_err(|e| error!("{}", e))
+			.unwrap_or_default()
+	} else {
+		Signatures::default()
+	};
 
 	MOD_ENTRIES.set(custom_packages).unwrap();
+	MOD_EDITS.set(package_edits).unwrap();
+	SIGNATURES.set(signatures).unwrap();
 	LOAD_ORDER.set(load_order).unwrap();
 
 	info!("Main Hook Running");
@@ -205,20 +212,7 @@ unsafe extern "C" fn main() {
 	// TODO: port plugin configs to emtk_core::plugin::Manifest
 	// gui::inject_gui();
 
-	// let plugin_configs = match read_plugin_configs() {
-	// 	Ok(configs) => configs,
-	// 	Err(e) => {
-	// 		error!("Failed to load plugin configs: {:?}", e);
-	// 		return;
-	// 	}
-	// };
-
-	// for config in plugin_configs {
-	// 	let result = plugins::load_plugin(config);
-	// 	if let Err(e) = result {
-	// 		error!("Failed to load plugin: {:?}", e);
-	// 	}
-	// }
+	plugins::load_plugins(enabled_mods);
 
 	info!("Running Original Program Entrypoint");
 
@@ -2,18 +2,40 @@
 #![allow(dead_code)]
 
 use std::{
-	collections::HashMap,
+	collections::{BTreeMap, HashMap},
+	ffi::{CStr, c_char},
+	path::PathBuf,
 	sync::{Arc, LazyLock, RwLock},
 };
 
-use anyhow::Result;
-use emtk_framework_types::config;
-use libloading::os::windows::Symbol;
+use emtk_framework_types::{
+	config,
+	ffi::{ABI_VERSION, DESCRIPTOR_SYMBOL, DescriptorFn, PluginDescriptor, PluginVTable},
+};
 use safer_ffi::prelude::*;
-use tracing::error;
+use tracing::{error, warn};
 
 use super::write_plugin_config;
 
+/// A plugin library that could not be loaded.
+#[derive(Debug, thiserror::Error)]
+pub enum LoadError {
+	#[error("executable {} is outside of the plugin directory", .0.display())]
+	InvalidPath(PathBuf),
+	#[error("failed to load library: {0}")]
+	Library(#[from] libloading::Error),
+	#[error("library does not export the required symbol \"{0}\"")]
+	MissingSymbol(&'static str),
+	#[error("plugin descriptor is null")]
+	NullDescriptor,
+	#[error("plugin descriptor has a null id")]
+	NullId,
+	#[error("plugin was built for ABI version {found}, expected {expected}")]
+	IncompatibleAbi { expected: u32, found: u32 },
+	#[error("plugin descriptor has the id \"{found}\", expected \"{expected}\"")]
+	IdMismatch { expected: String, found: String },
+}
+
 unsafe impl Send for PluginState {}
 
 pub struct PluginState {
@@ -23,88 +45,146 @@ pub struct PluginState {
 
 	pub lib: libloading::Library,
 
-	pub enable: Symbol<extern "C" fn() -> bool>,
-	pub disable: Symbol<extern "C" fn() -> bool>,
+	/// The callbacks of the plugin, only valid while `lib` is loaded
+	pub vtable: PluginVTable,
+}
+
+impl PluginState {
+	/// Returns a result to the state of the plugin `info` loaded from `lib`.
+	///
+	/// The callbacks are read from the descriptor `lib` exports as
+	/// [`DESCRIPTOR_SYMBOL`], or from loose symbols for plugins built without
+	/// one.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if:
+	///
+	/// - the descriptor or its id is null
+	/// - the descriptor has a different ABI version or plugin id
+	/// - the vtable of the descriptor does not have the `enable` or `disable`
+	///   callbacks
+	/// - `lib` has no descriptor and does not export the `enable` or `disable`
+	///   functions
+	pub unsafe fn new(
+		lib: libloading::Library,
+		info: config::PluginInfo,
+	) -> Result<PluginState, LoadError> {
+		let vtable = match unsafe { lib.get::<DescriptorFn>(DESCRIPTOR_SYMBOL.as_bytes()) } {
+			Ok(descriptor) => unsafe { descriptor_vtable(descriptor(), &info)? },
+			Err(_) => {
+				warn!(
+					"Plugin {} does not export {}. Falling back to loose symbols.",
+					info.config.plugin.id, DESCRIPTOR_SYMBOL
+				);
+				unsafe { legacy_vtable(&lib)? }
+			}
+		};
+
+		Ok(PluginState {
+			loaded: false,
+			enabled: false,
+			info,
+			lib,
+			vtable,
+		})
+	}
+}
 
-	pub send_message: Option<Symbol<extern "C" fn(sender_id: char_p::Box, message: char_p::Box)>>,
+/// Returns a result to the vtable of the plugin `info` described by
+/// `descriptor`.
+unsafe fn descriptor_vtable(
+	descriptor: *const PluginDescriptor,
+	info: &config::PluginInfo,
+) -> Result<PluginVTable, LoadError> {
+	if descriptor.is_null() {
+		return Err(LoadError::NullDescriptor);
+	}
+	// `char_p::Ref` can not be null, so the id is read as a raw pointer and
+	// checked before the descriptor is referenced
+	let id = unsafe { (&raw const (*descriptor).id).cast::<*const c_char>().read() };
+	if id.is_null() {
+		return Err(LoadError::NullId);
+	}
+	let descriptor = unsafe { &*descriptor };
+	if descriptor.abi_version != ABI_VERSION {
+		return Err(LoadError::IncompatibleAbi {
+			expected: ABI_VERSION,
+			found: descriptor.abi_version,
+		});
+	}
+	let id = unsafe { CStr::from_ptr(id) }.to_string_lossy();
+	if id != info.config.plugin.id {
+		return Err(LoadError::IdMismatch {
+			expected: info.config.plugin.id.clone(),
+			found: id.into_owned(),
+		});
+	}
 
-	pub read_setting_bool: Option<Symbol<extern "C" fn(setting: char_p::Box) -> bool>>,
-	pub read_setting_int: Option<Symbol<extern "C" fn(setting: char_p::Box) -> i64>>,
-	pub read_setting_float: Option<Symbol<extern "C" fn(setting: char_p::Box) -> f64>>,
-	pub read_setting_string: Option<Symbol<extern "C" fn(setting: char_p::Box) -> char_p::Box>>,
+	let vtable = unsafe { descriptor.vtable() };
+	if vtable.enable.is_none() {
+		return Err(LoadError::MissingSymbol("enable"));
+	}
+	if vtable.disable.is_none() {
+		return Err(LoadError::MissingSymbol("disable"));
+	}
 
-	pub setting_changed_bool: Option<Symbol<extern "C" fn(setting: char_p::Box, value: bool)>>,
-	pub setting_changed_int: Option<Symbol<extern "C" fn(setting: char_p::Box, value: i64)>>,
-	pub setting_changed_float: Option<Symbol<extern "C" fn(setting: char_p::Box, value: f64)>>,
-	pub setting_changed_string:
-		Option<Symbol<extern "C" fn(setting: char_p::Box, value: char_p::Box)>>,
+	Ok(vtable)
 }
 
-impl PluginState {
-	pub unsafe fn new(lib: libloading::Library, info: config::PluginInfo) -> Result<PluginState> {
-		/// Helper macro to get a symbol from a library & turn it into a raw (no lifetime checks) reference.
-		///
-		/// Make sure that `lib` stays in scope as long as the returned reference is used or the function call will crash.
-		macro_rules! sym {
-			($name:expr, $type:ty) => {
-				if let Ok(sym) = lib.get::<$type>($name) {
-					Some(sym.into_raw())
-				} else {
-					None
-				}
-			};
-		}
+/// Returns a result to the vtable of the loose symbols `lib` exports.
+unsafe fn legacy_vtable(lib: &libloading::Library) -> Result<PluginVTable, LoadError> {
+	/// Helper macro to get a function pointer from a library.
+	///
+	/// Make sure that `lib` stays loaded as long as the returned pointer is used or the function call will crash.
+	macro_rules! sym {
+		($name:expr, $type:ty) => {
+			if let Ok(sym) = lib.get::<$type>($name) {
+				Some(*sym)
+			} else {
+				None
+			}
+		};
+	}
 
-		unsafe {
-			let enable = sym!(b"enable", extern "C" fn() -> bool);
-			let disable = sym!(b"disable", extern "C" fn() -> bool);
+	unsafe {
+		let enable =
+			sym!(b"enable", extern "C" fn() -> bool).ok_or(LoadError::MissingSymbol("enable"))?;
+		let disable =
+			sym!(b"disable", extern "C" fn() -> bool).ok_or(LoadError::MissingSymbol("disable"))?;
 
-			let send_message = sym!(b"on_message", extern "C" fn(char_p::Box, char_p::Box));
+		Ok(PluginVTable {
+			enable: Some(enable),
+			disable: Some(disable),
 
-			let read_setting_bool = sym!(b"read_setting_bool", extern "C" fn(char_p::Box) -> bool);
-			let read_setting_int = sym!(b"read_setting_int", extern "C" fn(char_p::Box) -> i64);
-			let read_setting_float = sym!(b"read_setting_float", extern "C" fn(char_p::Box) -> f64);
-			let read_setting_string = sym!(
+			on_message: sym!(b"on_message", extern "C" fn(char_p::Box, char_p::Box)),
+
+			read_setting_bool: sym!(b"read_setting_bool", extern "C" fn(char_p::Box) -> bool),
+			read_setting_int: sym!(b"read_setting_int", extern "C" fn(char_p::Box) -> i64),
+			read_setting_float: sym!(b"read_setting_float", extern "C" fn(char_p::Box) -> f64),
+			read_setting_string: sym!(
 				b"read_setting_string",
 				extern "C" fn(char_p::Box) -> char_p::Box
-			);
-
-			let setting_changed_bool =
-				sym!(b"setting_changed_bool", extern "C" fn(char_p::Box, bool));
-			let setting_changed_int = sym!(b"setting_changed_int", extern "C" fn(char_p::Box, i64));
-			let setting_changed_float =
-				sym!(b"setting_changed_float", extern "C" fn(char_p::Box, f64));
-			let setting_changed_string = sym!(
+			),
+
+			setting_changed_bool: sym!(b"setting_changed_bool", extern "C" fn(char_p::Box, bool)),
+			setting_changed_int: sym!(b"setting_changed_int", extern "C" fn(char_p::Box, i64)),
+			setting_changed_float: sym!(b"setting_changed_float", extern "C" fn(char_p::Box, f64)),
+			setting_changed_string: sym!(
 				b"setting_changed_string",
 				extern "C" fn(char_p::Box, char_p::Box)
-			);
-
-			Ok(PluginState {
-				loaded: false,
-				enabled: false,
-				info,
-				lib,
-				enable: enable.expect("Plugin does not have an enable function"),
-				disable: disable.expect("Plugin does not have a disable function"),
-
-				send_message,
-
-				read_setting_bool,
-				read_setting_int,
-				read_setting_float,
-				read_setting_string,
-
-				setting_changed_bool,
-				setting_changed_int,
-				setting_changed_float,
-				setting_changed_string,
-			})
-		}
+			),
+		})
 	}
 }
 
+/// The loaded plugins, by the id of their mod, which is also the id of their
+/// config.
 static PLUGIN_MANAGER: LazyLock<RwLock<HashMap<String, Arc<RwLock<PluginState>>>>> =
 	LazyLock::new(|| RwLock::new(HashMap::new()));
+/// The reasons plugins failed, by the id of their mod like [`PLUGIN_MANAGER`].
+static FAILED_PLUGINS: LazyLock<RwLock<BTreeMap<String, String>>> =
+	LazyLock::new(|| RwLock::new(BTreeMap::new()));
 
 pub struct PluginManager;
 
@@ -166,17 +246,37 @@ impl PluginManager {
 		Some(())
 	}
 
-	pub fn add(plugin: PluginState) -> Option<Arc<RwLock<PluginState>>> {
+	pub fn add(plugin: PluginState) -> Arc<RwLock<PluginState>> {
 		let id: String = plugin.info.config.plugin.id.to_owned();
+		let plugin = Arc::new(RwLock::new(plugin));
 		let mut writer = PLUGIN_MANAGER.write().unwrap();
-		writer.insert(
-			plugin.info.config.plugin.id.to_string(),
-			Arc::new(RwLock::new(plugin)),
-		);
+		writer.insert(id, Arc::clone(&plugin));
 
-		let plugin = writer.get(&id);
+		plugin
+	}
 
-		plugin.map(Arc::clone)
+	/// Removes the plugin `id`, unloading its library once no longer used.
+	pub fn remove(id: &str) -> Option<Arc<RwLock<PluginState>>> {
+		let mut writer = PLUGIN_MANAGER.write().unwrap();
+		writer.remove(id)
+	}
+
+	/// Marks the plugin `id` as failed for `reason`. Messages are no longer sent
+	/// to a failed plugin.
+	pub fn set_failed(id: &str, reason: String) {
+		let mut writer = FAILED_PLUGINS.write().unwrap();
+		writer.insert(id.to_string(), reason);
+	}
+
+	pub fn is_failed(id: &str) -> bool {
+		let lock = FAILED_PLUGINS.read().unwrap();
+		lock.contains_key(id)
+	}
+
+	/// Returns the reasons plugins failed, by the id of their mod.
+	pub fn failed() -> BTreeMap<String, String> {
+		let lock = FAILED_PLUGINS.read().unwrap();
+		lock.clone()
 	}
 
 	pub fn get(id: &str) -> Option<Arc<RwLock<PluginState>>> {
@@ -186,9 +286,18 @@ impl PluginManager {
 		Some(Arc::clone(state))
 	}
 
-	pub fn send_message(id: &str, message: PluginMessage) {
+	/// Sends `message` to the plugin `id`, returning false if the plugin is
+	/// unknown or failed.
+	pub fn send_message(id: &str, message: PluginMessage) -> bool {
+		if PluginManager::is_failed(id) {
+			warn!("Cannot send message to failed plugin {}", id);
+			return false;
+		}
 		let lock = PLUGIN_MANAGER.read().unwrap();
-		let state = lock.get(id).unwrap();
+		let Some(state) = lock.get(id) else {
+			warn!("Cannot send message to unknown plugin {}", id);
+			return false;
+		};
 
 		let state = state.read().unwrap();
 
@@ -203,36 +312,38 @@ impl PluginManager {
 		match message {
 			PluginMessage::Message(sender_id, message) => {
 				fn_if_exists!(
-					&state.send_message,
+					&state.vtable.on_message,
 					char_p::new(sender_id),
 					char_p::new(message)
 				)
 			}
 			PluginMessage::Enable => {
-				(state.enable)();
+				fn_if_exists!(&state.vtable.enable)
 			}
 			PluginMessage::Disable => {
-				(state.disable)();
+				fn_if_exists!(&state.vtable.disable)
 			}
 			PluginMessage::SettingChanged((key, value)) => match value {
 				config::PluginConfigSettingValue::Boolean(value) => {
-					fn_if_exists!(&state.setting_changed_bool, char_p::new(key), value);
+					fn_if_exists!(&state.vtable.setting_changed_bool, char_p::new(key), value);
 				}
 				config::PluginConfigSettingValue::Float(value) => {
-					fn_if_exists!(&state.setting_changed_float, char_p::new(key), value);
+					fn_if_exists!(&state.vtable.setting_changed_float, char_p::new(key), value);
 				}
 				config::PluginConfigSettingValue::Integer(value) => {
-					fn_if_exists!(&state.setting_changed_int, char_p::new(key), value);
+					fn_if_exists!(&state.vtable.setting_changed_int, char_p::new(key), value);
 				}
 				config::PluginConfigSettingValue::String(value) => {
 					fn_if_exists!(
-						&state.setting_changed_string,
+						&state.vtable.setting_changed_string,
 						char_p::new(key),
 						char_p::new(value)
 					);
 				}
 			},
 		};
+
+		true
 	}
 }
 
@@ -1,95 +1,244 @@
 pub(crate) mod manager;
-mod parser;
 
-use std::{path::PathBuf, result::Result::Ok};
+use std::{
+	fmt,
+	path::PathBuf,
+	result::Result::Ok,
+	sync::{Arc, Mutex, OnceLock, RwLock},
+};
 
 use anyhow::*;
-use emtk_framework_types::config;
+use emtk_core::{
+	lifecycle::{Lifecycle, Loader, Module},
+	plugin,
+	profile::LoadOrderEntry,
+};
+use emtk_framework_types::config::{self, PluginConfigSettingValue};
 use path_clean::PathClean;
-use tracing::{error, info};
+use tracing::{info, instrument};
 
 use crate::internal::utils::get_game_dir;
 
-pub fn load_plugin(info: config::PluginInfo) -> Result<()> {
+/// The lifecycle of the plugins of the enabled mods, set once they are loaded.
+pub(crate) static LIFECYCLE: OnceLock<Mutex<Lifecycle<LibraryLoader>>> = OnceLock::new();
+
+/// Loads and enables the plugins of `enabled_mods` into [`LIFECYCLE`]. Plugins
+/// that fail are marked failed in the [`manager::PluginManager`] while the rest
+/// keep loading.
+#[instrument(level = "trace", skip(enabled_mods))]
+pub(crate) fn load_plugins(enabled_mods: Vec<(plugin::Id, LoadOrderEntry, plugin::Manifest)>) {
+	let mut lifecycle = Lifecycle::new(LibraryLoader);
+	lifecycle.discover(enabled_mods);
+	lifecycle.resolve();
+	lifecycle.load();
+	lifecycle.enable();
+	for (id, error) in lifecycle.failed() {
+		let reason = error.map_or_else(|| "unknown error".to_string(), ToString::to_string);
+		manager::PluginManager::set_failed(&id.to_string(), reason);
+	}
+
+	LIFECYCLE.set(Mutex::new(lifecycle)).unwrap();
+}
+
+/// Loads the libraries of plugins into the game.
+#[derive(Debug, Default)]
+pub(crate) struct LibraryLoader;
+
+impl Loader for LibraryLoader {
+	type Module = Library;
+
+	#[instrument(level = "trace", skip(self, manifest))]
+	fn load(&mut self, id: &plugin::Id, manifest: &plugin::Manifest) -> Result<Library> {
+		let plugin_dir = get_game_dir().join(id.plugin_dir());
+		let settings_path = plugin_dir.join(plugin::Settings::TOML);
+		let settings = if settings_path.is_file() {
+			let settings = std::fs::read_to_string(&settings_path)?;
+			toml::from_str(&settings)
+				.with_context(|| format!("invalid plugin settings {}", settings_path.display()))?
+		} else {
+			plugin::Settings::default()
+		};
+		let info = config::PluginInfo {
+			config: plugin_config(id, manifest, settings),
+			path: plugin_dir
+				.to_str()
+				.ok_or_else(|| anyhow!("plugin path is not valid UTF-8"))?
+				.to_owned(),
+		};
+
+		Ok(Library {
+			id: id.clone(),
+			state: unsafe { load_library(info)? },
+		})
+	}
+}
+
+/// Returns the config of the plugin `id` from its manifest, with a setting for
+/// each widget of its `settings` identified by the widget's label. The config
+/// has the id of the mod, so the plugin is known by the same id when loaded or
+/// failed in the [`manager::PluginManager`].
+fn plugin_config(
+	id: &plugin::Id,
+	manifest: &plugin::Manifest,
+	settings: plugin::Settings,
+) -> config::PluginConfig {
+	let settings = settings
+		.widgets
+		.into_iter()
+		.map(|widget| {
+			let (label, about, value) = match widget {
+				plugin::Widget::Checkbox(checkbox) => (
+					checkbox.label,
+					checkbox.about,
+					PluginConfigSettingValue::Boolean(checkbox.value),
+				),
+				plugin::Widget::Dropdown(dropdown) => (
+					dropdown.label,
+					dropdown.about,
+					PluginConfigSettingValue::Integer(dropdown.value),
+				),
+				plugin::Widget::Radio(radio) => (
+					radio.label,
+					radio.about,
+					PluginConfigSettingValue::Integer(radio.value),
+				),
+				plugin::Widget::Slider(slider) => (
+					slider.label,
+					slider.about,
+					PluginConfigSettingValue::Float(slider.value),
+				),
+				plugin::Widget::TextInput(text_input) => (
+					text_input.label,
+					text_input.about,
+					PluginConfigSettingValue::String(text_input.value),
+				),
+			};
+			config::PluginConfigSetting {
+				name: label.clone(),
+				id: label,
+				description: about.unwrap_or_default(),
+				default: value.clone(),
+				value: Some(value),
+			}
+		})
+		.collect();
+
+	config::PluginConfig {
+		plugin: config::PluginConfigPlugin {
+			id: id.to_string(),
+			name: manifest.plugin.name.clone(),
+			description: None,
+			version: manifest.plugin.version.clone(),
+			supported_versions: manifest.plugin.supported_versions.clone(),
+			url: String::new(),
+			author: config::PluginConfigAuthor {
+				name: String::new(),
+				contact: None,
+				url: None,
+			},
+			executable: manifest.plugin.executable.clone(),
+			enabled: true,
+		},
+		settings,
+	}
+}
+
+/// A plugin loaded by the [`LibraryLoader`]. Mods without an executable have no
+/// library to run.
+pub(crate) struct Library {
+	id: plugin::Id,
+	state: Option<Arc<RwLock<manager::PluginState>>>,
+}
+
+impl fmt::Debug for Library {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Library")
+			.field("id", &self.id)
+			.field("executable", &self.state.is_some())
+			.finish()
+	}
+}
+
+impl Module for Library {
+	fn enable(&mut self) -> Result<()> {
+		let Some(state) = &self.state else {
+			return Ok(());
+		};
+		// the guard is released before the callback, which may read the state
+		let enable = state.read().unwrap().vtable.enable;
+		if let Some(enable) = enable
+			&& !enable()
+		{
+			return Err(anyhow!("plugin reported that it could not be enabled"));
+		}
+		state.write().unwrap().enabled = true;
+
+		Ok(())
+	}
+
+	fn disable(&mut self) -> Result<()> {
+		let Some(state) = &self.state else {
+			return Ok(());
+		};
+		// the guard is released before the callback, which may read the state
+		let disable = state.read().unwrap().vtable.disable;
+		if let Some(disable) = disable
+			&& !disable()
+		{
+			return Err(anyhow!("plugin reported that it could not be disabled"));
+		}
+		state.write().unwrap().enabled = false;
+
+		Ok(())
+	}
+
+	fn unload(self) -> Result<()> {
+		if let Some(state) = self.state {
+			let id = state.read().unwrap().info.config.plugin.id.clone();
+			manager::PluginManager::remove(&id);
+		}
+
+		Ok(())
+	}
+}
+
+/// Loads the library of the plugin described by `info` and adds it to the
+/// [`manager::PluginManager`], returning `None` if the plugin does not have an
+/// executable.
+unsafe fn load_library(
+	info: config::PluginInfo,
+) -> Result<Option<Arc<RwLock<manager::PluginState>>>, manager::LoadError> {
 	info!(
 		"Loading Plugin: {} ({})",
 		info.config.plugin.name, info.config.plugin.id
 	);
 
-	if info.config.plugin.executable.is_none() {
+	let Some(executable) = info.config.plugin.executable.as_ref() else {
 		info!("Plugin does not have an executable. Skipping.");
-		return Ok(());
-	}
+		return Ok(None);
+	};
 
 	unsafe {
-		let executable = info.config.plugin.executable.as_ref().unwrap();
 		let dll_path = PathBuf::from(&info.path).join(executable);
 		let dll_path = dll_path.clean();
 
 		// Security: Make sure the dll path is in the current mod folder.
 		if !dll_path.starts_with(&info.path) {
-			return Err(anyhow!("Invalid plugin path. {}", dll_path.display()));
+			return Err(manager::LoadError::InvalidPath(dll_path));
 		}
 
 		info!("Loading DLL: {}", dll_path.display());
 		let lib = libloading::Library::new(dll_path)?;
 
 		let state = manager::PluginManager::add(manager::PluginState::new(lib, info.clone())?);
+		let mut writer = state.write().unwrap();
+		writer.loaded = true;
+		drop(writer);
 
-		if let Some(state) = state {
-			if info.config.plugin.enabled {
-				let reader = state.read().unwrap();
-				(reader.enable)();
-			}
-
-			Ok(())
-		} else {
-			Err(anyhow!("Failed to load plugin."))
-		}
+		Ok(Some(state))
 	}
 }
 
-pub fn read_plugin_configs() -> Result<Vec<config::PluginInfo>> {
-	let mut configs = Vec::new();
-
-	let path = get_game_dir().join("mods");
-	if !path.exists() {
-		std::fs::create_dir(&path).expect("error trying to create mods folder");
-	}
-
-	for entry in std::fs::read_dir(path)? {
-		let entry = entry?;
-		if entry.file_type()?.is_dir() {
-			let config_path = entry.path().join("config.toml");
-			if !config_path.exists() {
-				continue;
-			}
-
-			let config = std::fs::read_to_string(&config_path)?;
-			let config = parser::parse_plugin_config(&config);
-
-			match config {
-				Ok(config) => {
-					configs.push(config::PluginInfo {
-						config,
-						path: entry.path().to_str().unwrap().to_owned(),
-					});
-				}
-				Err(e) => {
-					error!(
-						"Failed to parse plugin config for {}. Error: {}",
-						config_path.to_str().unwrap(),
-						e
-					);
-					continue;
-				}
-			}
-		}
-	}
-
-	Ok(configs)
-}
-
 pub fn write_plugin_config(info: &config::PluginInfo) -> Result<()> {
 	let path = PathBuf::from(&info.path).join("config.toml");
 	let toml = toml::to_string_pretty(&info.config)?;
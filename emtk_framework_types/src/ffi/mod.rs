@@ -1,5 +1,9 @@
+mod plugin;
+
 use safer_ffi::prelude::*;
 
+pub use plugin::*;
+
 #[derive_ReprC]
 #[repr(C)]
 pub struct GetSettingReturnValue<T> {
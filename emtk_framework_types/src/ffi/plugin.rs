@@ -0,0 +1,129 @@
+//! The descriptor a plugin library exports through [`DESCRIPTOR_SYMBOL`] so the
+//! framework can check its compatibility before calling into it.
+//!
+//! ```ignore
+//! static VTABLE: PluginVTable = PluginVTable {
+//! 	enable: Some(enable),
+//! 	disable: Some(disable),
+//! 	..PluginVTable::EMPTY
+//! };
+//! static DESCRIPTOR: PluginDescriptor =
+//! 	PluginDescriptor::new(c!("com.yourusername.pluginname"), &VTABLE);
+//!
+//! #[unsafe(no_mangle)]
+//! extern "C" fn emtk_plugin_descriptor() -> *const PluginDescriptor {
+//! 	&DESCRIPTOR
+//! }
+//! ```
+
+use std::{mem, ptr};
+
+use safer_ffi::prelude::*;
+
+/// The version of the plugin ABI, incremented whenever [`PluginDescriptor`] or
+/// the existing callbacks of [`PluginVTable`] change. Appending callbacks to
+/// the vtable does not change the ABI version.
+pub const ABI_VERSION: u32 = 1;
+
+/// The name of the function plugins export, returning a pointer to their
+/// [`PluginDescriptor`].
+pub const DESCRIPTOR_SYMBOL: &str = "emtk_plugin_descriptor";
+
+/// The signature of the function exported as [`DESCRIPTOR_SYMBOL`].
+pub type DescriptorFn = extern "C" fn() -> *const PluginDescriptor;
+
+#[derive_ReprC]
+#[repr(C)]
+pub struct PluginDescriptor {
+	/// The [`ABI_VERSION`] the plugin was built against
+	pub abi_version: u32,
+	/// The unique ID of the plugin, matching its config
+	///
+	/// e.g. `"com.yourusername.pluginname"`
+	pub id: char_p::Ref<'static>,
+	/// The size of the vtable the plugin was built with, so callbacks appended
+	/// to [`PluginVTable`] after it are left unset.
+	pub vtable_size: usize,
+	pub vtable: *const PluginVTable,
+}
+
+// SAFETY: the descriptor only points to static data of the plugin.
+unsafe impl Send for PluginDescriptor {}
+unsafe impl Sync for PluginDescriptor {}
+
+impl PluginDescriptor {
+	/// Returns a descriptor of the plugin `id` built against the current
+	/// [`ABI_VERSION`].
+	pub const fn new(id: char_p::Ref<'static>, vtable: &'static PluginVTable) -> Self {
+		Self {
+			abi_version: ABI_VERSION,
+			id,
+			vtable_size: mem::size_of::<PluginVTable>(),
+			vtable,
+		}
+	}
+
+	/// Returns a copy of the vtable of the plugin, with the callbacks it was not
+	/// built with left unset.
+	///
+	/// # Safety
+	///
+	/// `vtable` must point to at least `vtable_size` bytes of callbacks.
+	pub unsafe fn vtable(&self) -> PluginVTable {
+		let mut vtable = PluginVTable::EMPTY;
+		if self.vtable.is_null() {
+			return vtable;
+		}
+		let size = self.vtable_size.min(mem::size_of::<PluginVTable>());
+		// unset callbacks are null pointers, so the callbacks past `size` stay
+		// `None`
+		unsafe {
+			ptr::copy_nonoverlapping(
+				self.vtable.cast::<u8>(),
+				(&raw mut vtable).cast::<u8>(),
+				size,
+			);
+		}
+
+		vtable
+	}
+}
+
+/// The callbacks of a plugin, all of which are optional. New callbacks are only
+/// ever appended.
+#[derive_ReprC]
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct PluginVTable {
+	pub enable: Option<extern "C" fn() -> bool>,
+	pub disable: Option<extern "C" fn() -> bool>,
+
+	pub on_message: Option<extern "C" fn(sender_id: char_p::Box, message: char_p::Box)>,
+
+	pub read_setting_bool: Option<extern "C" fn(setting: char_p::Box) -> bool>,
+	pub read_setting_int: Option<extern "C" fn(setting: char_p::Box) -> i64>,
+	pub read_setting_float: Option<extern "C" fn(setting: char_p::Box) -> f64>,
+	pub read_setting_string: Option<extern "C" fn(setting: char_p::Box) -> char_p::Box>,
+
+	pub setting_changed_bool: Option<extern "C" fn(setting: char_p::Box, value: bool)>,
+	pub setting_changed_int: Option<extern "C" fn(setting: char_p::Box, value: i64)>,
+	pub setting_changed_float: Option<extern "C" fn(setting: char_p::Box, value: f64)>,
+	pub setting_changed_string: Option<extern "C" fn(setting: char_p::Box, value: char_p::Box)>,
+}
+
+impl PluginVTable {
+	/// A vtable without any callbacks.
+	pub const EMPTY: Self = Self {
+		enable: None,
+		disable: None,
+		on_message: None,
+		read_setting_bool: None,
+		read_setting_int: None,
+		read_setting_float: None,
+		read_setting_string: None,
+		setting_changed_bool: None,
+		setting_changed_int: None,
+		setting_changed_float: None,
+		setting_changed_string: None,
+	};
+}
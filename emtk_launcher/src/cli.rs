@@ -1,10 +1,11 @@
 //! The command line interface for the end-user to manage mods for their game
 //! install
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
+use anyhow::anyhow;
 use clap::{Parser, Subcommand};
-use emtk_core::prelude::*;
+use emtk_core::{Error, ErrorKind, Result, prelude::*, signature};
 use tokio::{
 	fs,
 	io::{self, AsyncReadExt},
@@ -35,7 +36,7 @@ pub(super) struct App {
 
 impl App {
 	#[instrument(level = "trace")]
-	pub(super) async fn run(&self) {
+	pub(super) async fn run(&self) -> Result<()> {
 		// let file_appender = tracing_appender::rolling::never(
 		// 	std::path::absolute(PathBuf::from("./")).unwrap(),
 		// 	"testing.log",
@@ -63,8 +64,9 @@ impl App {
 				.init();
 		}
 
-		if let Some(cmd) = &self.command {
-			cmd.run().await;
+		match &self.command {
+			Some(cmd) => cmd.run().await,
+			None => Ok(()),
 		}
 	}
 }
@@ -81,13 +83,24 @@ enum AppCommands {
 		#[command(subcommand)]
 		command: InstanceCommands,
 	},
+	/// Scan the game executable for signatures
+	Signature {
+		/// # Examples
+		///
+		/// ```sh
+		/// $ emtk signature
+		/// ```
+		#[command(subcommand)]
+		command: SignatureCommands,
+	},
 }
 
 impl AppCommands {
 	#[instrument(level = "trace")]
-	async fn run(&self) {
+	async fn run(&self) -> Result<()> {
 		match self {
 			AppCommands::Instance { command } => command.run().await,
+			AppCommands::Signature { command } => command.run().await,
 		}
 	}
 }
@@ -95,6 +108,13 @@ impl AppCommands {
 /// Manage instances
 #[derive(Debug, Subcommand)]
 enum InstanceCommands {
+	/// Print out Exanima installs found in Steam libraries, GOG and other common
+	/// install locations
+	Discover {
+		/// Import every install found as an instance
+		#[arg(short, long)]
+		import: bool,
+	},
 	Launch,
 	/// Print out a history of imported instances
 	List,
@@ -118,30 +138,68 @@ enum InstanceCommands {
 	Import {
 		path: String,
 	},
+	/// Pin an instance so it is listed first and never pruned from the history
+	Pin {
+		path: String,
+	},
+	/// Remove instances that can no longer be found from the history
+	Prune,
+	/// Remove an instance from the history without touching its files
+	Remove {
+		path: String,
+	},
+	/// Set the display name of the most recent instance, or clear it if no name
+	/// is given
+	Rename {
+		name: Option<String>,
+	},
+	/// Unpin an instance in the history
+	Unpin {
+		path: String,
+	},
 }
 
 impl InstanceCommands {
 	#[instrument(level = "trace")]
-	async fn run(&self) {
+	async fn run(&self) -> Result<()> {
 		match self {
+			InstanceCommands::Discover { import } => self.discover(*import).await,
 			InstanceCommands::Launch => self.launch().await,
 			InstanceCommands::List => self.list().await,
 			InstanceCommands::Mod { command } => command.run().await,
 			InstanceCommands::Profile { command } => command.run().await,
 			InstanceCommands::Import { path } => self.import(path).await,
+			InstanceCommands::Pin { path } => self.pin(path, true).await,
+			InstanceCommands::Prune => self.prune().await,
+			InstanceCommands::Remove { path } => self.remove(path).await,
+			InstanceCommands::Rename { name } => self.rename(name.clone()).await,
+			InstanceCommands::Unpin { path } => self.pin(path, false).await,
 		}
 	}
 
+	/// # Examples
+	///
+	/// ```sh
+	/// $ emtk instance discover --import
+	/// ```
 	#[instrument(level = "trace")]
-	async fn launch(&self) {
-		let instance_history = instance::history().await.unwrap();
-		let instance_path = instance_history.last().unwrap();
-		let instance = Instance::with_path(instance_path)
-			.unwrap()
-			.build()
-			.await
-			.unwrap();
-		crate::launch(instance.path().as_path()).unwrap();
+	async fn discover(&self, import: bool) -> Result<()> {
+		for candidate in emtk_core::discover::discover().await {
+			println!("{} ({})", candidate.path.display(), candidate.source);
+			if import {
+				Instance::with_path(&candidate.path)?.build().await?;
+				info!("instance imported");
+			}
+		}
+
+		Ok(())
+	}
+
+	#[instrument(level = "trace")]
+	async fn launch(&self) -> Result<()> {
+		let instance = recent_instance().await?;
+		crate::warn_incompatible_mods(&instance).await;
+		crate::launch_profile(instance.profile()).await
 	}
 
 	/// Prints out the full path to a history of instances from the instance history
@@ -153,11 +211,23 @@ impl InstanceCommands {
 	/// $ emtk instance list
 	/// ```
 	#[instrument(level = "trace")]
-	async fn list(&self) {
-		let instance_history = instance::history().await.unwrap();
-		for path in instance_history.iter().rev() {
-			println!("{}", path.to_str().unwrap());
+	async fn list(&self) -> Result<()> {
+		let instance_history = instance::history().await?;
+		for entry in instance::sorted_history(instance_history) {
+			let mut line = entry.path.display().to_string();
+			if let Some(name) = entry.name {
+				line = format!("{line} ({name})");
+			}
+			if entry.pinned {
+				line = format!("{line} [pinned]");
+			}
+			if entry.missing {
+				line = format!("{line} [missing]");
+			}
+			println!("{line}");
 		}
+
+		Ok(())
 	}
 
 	/// # Examples
@@ -166,29 +236,248 @@ impl InstanceCommands {
 	/// $ emtk instance import "C:\\Program Files (x86)\\Steam\\steamapps\\common\\Exanima"
 	/// ```
 	#[instrument(level = "trace")]
-	async fn import(&self, path: &str) {
+	async fn import(&self, path: &str) -> Result<()> {
 		let path = PathBuf::from(path);
-		Instance::with_path(path).unwrap().build().await.unwrap();
+		Instance::with_path(path)?.build().await?;
 		info!("instance imported");
+
+		Ok(())
+	}
+
+	/// # Examples
+	///
+	/// ```sh
+	/// $ emtk instance pin "C:\\Program Files (x86)\\Steam\\steamapps\\common\\Exanima"
+	/// ```
+	#[instrument(level = "trace")]
+	async fn pin(&self, path: &str, pinned: bool) -> Result<()> {
+		instance::pin_in_history(&history_path(path), pinned).await
+	}
+
+	/// # Examples
+	///
+	/// ```sh
+	/// $ emtk instance prune
+	/// ```
+	#[instrument(level = "trace")]
+	async fn prune(&self) -> Result<()> {
+		for entry in instance::prune_history().await? {
+			println!("{}", entry.path.display());
+		}
+
+		Ok(())
+	}
+
+	/// # Examples
+	///
+	/// ```sh
+	/// $ emtk instance remove "C:\\Program Files (x86)\\Steam\\steamapps\\common\\Exanima"
+	/// ```
+	#[instrument(level = "trace")]
+	async fn remove(&self, path: &str) -> Result<()> {
+		if instance::remove_from_history(&history_path(path))
+			.await?
+			.is_none()
+		{
+			eprintln!("instance is not in the history");
+		}
+
+		Ok(())
+	}
+
+	/// # Examples
+	///
+	/// ```sh
+	/// $ emtk instance rename "Modded"
+	/// ```
+	#[instrument(level = "trace")]
+	async fn rename(&self, name: Option<String>) -> Result<()> {
+		let mut instance = recent_instance().await?;
+		instance.rename(name).await?;
+
+		Ok(())
+	}
+}
+
+/// Helper function that returns the path as recorded in the instance history,
+/// which is canonicalized unless the instance no longer exists.
+fn history_path(path: &str) -> PathBuf {
+	let path = PathBuf::from(path);
+	path.canonicalize().unwrap_or(path)
+}
+
+/// Helper function that returns a result to the most recently opened instance
+/// in the instance history.
+#[instrument(level = "trace")]
+async fn recent_instance() -> Result<Instance> {
+	let instance_history = instance::history().await?;
+	let entry = instance_history.last().ok_or(Error::new(
+		anyhow!("instance history is empty, import an instance first"),
+		"failed to find most recent instance",
+	))?;
+
+	Instance::with_path(&entry.path)?.build().await
+}
+
+/// Returns the exit code of the CLI for an error so scripts can tell apart
+/// failures without matching on the message. `2` is left for usage errors
+/// reported by clap.
+#[instrument(level = "trace")]
+pub(super) fn exit_code(e: &Error) -> u8 {
+	match e.kind() {
+		ErrorKind::InstanceNotFound { .. } => 3,
+		ErrorKind::ExecutableMissing { .. } => 4,
+		ErrorKind::InstanceLocked { .. } => 5,
+		ErrorKind::InvalidPluginId { .. } => 6,
+		ErrorKind::ManifestInvalid { .. } => 7,
+		ErrorKind::LoadOrderCorrupt { .. } => 8,
+		ErrorKind::DependencyUnmet { .. } => 9,
+		_ => 1,
 	}
 }
 
 /// Manage instance mods
 #[derive(Debug, Subcommand)]
 enum ModCommands {
+	/// Print out the assets provided by several enabled mods of the active
+	/// profile and the mod whose asset is used
+	Conflicts,
 	/// Print out README of a mod by their plugin ID
 	Info { id: String },
+	/// Install a mod from a zip or tar.gz archive
+	Install { path: String },
 	/// Print out currently installed mods for the instance
 	List,
+	/// Pack a mod directory into a zip archive for distribution
+	Pack {
+		path: String,
+		output: String,
+		/// Build the loose files of each game package into a package file
+		#[arg(short, long)]
+		prebuild: bool,
+	},
+	/// Restore the previous version of an upgraded mod by their plugin ID
+	Rollback { id: String },
+	/// Remove a mod by their plugin ID from the instance and every profile
+	Uninstall { id: String },
 }
 
 impl ModCommands {
 	#[instrument(level = "trace")]
-	async fn run(&self) {
+	async fn run(&self) -> Result<()> {
 		match self {
+			ModCommands::Conflicts => self.conflicts().await,
 			ModCommands::Info { id } => self.info(id).await,
+			ModCommands::Install { path } => self.install(path).await,
 			ModCommands::List => self.list().await,
+			ModCommands::Pack {
+				path,
+				output,
+				prebuild,
+			} => self.pack(path, output, *prebuild).await,
+			ModCommands::Rollback { id } => self.rollback(id).await,
+			ModCommands::Uninstall { id } => self.uninstall(id).await,
+		}
+	}
+
+	/// # Examples
+	///
+	/// ```sh
+	/// $ emtk instance mod conflicts
+	/// ```
+	#[instrument(level = "trace")]
+	async fn conflicts(&self) -> Result<()> {
+		let instance = recent_instance().await?;
+		let conflicts = instance.profile().asset_ownership().await?.conflicts();
+		if conflicts.is_empty() {
+			println!("no conflicts between enabled mods");
 		}
+		for conflict in conflicts {
+			println!("{}", conflict);
+		}
+
+		Ok(())
+	}
+
+	/// # Examples
+	///
+	/// ```sh
+	/// $ emtk instance mod uninstall com.example.my-mod
+	/// ```
+	#[instrument(level = "trace")]
+	async fn uninstall(&self, maybe_id: &str) -> Result<()> {
+		let plugin_id =
+			plugin::Id::try_from(maybe_id).map_err(Error::msg("failed to parse plugin id"))?;
+		let mut instance = recent_instance().await?;
+		let uninstalled = instance.uninstall_mod(&plugin_id).await?;
+		println!(
+			"uninstalled {} from {} profile(s)",
+			plugin_id,
+			uninstalled.profiles.len()
+		);
+		for dependent in uninstalled.dependents {
+			println!("warning: {} depends on {}", dependent, plugin_id);
+		}
+
+		Ok(())
+	}
+
+	/// # Examples
+	///
+	/// ```sh
+	/// $ emtk instance mod install com.example.my-mod.zip
+	/// ```
+	#[instrument(level = "trace")]
+	async fn install(&self, path: &str) -> Result<()> {
+		let mut instance = recent_instance().await?;
+		let installed = instance.install_mod(path).await?;
+		match installed.previous_version {
+			Some(previous_version) => println!(
+				"upgraded {} from {} to {}",
+				installed.id, previous_version, installed.version
+			),
+			None => println!("installed {} {}", installed.id, installed.version),
+		}
+
+		Ok(())
+	}
+
+	/// Validates and packs a mod directory into a zip archive along with a checksum
+	/// file.
+	///
+	/// # Examples
+	///
+	/// ```sh
+	/// $ emtk instance mod pack ./com.example.my-mod ./com.example.my-mod.zip --prebuild
+	/// ```
+	#[instrument(level = "trace")]
+	async fn pack(&self, path: &str, output: &str, prebuild: bool) -> Result<()> {
+		let packed =
+			emtk_core::pack::pack_mod(Path::new(path), Path::new(output), prebuild).await?;
+		println!(
+			"packed {} {} with {} file(s)",
+			packed.id,
+			packed.version,
+			packed.files.len()
+		);
+
+		Ok(())
+	}
+
+	/// # Examples
+	///
+	/// ```sh
+	/// $ emtk instance mod rollback com.example.my-mod
+	/// ```
+	#[instrument(level = "trace")]
+	async fn rollback(&self, maybe_id: &str) -> Result<()> {
+		let plugin_id =
+			plugin::Id::try_from(maybe_id).map_err(Error::msg("failed to parse plugin id"))?;
+		let mut instance = recent_instance().await?;
+		let version = instance.rollback_mod(&plugin_id).await?;
+		println!("rolled back {} to {}", plugin_id, version);
+
+		Ok(())
 	}
 
 	/// # Examples
@@ -197,20 +486,17 @@ impl ModCommands {
 	/// emtk instance mod info com.example.my-mod
 	/// ```
 	#[instrument(level = "trace")]
-	async fn info(&self, maybe_id: &str) {
+	async fn info(&self, maybe_id: &str) -> Result<()> {
 		let skin = termimad::MadSkin::default();
-		let plugin_id = plugin::Id::try_from(maybe_id).unwrap();
-		let instance_history = instance::history().await.unwrap();
-		let instance_path = instance_history.last().unwrap();
-		let instance = Instance::with_path(instance_path)
-			.unwrap()
-			.build()
+		let plugin_id =
+			plugin::Id::try_from(maybe_id).map_err(Error::msg("failed to parse plugin id"))?;
+		let instance = recent_instance().await?;
+		let mut read_mods_dir = read_mods_dir(&instance).await?;
+		while let Some(entry) = read_mods_dir
+			.next_entry()
 			.await
-			.unwrap();
-		let mut read_mods_dir = fs::read_dir(instance.mods_dir().await.unwrap())
-			.await
-			.unwrap();
-		while let Some(entry) = read_mods_dir.next_entry().await.unwrap() {
+			.map_err(Error::msg("failed to read next entry in mods directory"))?
+		{
 			let entry_path = entry.path();
 			if !entry_path.is_dir() {
 				continue;
@@ -224,10 +510,15 @@ impl ModCommands {
 				if !readme_path.is_file() {
 					break;
 				}
-				let file = fs::File::open(readme_path).await.unwrap();
+				let file = fs::File::open(readme_path)
+					.await
+					.map_err(Error::msg("failed to open mod readme"))?;
 				let mut reader = io::BufReader::new(file);
 				let mut buffer = String::new();
-				reader.read_to_string(&mut buffer).await.unwrap();
+				reader
+					.read_to_string(&mut buffer)
+					.await
+					.map_err(Error::msg("failed to read mod readme into buffer"))?;
 
 				// crossterm::execute!(io::stdout(), EnterAlternateScreen).unwrap();
 
@@ -239,6 +530,8 @@ impl ModCommands {
 				break;
 			}
 		}
+
+		Ok(())
 	}
 
 	/// # Examples
@@ -247,18 +540,14 @@ impl ModCommands {
 	/// $ emtk instance mod list
 	/// ```
 	#[instrument(level = "trace")]
-	async fn list(&self) {
-		let instance_history = instance::history().await.unwrap();
-		let instance_path = instance_history.last().unwrap();
-		let instance = Instance::with_path(instance_path)
-			.unwrap()
-			.build()
-			.await
-			.unwrap();
-		let mut read_mods_dir = fs::read_dir(instance.mods_dir().await.unwrap())
+	async fn list(&self) -> Result<()> {
+		let instance = recent_instance().await?;
+		let mut read_mods_dir = read_mods_dir(&instance).await?;
+		while let Some(entry) = read_mods_dir
+			.next_entry()
 			.await
-			.unwrap();
-		while let Some(entry) = read_mods_dir.next_entry().await.unwrap() {
+			.map_err(Error::msg("failed to read next entry in mods directory"))?
+		{
 			let entry_path = entry.path();
 			if !entry_path.is_dir() {
 				continue;
@@ -272,56 +561,311 @@ impl ModCommands {
 				println!("{}", plugin_id);
 			}
 		}
+
+		Ok(())
+	}
+}
+
+/// Helper function that returns a result to the entries of the instance's mods
+/// directory.
+#[instrument(level = "trace")]
+async fn read_mods_dir(instance: &Instance) -> Result<fs::ReadDir> {
+	let mods_dir = instance
+		.mods_dir()
+		.await
+		.map_err(Error::msg("failed to get mods directory"))?;
+	fs::read_dir(mods_dir)
+		.await
+		.map_err(Error::msg("failed to read mods directory"))
+}
+
+/// Scan the game executable for signatures
+#[derive(Debug, Subcommand)]
+enum SignatureCommands {
+	/// Print out the relative virtual addresses of every match of an IDA-style
+	/// signature, e.g. "48 8B ?? ?? 89", in the code of a PE file
+	Scan {
+		signature: String,
+		/// Path to the PE file, defaults to the executable of the most recent
+		/// instance
+		#[arg(short, long)]
+		path: Option<String>,
+	},
+	/// Print out the relative virtual address of every signature of a
+	/// signatures.toml registry for the game version of a PE file
+	Verify {
+		/// Path to the PE file, defaults to the executable of the most recent
+		/// instance
+		#[arg(short, long)]
+		path: Option<String>,
+		/// Path to the registry, defaults to the registry of the most recent
+		/// instance
+		#[arg(short, long)]
+		registry: Option<String>,
+	},
+}
+
+impl SignatureCommands {
+	#[instrument(level = "trace")]
+	async fn run(&self) -> Result<()> {
+		match self {
+			SignatureCommands::Scan { signature, path } => {
+				self.scan(signature, path.as_deref()).await
+			}
+			SignatureCommands::Verify { path, registry } => {
+				self.verify(path.as_deref(), registry.as_deref()).await
+			}
+		}
+	}
+
+	/// # Examples
+	///
+	/// ```sh
+	/// $ emtk signature scan "48 8B ?? ?? 89" --path ./Exanima.exe
+	/// ```
+	#[instrument(level = "trace")]
+	async fn scan(&self, signature: &str, path: Option<&str>) -> Result<()> {
+		let pattern: signature::Pattern = signature.parse()?;
+		let path = match path {
+			Some(path) => PathBuf::from(path),
+			None => recent_instance().await?.path().join(Instance::BINARY),
+		};
+		let rvas = signature::Image::read(&path).await?.scan(&pattern)?;
+		if rvas.is_empty() {
+			return Err(Error::new(
+				anyhow!("no match for \"{}\" in \"{}\"", pattern, path.display()),
+				"failed to scan signature",
+			));
+		}
+		for rva in rvas {
+			println!("{:#010X}", rva);
+		}
+
+		Ok(())
+	}
+
+	/// Resolves every signature of the registry so that signatures broken by a
+	/// game update are found before a plugin using them is shipped.
+	///
+	/// # Examples
+	///
+	/// ```sh
+	/// $ emtk signature verify --path ./Exanima.exe --registry ./signatures.toml
+	/// ```
+	#[instrument(level = "trace")]
+	async fn verify(&self, path: Option<&str>, registry: Option<&str>) -> Result<()> {
+		let instance_path = if path.is_none() || registry.is_none() {
+			recent_instance().await?.path().clone()
+		} else {
+			PathBuf::new()
+		};
+		let path = path.map_or_else(|| instance_path.join(Instance::BINARY), PathBuf::from);
+		let registry = registry.map_or_else(
+			|| {
+				instance_path
+					.join(Instance::DATA_DIR)
+					.join(signature::Signatures::TOML)
+			},
+			PathBuf::from,
+		);
+
+		let signatures = signature::Signatures::read(&registry).await?;
+		let image = signature::Image::read(&path).await?;
+		let game_version = image.game_version();
+		println!("game version {}", game_version.name());
+
+		let mut failed = 0;
+		for name in signatures.names() {
+			match signatures.resolve(name, &image, &game_version) {
+				Ok(rva) => println!("{name}: {rva:#010X}"),
+				Err(e) => {
+					failed += 1;
+					println!("{name}: {e}");
+				}
+			}
+		}
+		if failed > 0 {
+			return Err(Error::new(
+				anyhow!("{failed} signature(s) could not be resolved"),
+				"failed to verify signatures",
+			));
+		}
+
+		Ok(())
 	}
 }
 
 /// Manage instance profiles
 #[derive(Debug, Subcommand)]
 enum ProfileCommands {
+	/// Build the game's packages with the enabled mods merged into them, for
+	/// use without the framework
+	Build,
 	/// Create a new profile
-	Create,
-	// Delete an existing profile
+	Create {
+		name: String,
+		/// Name of an existing profile to copy the load order from
+		#[arg(short, long)]
+		from: Option<String>,
+	},
+	/// Delete an existing profile
 	Delete {
+		name: String,
+		/// Switch to the default profile first if the profile is currently in use
 		#[arg(short, long)]
 		force: bool,
 	},
+	/// Export the current profile to a modpack file
+	Export { path: String },
+	/// Create a new profile from a modpack file
+	Import { path: String, name: String },
 	/// Print out the available profiles for the most recently imported instance
 	List,
+	/// Rename an existing profile
+	Rename { name: String, new_name: String },
 }
 
 impl ProfileCommands {
 	#[instrument(level = "trace")]
-	async fn run(&self) {
+	async fn run(&self) -> Result<()> {
 		match self {
-			ProfileCommands::Create => self.create(),
-			ProfileCommands::Delete { force } => self.delete(*force),
+			ProfileCommands::Build => self.build().await,
+			ProfileCommands::Create { name, from } => self.create(name, from.as_deref()).await,
+			ProfileCommands::Delete { name, force } => self.delete(name, *force).await,
+			ProfileCommands::Export { path } => self.export(path).await,
+			ProfileCommands::Import { path, name } => self.import(path, name).await,
 			ProfileCommands::List => self.list().await,
+			ProfileCommands::Rename { name, new_name } => self.rename(name, new_name).await,
 		}
 	}
 
+	/// # Examples
+	///
+	/// ```sh
+	/// $ emtk instance profile build
+	/// ```
 	#[instrument(level = "trace")]
-	fn create(&self) {
-		todo!("profile create wip");
+	async fn build(&self) -> Result<()> {
+		let instance = recent_instance().await?;
+		for path in instance.profile().build_packages().await? {
+			println!("{}", path.display());
+		}
+		info!("profile packages built");
+
+		Ok(())
 	}
 
+	/// # Examples
+	///
+	/// ```sh
+	/// $ emtk instance profile create "My Profile" --from Default
+	/// ```
 	#[instrument(level = "trace")]
-	fn delete(&self, _force: bool) {
-		todo!("profile delete wip");
+	async fn create(&self, name: &str, from: Option<&str>) -> Result<()> {
+		let instance = recent_instance().await?;
+		if let Some(source) = from {
+			instance.clone_profile(source, name).await?;
+		} else {
+			instance.create_profile(name).await?;
+		}
+		info!("profile created");
+
+		Ok(())
 	}
 
+	/// # Examples
+	///
+	/// ```sh
+	/// $ emtk instance profile delete "My Profile"
+	/// ```
 	#[instrument(level = "trace")]
-	async fn list(&self) {
-		let instance_history = instance::history().await.unwrap();
-		let instance_path = instance_history.last().unwrap();
-		let instance = Instance::with_path(instance_path)
-			.unwrap()
-			.build()
+	async fn delete(&self, name: &str, force: bool) -> Result<()> {
+		let mut instance = recent_instance().await?;
+		if force && instance.profile().path() == &instance.profile_dir(name).await? {
+			let default_profile_dir = instance.profile_dir(Instance::DEFAULT_PROFILE_DIR).await?;
+			let default_profile = Profile::with_path(default_profile_dir)
+				.await?
+				.build()
+				.await?;
+			instance.set_profile(default_profile).await?;
+		}
+		instance.delete_profile(name).await?;
+		info!("profile deleted");
+
+		Ok(())
+	}
+
+	/// # Examples
+	///
+	/// ```sh
+	/// $ emtk instance profile export "my-profile.emtkpack"
+	/// ```
+	#[instrument(level = "trace")]
+	async fn export(&self, path: &str) -> Result<()> {
+		let instance = recent_instance().await?;
+		let modpack = instance.export_modpack().await?;
+		modpack.write(path).await?;
+		info!("profile exported");
+
+		Ok(())
+	}
+
+	/// # Examples
+	///
+	/// ```sh
+	/// $ emtk instance profile import "my-profile.emtkpack" "My Profile"
+	/// ```
+	#[instrument(level = "trace")]
+	async fn import(&self, path: &str, name: &str) -> Result<()> {
+		let instance = recent_instance().await?;
+		let modpack = Modpack::read(path).await?;
+		let (_, report) = instance.import_modpack(&modpack, name).await?;
+		for id in &report.missing {
+			println!("missing: {id}");
+		}
+		for mismatch in &report.version_mismatches {
+			println!(
+				"wrong version: {} (expected {}, found {})",
+				mismatch.id, mismatch.expected, mismatch.found
+			);
+		}
+		for id in &report.kept_settings {
+			println!("kept existing settings: {id}");
+		}
+		for (id, reason) in &report.failed_settings {
+			println!("settings not restored: {id} ({reason})");
+		}
+		info!("profile imported");
+
+		Ok(())
+	}
+
+	#[instrument(level = "trace")]
+	async fn list(&self) -> Result<()> {
+		let instance = recent_instance().await?;
+		let profile_paths = instance
+			.profile_dirs()
 			.await
-			.unwrap();
-		let profile_paths = instance.profile_dirs().await.unwrap();
+			.map_err(Error::msg("failed to read profile directories"))?;
 		for path in profile_paths.iter().rev() {
 			// TODO: if instance.profile.path == path, indicate profile as current?
-			println!("{}", path.to_str().unwrap());
+			println!("{}", path.display());
 		}
+
+		Ok(())
+	}
+
+	/// # Examples
+	///
+	/// ```sh
+	/// $ emtk instance profile rename "My Profile" "My Other Profile"
+	/// ```
+	#[instrument(level = "trace")]
+	async fn rename(&self, name: &str, new_name: &str) -> Result<()> {
+		let mut instance = recent_instance().await?;
+		instance.rename_profile(name, new_name).await?;
+		info!("profile renamed");
+
+		Ok(())
 	}
 }
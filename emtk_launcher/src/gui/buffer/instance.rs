@@ -1,15 +1,22 @@
 use std::{
-	env, fmt,
+	fmt,
 	path::{Path, PathBuf},
 };
 
 use anyhow::anyhow;
-use emtk_core::{Error, TomlError, instance, plugin, profile};
+use emtk_core::{
+	Error, TomlError,
+	conflict::Conflict,
+	instance, plugin, profile,
+	watch::{Watch, WatchEvent},
+};
 use getset::Getters;
 use iced::{
-	Alignment, Border, Element, Fill, Font, Length, Point, Rectangle, Renderer, Shadow, Task,
-	Theme,
+	Alignment, Border, Element, Fill, Font, Length, Point, Rectangle, Renderer, Shadow,
+	Subscription, Task, Theme,
 	advanced::widget as iced_widget,
+	futures::{SinkExt, Stream, channel::mpsc},
+	stream::channel,
 	widget::{
 		Button, Space, Text, center_x, checkbox, column, horizontal_rule, horizontal_space,
 		markdown, pick_list, responsive, row, rule, scrollable, text, text_editor, text_input,
@@ -305,6 +312,11 @@ impl fmt::Display for MarkdownKind {
 pub struct Instance {
 	#[getset(get = "pub")]
 	inner: emtk_core::Instance,
+	/// Assets provided by several enabled mods of the active profile.
+	conflicts: Vec<Conflict>,
+	/// Whether the cache build of the profile is still valid, known after mods
+	/// or the load order change on disk.
+	is_cache_build_valid: Option<bool>,
 	is_plugin_maximized: bool,
 	markdown_kind: Option<MarkdownKind>,
 	plugin: Plugin,
@@ -318,6 +330,7 @@ pub struct Instance {
 #[derive(Debug, Clone)]
 pub enum Message {
 	ClickedRow(usize),
+	Conflicts(Vec<Conflict>),
 	DraggedRow(Point, Rectangle),
 	DraggedRowCanceled,
 	DroppedRow(Point, Rectangle),
@@ -346,6 +359,7 @@ pub enum Message {
 	ProfileFormSubmitted,
 	Profiles(Vec<PathBuf>),
 	ProfileSelected(String),
+	RefreshConflicts,
 	RefreshProfiles,
 	ReorderRows(Vec<(iced_widget::Id, Rectangle)>),
 	SettingsPressed,
@@ -356,6 +370,17 @@ pub enum Message {
 	TableSyncHeader(scrollable::AbsoluteOffset),
 	TextEditorLoaded(String),
 	TextEditorAction(text_editor::Action),
+	Watched(Watched),
+}
+
+/// A batch of changes made on disk to the mods or active profile of an
+/// instance, along with the profile rebuilt after the changes.
+#[derive(Debug, Clone)]
+pub struct Watched {
+	pub instance_path: PathBuf,
+	pub events: Vec<WatchEvent>,
+	pub profile: emtk_core::Profile,
+	pub is_cache_build_valid: bool,
 }
 
 impl Instance {
@@ -367,7 +392,8 @@ impl Instance {
 				.chain(
 					Task::future(async move {
 						let instance_history = instance::history().await?;
-						let Some(instance_path) = instance_history.last() else {
+						let Some(instance_path) = instance_history.last().map(|entry| &entry.path)
+						else {
 							return Err(Error::new(
 								anyhow!("instance history is empty"),
 								"failed to initialize instance",
@@ -379,7 +405,7 @@ impl Instance {
 					.map(|result| {
 						result
 							.map(Message::Init)
-							.map_err(|e| error!("{}", e))
+							.map_err(|e| error!("{}", crate::gui::describe(&e)))
 							.unwrap_or(Message::InitFailed)
 					}),
 				)
@@ -398,7 +424,7 @@ impl Instance {
 						.map(|result| {
 							result
 								.map(|instance| Message::Init(instance))
-								.map_err(|e| error!("{}", e))
+								.map_err(|e| error!("{}", crate::gui::describe(&e)))
 								.unwrap_or(Message::InitFailed)
 						}),
 				)
@@ -412,6 +438,8 @@ impl Instance {
 		let table = Table::new(&inner);
 		let mut instance = Self {
 			inner,
+			conflicts: Vec::new(),
+			is_cache_build_valid: None,
 			is_plugin_maximized: false,
 			markdown_kind: None,
 			plugin: Plugin::default(),
@@ -434,6 +462,7 @@ impl Instance {
 					MarkdownKind::default(),
 				)));
 			}
+			Message::Conflicts(conflicts) => self.conflicts = conflicts,
 			Message::DraggedRow(point, _bounds) => {
 				if let Some(focus) = self.table.focus_row
 					&& let Some(row) = self.table.rows.get(focus)
@@ -535,32 +564,30 @@ impl Instance {
 			Message::Init(instance) => {
 				self.table = Table::new(&instance);
 				self.inner = instance;
-				return Action::Task(Task::done(Message::RefreshProfiles));
+				return Action::Task(Task::batch([
+					Task::done(Message::RefreshProfiles),
+					Task::done(Message::RefreshConflicts),
+				]));
 			}
 			Message::InitFailed => return Action::InitFailed,
 			Message::Launch => {
+				// the cache is rebuilt by the launch if needed
+				self.is_cache_build_valid = None;
+				let instance = self.inner.clone();
 				let profile = self.inner.profile().clone();
-				// TODO: env should be set within the launch() function to prevent forgetting to set this env
-				unsafe {
-					env::set_var(
-						"EMTK_LOAD_ORDER_PATH",
-						self.inner()
-							.profile()
-							.path()
-							.join(emtk_core::Profile::LOAD_ORDER_TOML),
-					);
-				}
 				return Action::Task(
 					Task::done(Message::Loading)
 						.chain(
-							Task::perform(
-								async move { profile.game_dir().await.map_err(|e| error!("{}", e)) },
-								|result| {
-									if let Ok(path) = result {
-										let _ = crate::launch(&path).map_err(|e| error!("{}", e));
-									}
-								},
-							)
+							Task::future(async move {
+								// the game's saves are synced back after the game exits so the
+								// launch must not block the gui
+								tokio::spawn(async move {
+									crate::warn_incompatible_mods(&instance).await;
+									let _ = crate::launch_profile(&profile)
+										.await
+										.map_err(|e| error!("{}", crate::gui::describe(&e)));
+								});
+							})
 							.discard(),
 						)
 						.chain(Task::done(Message::Loaded)),
@@ -769,7 +796,7 @@ impl Instance {
 					))
 					.map_err(|e| error!("{}", e))
 				else {
-					return Action::None;
+					return Action::Task(Task::done(Message::RefreshConflicts));
 				};
 				info!("profile path serialized into buffer");
 
@@ -797,6 +824,7 @@ impl Instance {
 							})
 							.discard(),
 						)
+						.chain(Task::done(Message::RefreshConflicts))
 						.chain(Task::done(Message::Loaded)),
 				);
 			}
@@ -846,7 +874,9 @@ impl Instance {
 								}
 								emtk_core::Profile::with_path(new_dir).await?.build().await
 							})
-							.map(|result| result.map_err(|e| error!("{}", e)))
+							.map(|result| {
+								result.map_err(|e| error!("{}", crate::gui::describe(&e)))
+							})
 							.and_then(|profile| {
 								Task::batch([
 									Task::done(Message::ProfileChanged(profile)),
@@ -878,12 +908,26 @@ impl Instance {
 									.build()
 									.await
 							})
-							.map(|result| result.map_err(|e| error!("{}", e)))
+							.map(|result| {
+								result.map_err(|e| error!("{}", crate::gui::describe(&e)))
+							})
 							.and_then(|profile| Task::done(Message::ProfileChanged(profile))),
 						)
 						.chain(Task::done(Message::Loaded)),
 				);
 			}
+			Message::RefreshConflicts => {
+				let profile = self.inner.profile().clone();
+				return Action::Task(
+					Task::future(async move { profile.asset_ownership().await })
+						.map(|result| {
+							result
+								.map(|ownership| ownership.conflicts())
+								.map_err(|e| error!("{}", crate::gui::describe(&e)))
+						})
+						.and_then(|conflicts| Task::done(Message::Conflicts(conflicts))),
+				);
+			}
 			Message::RefreshProfiles => {
 				let path = self.inner.path().clone();
 				return Action::Task(
@@ -1033,6 +1077,21 @@ impl Instance {
 					content.perform(action);
 				}
 			}
+			Message::Watched(watched) => {
+				// the active profile changed since the changes were read
+				if watched.profile.path() != self.inner.profile().path() {
+					return Action::None;
+				}
+				info!("instance changed on disk {:?}", watched.events);
+				self.is_cache_build_valid = Some(watched.is_cache_build_valid);
+				// changes written by the launcher itself are already reflected in the table
+				if watched.profile.load_order() != self.inner.profile().load_order() {
+					self.inner.profile = watched.profile;
+					self.table = Table::new(&self.inner);
+					info!("load order table refreshed");
+				}
+				return Action::Task(Task::done(Message::RefreshConflicts));
+			}
 		}
 
 		Action::None
@@ -1078,9 +1137,37 @@ impl Instance {
 			..iced::widget::button::success(theme, status)
 		});
 
+		let cache_status: Element<_> = if self.is_cache_build_valid == Some(false) {
+			tooltip(
+				text("Mods changed").style(text::warning),
+				text("the cache will be rebuilt on the next launch"),
+				tooltip::Position::Bottom,
+			)
+			.into()
+		} else {
+			Space::new(0, 0).into()
+		};
+
+		let conflict_status: Element<_> = if self.conflicts.is_empty() {
+			Space::new(0, 0).into()
+		} else {
+			tooltip(
+				text(format!("{} conflict(s)", self.conflicts.len())).style(text::warning),
+				column(
+					self.conflicts
+						.iter()
+						.map(|conflict| text(conflict.to_string()).into()),
+				),
+				tooltip::Position::Bottom,
+			)
+			.into()
+		};
+
 		let controls = row![
 			profile_controls,
 			horizontal_space(),
+			conflict_status,
+			cache_status,
 			instance_settings_btn,
 			play_btn
 		]
@@ -1134,6 +1221,23 @@ impl Instance {
 			.unwrap_or(self.inner.path().display().to_string())
 	}
 
+	/// Returns a subscription to the changes made on disk to the mods and active
+	/// profile of the instance.
+	#[instrument(level = "trace")]
+	pub fn subscription(&self) -> Subscription<Watched> {
+		if self.inner.path().as_os_str().is_empty() {
+			return Subscription::none();
+		}
+
+		Subscription::run_with(
+			(
+				self.inner.path().clone(),
+				self.inner.profile().path().clone(),
+			),
+			watch,
+		)
+	}
+
 	#[instrument(level = "trace")]
 	fn plugin_controls(&self) -> Element<'_, Message> {
 		use MarkdownKind::*;
@@ -1522,6 +1626,8 @@ impl Default for Instance {
 	fn default() -> Self {
 		Self {
 			inner: emtk_core::Instance::default(),
+			conflicts: Vec::new(),
+			is_cache_build_valid: None,
 			is_plugin_maximized: false,
 			markdown_kind: None,
 			plugin: Plugin::default(),
@@ -1534,6 +1640,56 @@ impl Default for Instance {
 	}
 }
 
+/// Returns a stream of the changes made on disk to the mods of the instance at
+/// `instance_path` and the profile at `profile_dir`. Bursts of changes are
+/// batched together before the profile is rebuilt.
+#[instrument(level = "trace")]
+fn watch((instance_path, profile_dir): &(PathBuf, PathBuf)) -> impl Stream<Item = Watched> {
+	let instance_path = instance_path.clone();
+	let profile_dir = profile_dir.clone();
+	channel(16, |mut tx: mpsc::Sender<Watched>| async move {
+		let mods_dir = instance_path.join(emtk_core::Instance::MODS_DIR);
+		let Ok(mut watch) = Watch::new(&mods_dir, &profile_dir).map_err(|e| error!("{}", e)) else {
+			return;
+		};
+
+		while let Some(event) = watch.recv().await {
+			let mut events = vec![event];
+			while let Some(event) = watch.try_recv() {
+				if !events.contains(&event) {
+					events.push(event);
+				}
+			}
+
+			let Ok(profile) = async {
+				emtk_core::Profile::with_path(&profile_dir)
+					.await?
+					.build()
+					.await
+			}
+			.await
+			.map_err(|e| error!("{}", crate::gui::describe(&e))) else {
+				continue;
+			};
+			let is_cache_build_valid = profile
+				.is_cache_build_valid()
+				.await
+				.map_err(|e| error!("{}", e))
+				.unwrap_or(false);
+
+			let watched = Watched {
+				instance_path: instance_path.clone(),
+				events,
+				profile,
+				is_cache_build_valid,
+			};
+			if tx.send(watched).await.is_err() {
+				break;
+			}
+		}
+	})
+}
+
 pub fn view_checkbox<'a>(plugin_checkbox: plugin::Checkbox) -> Element<'a, Message> {
 	Space::new(Fill, Fill).into()
 }
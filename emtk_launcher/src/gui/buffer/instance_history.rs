@@ -2,20 +2,15 @@ use super::*;
 
 use std::path::PathBuf;
 
-use emtk_core::instance::write_instance_history;
+use emtk_core::{HistoryEntry, discover::Candidate, instance};
 use getset::Getters;
 use iced::{
 	Task, Theme,
-	futures::future,
 	widget::{
 		column, container, horizontal_rule, mouse_area, responsive, right_center, scrollable,
 		stack, text,
 	},
 };
-use tokio::{
-	fs,
-	io::{self, AsyncReadExt},
-};
 use tracing::{error, instrument, warn};
 
 use crate::gui::widget::{button, icon, tooltip};
@@ -30,13 +25,17 @@ pub enum Action {
 
 #[derive(Debug, Getters)]
 pub struct InstanceHistory {
+	/// Installs found by `emtk_core::discover` that are not in the history
+	discovered: Vec<Candidate>,
 	hover: Option<usize>,
+	/// Entries of the instance history sorted by `instance::sorted_history`
 	#[getset(get = "pub")]
-	inner: Vec<(PathBuf, Option<String>)>,
+	inner: Vec<HistoryEntry>,
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
+	Discovered(Vec<Candidate>),
 	EnteredBtnRegion(Option<usize>),
 	ExitedBtnRegion(Option<usize>),
 	Loaded,
@@ -44,7 +43,8 @@ pub enum Message {
 	NewInstance,
 	OpenDirectory(PathBuf),
 	OpenInstance(PathBuf),
-	Refresh(Vec<(PathBuf, Option<String>)>),
+	PinInstance(usize),
+	Refresh(Vec<HistoryEntry>),
 	RemoveInstance(usize),
 }
 
@@ -54,42 +54,25 @@ impl InstanceHistory {
 		let task = Task::done(Message::Loading)
 			.chain(Task::perform(
 				async {
-					let history = match emtk_core::instance::history().await {
-						Ok(instance_history) => instance_history,
+					match instance::history().await {
+						Ok(instance_history) => instance::sorted_history(instance_history),
 						Err(e) => {
 							warn!("{}", e);
 							Vec::new()
 						}
-					};
-
-					future::join_all(history.into_iter().map(async |path| 'read_toml: {
-						let Ok(file) = fs::File::open(
-							path.join(emtk_core::Instance::DATA_DIR)
-								.join(emtk_core::Instance::TOML),
-						)
-						.await
-						else {
-							break 'read_toml (path, None);
-						};
-						let mut reader = io::BufReader::new(file);
-						let mut buffer = String::new();
-						if reader.read_to_string(&mut buffer).await.is_err() {
-							break 'read_toml (path, None);
-						};
-						let Ok(settings) = toml::from_str::<emtk_core::instance::Settings>(&buffer)
-						else {
-							break 'read_toml (path, None);
-						};
-						break 'read_toml (path, settings.name);
-					}))
-					.await
+					}
 				},
 				Message::Refresh,
 			))
+			.chain(Task::perform(
+				emtk_core::discover::discover(),
+				Message::Discovered,
+			))
 			.chain(Task::done(Message::Loaded));
 
 		(
 			Self {
+				discovered: Vec::new(),
 				hover: None,
 				inner: Vec::new(),
 			},
@@ -100,6 +83,12 @@ impl InstanceHistory {
 	#[instrument(level = "trace")]
 	pub fn update(&mut self, message: Message) -> Action {
 		match message {
+			Message::Discovered(candidates) => {
+				self.discovered = candidates
+					.into_iter()
+					.filter(|candidate| !self.inner.iter().any(|entry| entry.path == candidate.path))
+					.collect();
+			}
 			Message::EnteredBtnRegion(hover) => self.hover = hover,
 			Message::ExitedBtnRegion(hover) => {
 				if self.hover == hover {
@@ -141,20 +130,43 @@ impl InstanceHistory {
 			Message::Refresh(history) => {
 				self.inner = history;
 			}
+			Message::PinInstance(index) => {
+				let Some(entry) = self.inner.get_mut(index) else {
+					return Action::None;
+				};
+				entry.pinned = !entry.pinned;
+				let path = entry.path.clone();
+				let pinned = entry.pinned;
+				// the history file orders entries by when they were opened so only the
+				// displayed entries need sorting again
+				self.inner.sort_by(|a, b| {
+					b.pinned
+						.cmp(&a.pinned)
+						.then(b.last_opened.cmp(&a.last_opened))
+				});
+				return Action::Task(
+					Task::done(Message::Loading)
+						.chain(
+							Task::future(async move {
+								instance::pin_in_history(&path, pinned)
+									.await
+									.map_err(|e| error!("{}", e))
+							})
+							.discard(),
+						)
+						.chain(Task::done(Message::Loaded)),
+				);
+			}
 			Message::RemoveInstance(index) => {
-				dbg!(&index);
-				self.inner.remove(index);
-				let history: Vec<_> = self
-					.inner
-					.clone()
-					.into_iter()
-					.map(|(path, _)| path)
-					.collect();
+				if index >= self.inner.len() {
+					return Action::None;
+				}
+				let path = self.inner.remove(index).path;
 				return Action::Task(
 					Task::done(Message::Loading)
 						.chain(
 							Task::future(async move {
-								write_instance_history(&history)
+								instance::remove_from_history(&path)
 									.await
 									.map_err(|e| error!("{}", e))
 							})
@@ -170,7 +182,7 @@ impl InstanceHistory {
 
 	#[instrument(level = "trace")]
 	pub fn view(&self) -> Element<'_, Message> {
-		let history_len = self.inner.len() as u32;
+		let history_len = (self.inner.len() + self.discovered.len()) as u32;
 		let history_btn_size = 54;
 		let control_icon_size = 18;
 		let control_btn_size = 36;
@@ -194,11 +206,34 @@ impl InstanceHistory {
 						text("Open file dialog"),
 						tooltip::Position::FollowCursor
 					)]
-					.extend(self.inner.iter().enumerate().rev().map(
-						|(i, (path, maybe_name))| {
+					.extend(self.discovered.iter().map(|candidate| {
+						column![
+							horizontal_rule(5),
+							tooltip(
+								button(column![
+									text(format!("Import {} Install", candidate.source))
+										.size(20)
+										.center()
+										.wrapping(text::Wrapping::None),
+									text(candidate.path.display().to_string())
+										.size(14)
+										.center()
+								])
+								.on_press(Message::OpenInstance(candidate.path.clone()))
+								.width(Fill)
+								.height(history_btn_size),
+								text("Import discovered instance"),
+								tooltip::Position::FollowCursor,
+							)
+						]
+						.into()
+					}))
+					.extend(self.inner.iter().enumerate().map(
+						|(i, entry)| {
+							let path = &entry.path;
 							let instance_btn = mouse_area(tooltip(
 								button(column![
-									text(if let Some(name) = maybe_name {
+									text(if let Some(name) = &entry.name {
 										name.clone()
 									} else {
 										path.file_name().unwrap().display().to_string()
@@ -206,7 +241,14 @@ impl InstanceHistory {
 									.size(20)
 									.center()
 									.wrapping(text::Wrapping::None),
-									text(path.display().to_string()).size(14).center().style(
+									text(if entry.missing {
+										format!("{} (missing)", path.display())
+									} else {
+										path.display().to_string()
+									})
+									.size(14)
+									.center()
+									.style(
 										move |theme: &Theme| {
 											let ext_palette = theme.extended_palette();
 											let color = Some(
@@ -254,6 +296,37 @@ impl InstanceHistory {
 									tooltip::Position::Top,
 								);
 
+								let pin_btn = tooltip(
+									button(
+										if entry.pinned {
+											icon::circle_x()
+										} else {
+											icon::check()
+										}
+										.size(control_icon_size)
+										.center(),
+									)
+									.width(control_btn_size)
+									.height(control_btn_size)
+									.on_press(Message::PinInstance(i))
+									.style(|theme, status| {
+										let primary = button::primary(theme, status);
+										match status {
+											button::Status::Active => button::Style {
+												background: Some(theme.palette().background.into()),
+												..primary
+											},
+											_ => primary,
+										}
+									}),
+									text(if entry.pinned {
+										"Unpin from top of history list"
+									} else {
+										"Pin to top of history list"
+									}),
+									tooltip::Position::Top,
+								);
+
 								let remove_btn = tooltip(
 									button(icon::trash().size(control_icon_size).center())
 										.width(control_btn_size)
@@ -275,7 +348,7 @@ impl InstanceHistory {
 									tooltip::Position::Top,
 								);
 
-								let controls = row![open_btn, remove_btn].spacing(8);
+								let controls = row![pin_btn, open_btn, remove_btn].spacing(8);
 
 								// FIX: controls break hover state sync between mouse_area and button::Status::Hovered
 								stack![instance_btn, right_center(controls).padding([0, 8])].into()
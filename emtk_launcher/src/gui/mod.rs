@@ -7,7 +7,7 @@ mod widget;
 
 use std::env;
 
-use emtk_core::{Error, Result, TomlError};
+use emtk_core::{Error, ErrorKind, Result, TomlError};
 use getset::{Getters, WithSetters};
 use iced::widget::pane_grid;
 use iced::{
@@ -233,12 +233,41 @@ pub struct App {
 	root: Root,
 }
 
+/// Returns the error along with how the user can resolve it, which is shown in
+/// the error toast once logged.
+#[instrument(level = "trace")]
+pub(crate) fn describe(e: &Error) -> String {
+	let hint = match e.kind() {
+		ErrorKind::InstanceNotFound { .. } => {
+			"Import the instance again if the game directory was moved."
+		}
+		ErrorKind::ExecutableMissing { .. } => {
+			"Verify the integrity of the game files or import another game directory."
+		}
+		ErrorKind::InstanceLocked { .. } => {
+			"Close the other launcher managing this instance and try again."
+		}
+		ErrorKind::InvalidPluginId { .. } => {
+			"Rename the mod's directory to an ID such as \"com.example.my-mod\"."
+		}
+		ErrorKind::ManifestInvalid { .. } => "Fix or reinstall the mod with the invalid manifest.",
+		ErrorKind::LoadOrderCorrupt { .. } => {
+			"Fix the load order file or delete it to start from a fresh load order."
+		}
+		ErrorKind::DependencyUnmet { .. } => "Install and enable the dependency, then try again.",
+		_ => return e.to_string(),
+	};
+
+	format!("{e}. {hint}")
+}
+
 #[derive(Debug, Clone)]
 enum Message {
 	ClosedToast(usize),
 	Loaded,
 	Log(log::Event),
 	HotkeyPressed(Hotkey),
+	InstanceWatched(instance::Watched),
 	RefreshConfig(Config),
 	Tab(tab::Message),
 }
@@ -295,6 +324,19 @@ impl App {
 					self.toasts.remove(index);
 				}
 			}
+			Message::InstanceWatched(watched) => {
+				// every tab of the instance is updated, not only the focused one
+				for (_, tab_manager) in self.tab_managers.iter_mut() {
+					for tab in &mut tab_manager.tabs {
+						if let Buffer::Instance(instance_buffer) = &mut tab.buffer
+							&& *instance_buffer.inner().path() == watched.instance_path
+						{
+							let _ =
+								instance_buffer.update(instance::Message::Watched(watched.clone()));
+						}
+					}
+				}
+			}
 			Message::Loaded => self.root.loading = false,
 			Message::Log(event) => {
 				self.root.logs.push(event.clone());
@@ -908,7 +950,18 @@ impl App {
 			None
 		});
 
-		Subscription::batch([logs, events])
+		let watches = self
+			.tab_managers
+			.iter()
+			.flat_map(|(_, tab_manager)| &tab_manager.tabs)
+			.filter_map(|tab| match &tab.buffer {
+				Buffer::Instance(instance) => {
+					Some(instance.subscription().map(Message::InstanceWatched))
+				}
+				_ => None,
+			});
+
+		Subscription::batch([logs, events].into_iter().chain(watches))
 	}
 
 	#[instrument(level = "trace")]
@@ -6,13 +6,49 @@ use detours_sys::{
 use emtk_core::{Error, Result};
 use tracing::instrument;
 
+/// Waits forever when passed to `WaitForSingleObject`.
+const INFINITE: DWORD = 0xFFFFFFFF;
+
+/// Returned by `WaitForSingleObject` when waiting failed.
+const WAIT_FAILED: DWORD = 0xFFFFFFFF;
+
+/// A handle to a process created by [`inject`]. The handle is closed when
+/// dropped.
+#[derive(Debug)]
+pub(crate) struct Process {
+	handle: HANDLE,
+}
+
+// SAFETY: a process handle is not tied to the thread that created it
+unsafe impl Send for Process {}
+
+impl Process {
+	/// Blocks the current thread until the process exits.
+	#[instrument(level = "trace")]
+	pub(crate) fn wait(self) -> io::Result<()> {
+		if unsafe { WaitForSingleObject(self.handle, INFINITE) } == WAIT_FAILED {
+			return Err(io::Error::last_os_error());
+		}
+
+		Ok(())
+	}
+}
+
+impl Drop for Process {
+	fn drop(&mut self) {
+		unsafe {
+			CloseHandle(self.handle);
+		}
+	}
+}
+
 /// Inject a DLL into a target process.
 ///
 /// # Safety
 ///
 /// This function is unsafe because it is injecting a DLL into a live process.
 #[instrument(level = "trace")]
-pub(crate) unsafe fn inject(dll_path: &str, target_exe: &str) -> Result<()> {
+pub(crate) unsafe fn inject(dll_path: &str, target_exe: &str) -> Result<Process> {
 	let binding = CString::new(target_exe).map_err(Error::msg(
 		"failed to create new C string for the target executable",
 	))?;
@@ -53,11 +89,12 @@ pub(crate) unsafe fn inject(dll_path: &str, target_exe: &str) -> Result<()> {
 		}
 
 		ResumeThread(process_info.hThread as _);
-		CloseHandle(process_info.hProcess as _);
 		CloseHandle(process_info.hThread as _);
 	}
 
-	Ok(())
+	Ok(Process {
+		handle: process_info.hProcess as _,
+	})
 }
 
 #[link(name = "kernel32")]
@@ -65,4 +102,6 @@ unsafe extern "system" {
 	unsafe fn CloseHandle(hObject: HANDLE) -> BOOL;
 
 	unsafe fn ResumeThread(hThread: HANDLE) -> DWORD;
+
+	unsafe fn WaitForSingleObject(hHandle: HANDLE, dwMilliseconds: DWORD) -> DWORD;
 }
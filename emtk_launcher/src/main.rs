@@ -8,11 +8,12 @@ mod cli;
 mod gui;
 mod injector;
 
-use std::{env, path::Path, sync::OnceLock};
+use std::{env, path::Path, process::ExitCode, sync::OnceLock};
 
+use anyhow::anyhow;
 use clap::Parser;
-use emtk_core::{Error, Result};
-use tracing::instrument;
+use emtk_core::{Error, Instance, Profile, Result, saves};
+use tracing::{instrument, warn};
 use tracing_subscriber::{EnvFilter, filter};
 
 #[cfg(debug_assertions)]
@@ -26,20 +27,28 @@ pub(crate) static TRACING_GUARD: OnceLock<tracing_appender::non_blocking::Worker
 	OnceLock::new();
 
 #[instrument(level = "trace")]
-fn main() {
+fn main() -> ExitCode {
 	#[cfg(debug_assertions)]
 	tracing_tracy::client::Client::start();
 
 	if env::args_os().len() == 1 {
 		let _ = gui::App::run().map_err(|e| eprintln!("failed to run gui: {e}"));
+		ExitCode::SUCCESS
 	} else {
 		let body = async { cli::App::parse().run().await };
-		tokio::runtime::Builder::new_multi_thread()
+		let result = tokio::runtime::Builder::new_multi_thread()
 			.enable_all()
 			.build()
 			.expect("Failed building the Runtime")
 			.block_on(body);
-	};
+		match result {
+			Ok(()) => ExitCode::SUCCESS,
+			Err(e) => {
+				eprintln!("error: {e}");
+				ExitCode::from(cli::exit_code(&e))
+			}
+		}
+	}
 }
 
 /// Helper function to parse a string into tracing's filter [`Directive`] for
@@ -75,7 +84,7 @@ fn env_filter() -> EnvFilter {
 
 /// Executes Exanima as a process inside the given path's directory
 #[instrument(level = "trace")]
-pub fn launch(path: &Path) -> Result<()> {
+pub fn launch(path: &Path) -> Result<injector::Process> {
 	let exanima_exe = path
 		.join(emtk_core::Instance::BINARY)
 		.canonicalize()
@@ -100,11 +109,97 @@ pub fn launch(path: &Path) -> Result<()> {
 		.display()
 		.to_string();
 
+	unsafe { crate::injector::inject(&emtk_framework_dll, &exanima_exe.display().to_string()) }
+}
+
+/// Executes Exanima with the saves of the given profile swapped in, then syncs
+/// the saves back into the profile once the game exits. The framework is told
+/// to load the profile's load order through `EMTK_LOAD_ORDER_PATH`. Unmet dependencies of
+/// the enabled mods are only warned about, the framework skips the mods that
+/// need them.
+#[instrument(level = "trace")]
+pub async fn launch_profile(profile: &Profile) -> Result<()> {
+	if let Err(e) = profile.check_dependencies().await {
+		warn!("{e}");
+	}
+
+	let game_saves_dir = saves::game_saves_dir().ok_or(Error::new(
+		anyhow!("AppData directory could not be found"),
+		"failed to find the game's saves directory",
+	))?;
+
+	if let Some(report) = profile.swap_in_saves(&game_saves_dir).await? {
+		warn_conflicts(&report);
+	}
+
+	// the game inherits the environment, the framework reads the profile's load
+	// order from this variable once injected
 	unsafe {
-		crate::injector::inject(&emtk_framework_dll, &exanima_exe.display().to_string())?;
+		env::set_var(
+			"EMTK_LOAD_ORDER_PATH",
+			profile.path().join(Profile::LOAD_ORDER_TOML),
+		);
 	}
+	let process = profile.game_dir().await.and_then(|path| launch(&path));
+	let waited = match process {
+		Ok(process) => tokio::task::spawn_blocking(move || process.wait())
+			.await
+			.map_err(|e| Error::new(anyhow!(e), "failed to wait for the game to exit"))
+			.and_then(|result| result.map_err(Error::msg("failed to wait for the game to exit"))),
+		Err(e) => Err(e),
+	};
 
-	Ok(())
+	// sync back even if launching failed so saves without a profile are restored
+	match (waited, saves::sync_back(&game_saves_dir).await) {
+		(waited, Ok(report)) => {
+			if let Some(report) = report {
+				warn_conflicts(&report);
+			}
+			waited
+		}
+		(Ok(()), Err(e)) => Err(e),
+		(Err(waited), Err(synced)) => Err(Error::new(
+			anyhow!("{waited}, then {synced}"),
+			"failed to run the game and sync saves back",
+		)),
+	}
+}
+
+/// Warns about enabled mods of the instance's current profile that do not
+/// support the instance's game version. This does not prevent launching.
+#[instrument(level = "trace")]
+pub async fn warn_incompatible_mods(instance: &Instance) {
+	let Some(game_version) = instance.game_version() else {
+		return;
+	};
+	for (id, supported_versions) in instance.incompatible_mods().await {
+		warn!(
+			"mod \"{}\" supports game versions \"{}\" but the game version is \"{}\"",
+			id,
+			supported_versions.join(", "),
+			game_version.name()
+		);
+	}
+}
+
+/// Helper function to log saves that changed in both the game and the profile.
+#[instrument(level = "trace")]
+fn warn_conflicts(report: &saves::SyncReport) {
+	for conflict in &report.conflicts {
+		match &conflict.kept_as {
+			Some(kept_as) => warn!(
+				"save \"{}\" changed in both the game and the profile \"{}\", the game's version was kept as \"{}\"",
+				conflict.name,
+				report.profile.display(),
+				kept_as
+			),
+			None => warn!(
+				"save \"{}\" changed in the profile \"{}\" but was deleted by the game, the profile's version was kept",
+				conflict.name,
+				report.profile.display()
+			),
+		}
+	}
 }
 
 #[cfg(test)]
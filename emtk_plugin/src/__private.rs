@@ -0,0 +1,115 @@
+//! Items used by the code the [`crate::plugin`] attribute generates.
+
+use std::{
+	ffi::CStr,
+	panic::{self, AssertUnwindSafe},
+	sync::{Mutex, MutexGuard, OnceLock, PoisonError},
+};
+
+pub use emtk_framework_types::ffi::PluginDescriptor;
+use emtk_framework_types::ffi::PluginVTable;
+use safer_ffi::prelude::char_p;
+use tracing::error;
+
+use crate::{Plugin, SettingValue};
+
+/// The instance of a plugin, implemented by the [`crate::plugin`] attribute.
+pub trait Instance: Plugin {
+	const ID: &'static CStr;
+
+	fn instance() -> &'static Mutex<Self>;
+}
+
+/// Returns the descriptor of the plugin `P`, initializing `descriptor` the
+/// first time the framework reads it.
+pub fn descriptor<P: Instance>(
+	descriptor: &'static OnceLock<PluginDescriptor>,
+) -> *const PluginDescriptor {
+	descriptor.get_or_init(|| {
+		if let Ok(id) = P::ID.to_str() {
+			let _ = crate::ID.set(id);
+		}
+		let vtable = Box::leak(Box::new(vtable::<P>()));
+		PluginDescriptor::new(P::ID.into(), vtable)
+	})
+}
+
+fn vtable<P: Instance>() -> PluginVTable {
+	PluginVTable {
+		enable: Some(enable::<P>),
+		disable: Some(disable::<P>),
+		on_message: Some(on_message::<P>),
+		setting_changed_bool: Some(setting_changed_bool::<P>),
+		setting_changed_int: Some(setting_changed_int::<P>),
+		setting_changed_float: Some(setting_changed_float::<P>),
+		setting_changed_string: Some(setting_changed_string::<P>),
+		..PluginVTable::EMPTY
+	}
+}
+
+/// Returns the instance of `P`, even if a callback panicked while holding it.
+fn lock<P: Instance>() -> MutexGuard<'static, P> {
+	P::instance().lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Returns the result of `callback`, or `None` if it panicked. Panics are
+/// logged instead of unwinding across the FFI boundary into the framework.
+fn catch_panic<R>(callback: impl FnOnce() -> R) -> Option<R> {
+	panic::catch_unwind(AssertUnwindSafe(callback))
+		.map_err(|payload| {
+			let message = payload
+				.downcast_ref::<&str>()
+				.copied()
+				.or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+				.unwrap_or("unknown panic");
+			error!("plugin panicked: {}", message);
+		})
+		.ok()
+}
+
+extern "C" fn enable<P: Instance>() -> bool {
+	catch_panic(|| match lock::<P>().enable() {
+		Ok(()) => true,
+		Err(e) => {
+			error!("{:?}", e);
+			false
+		}
+	})
+	.unwrap_or(false)
+}
+
+extern "C" fn disable<P: Instance>() -> bool {
+	catch_panic(|| match lock::<P>().disable() {
+		Ok(()) => true,
+		Err(e) => {
+			error!("{:?}", e);
+			false
+		}
+	})
+	.unwrap_or(false)
+}
+
+extern "C" fn on_message<P: Instance>(sender_id: char_p::Box, message: char_p::Box) {
+	catch_panic(|| lock::<P>().on_message(sender_id.to_str(), message.to_str()));
+}
+
+extern "C" fn setting_changed_bool<P: Instance>(setting: char_p::Box, value: bool) {
+	catch_panic(|| lock::<P>().setting_changed(setting.to_str(), SettingValue::Boolean(value)));
+}
+
+extern "C" fn setting_changed_int<P: Instance>(setting: char_p::Box, value: i64) {
+	catch_panic(|| lock::<P>().setting_changed(setting.to_str(), SettingValue::Integer(value)));
+}
+
+extern "C" fn setting_changed_float<P: Instance>(setting: char_p::Box, value: f64) {
+	catch_panic(|| lock::<P>().setting_changed(setting.to_str(), SettingValue::Float(value)));
+}
+
+extern "C" fn setting_changed_string<P: Instance>(setting: char_p::Box, value: char_p::Box) {
+	catch_panic(|| {
+		lock::<P>().setting_changed(
+			setting.to_str(),
+			SettingValue::String(value.to_str().to_string()),
+		)
+	});
+}
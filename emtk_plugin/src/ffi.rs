@@ -0,0 +1,80 @@
+//! The functions the framework exports to plugins.
+
+use std::ffi::c_void;
+
+use emtk_framework_types::ffi::GetSettingReturnValue;
+use safer_ffi::prelude::repr_c;
+
+/// A hook owned by the framework.
+#[repr(C)]
+pub(crate) struct RawHook {
+	_private: [u8; 0],
+}
+
+/// A byte patch owned by the framework.
+#[repr(C)]
+pub(crate) struct RawPatch {
+	_private: [u8; 0],
+}
+
+#[cfg_attr(windows, link(name = "emtk_framework", kind = "raw-dylib"))]
+unsafe extern "C" {
+	pub(crate) fn hook_new(
+		hook_name: repr_c::String,
+		target_fn_ptr: *mut *mut c_void,
+		replacement_fn_ptr: *mut c_void,
+	) -> *mut RawHook;
+	pub(crate) fn hook_from_signature(
+		hook_name: repr_c::String,
+		signature: repr_c::String,
+		replacement_fn_ptr: *mut c_void,
+	) -> *mut RawHook;
+	pub(crate) fn hook_from_signature_name(
+		hook_name: repr_c::String,
+		signature_name: repr_c::String,
+		replacement_fn_ptr: *mut c_void,
+	) -> *mut RawHook;
+	pub(crate) fn hook_offset_pointer(hook: *mut RawHook, offset: isize) -> *mut *mut c_void;
+	pub(crate) fn hook_apply(hook: *mut RawHook) -> bool;
+	pub(crate) fn hook_revert(hook: *mut RawHook) -> bool;
+	pub(crate) fn hook_is_applied(hook: *const RawHook) -> bool;
+	pub(crate) fn hook_free(hook: *mut RawHook);
+
+	pub(crate) fn patch_new(address: u64, data: repr_c::Vec<u8>) -> *mut RawPatch;
+	pub(crate) fn patch_from_signature(
+		signature: repr_c::String,
+		data: repr_c::Vec<u8>,
+	) -> *mut RawPatch;
+	pub(crate) fn patch_from_signature_name(
+		signature_name: repr_c::String,
+		data: repr_c::Vec<u8>,
+	) -> *mut RawPatch;
+	pub(crate) fn patch_offset_pointer(patch: *mut RawPatch, offset: isize) -> *const u8;
+	pub(crate) fn patch_apply(patch: *mut RawPatch) -> bool;
+	pub(crate) fn patch_revert(patch: *mut RawPatch) -> bool;
+	pub(crate) fn patch_is_applied(patch: *const RawPatch) -> bool;
+	pub(crate) fn patch_free(patch: *mut RawPatch);
+
+	pub(crate) fn get_setting_bool(
+		id: repr_c::String,
+		key: repr_c::String,
+	) -> repr_c::Box<GetSettingReturnValue<bool>>;
+	pub(crate) fn get_setting_string(
+		id: repr_c::String,
+		key: repr_c::String,
+	) -> repr_c::Box<GetSettingReturnValue<repr_c::String>>;
+	pub(crate) fn get_setting_integer(
+		id: repr_c::String,
+		key: repr_c::String,
+	) -> repr_c::Box<GetSettingReturnValue<i64>>;
+	pub(crate) fn get_setting_float(
+		id: repr_c::String,
+		key: repr_c::String,
+	) -> repr_c::Box<GetSettingReturnValue<f64>>;
+
+	pub(crate) fn send_message(
+		sender: repr_c::String,
+		target: repr_c::String,
+		message: repr_c::String,
+	) -> bool;
+}
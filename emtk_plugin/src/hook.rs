@@ -0,0 +1,166 @@
+//! Function hooks that are reverted once dropped.
+
+use std::{ffi::c_void, ptr::NonNull};
+
+use tracing::error;
+
+use crate::{Error, ffi};
+
+/// A hook replacing a function of the game, reverted and freed once dropped.
+#[derive(Debug)]
+pub struct Hook {
+	name: String,
+	raw: NonNull<ffi::RawHook>,
+}
+
+// SAFETY: the framework does not tie hooks to the thread that created them.
+unsafe impl Send for Hook {}
+
+impl Hook {
+	/// Returns a hook named `name` replacing the function pointed to by
+	/// `target` with `replacement`. The hook is not applied yet.
+	///
+	/// # Safety
+	///
+	/// `target` must point to a pointer to a function with the same signature
+	/// as `replacement`. Once applied, `target` points to the original
+	/// function.
+	pub unsafe fn new(name: &str, target: *mut *mut c_void, replacement: *mut c_void) -> Self {
+		let raw = unsafe { ffi::hook_new(name.to_string().into(), target, replacement) };
+		Self {
+			name: name.to_string(),
+			raw: NonNull::new(raw).expect("framework should always create a hook"),
+		}
+	}
+
+	/// Returns a result to a hook named `name` replacing the function found
+	/// by `signature` with `replacement`. The hook is not applied yet.
+	///
+	/// # Errors
+	///
+	/// This function will return [`Error::SignatureNotFound`] if `signature` is
+	/// not found in the game.
+	///
+	/// # Safety
+	///
+	/// The function found by `signature` must have the same signature as
+	/// `replacement`.
+	pub unsafe fn from_signature(
+		name: &str,
+		signature: &str,
+		replacement: *mut c_void,
+	) -> Result<Self, Error> {
+		let raw = unsafe {
+			ffi::hook_from_signature(
+				name.to_string().into(),
+				signature.to_string().into(),
+				replacement,
+			)
+		};
+		Self::from_raw(name, raw, signature)
+	}
+
+	/// Returns a result to a hook named `name` replacing the function found by
+	/// the signature `signature_name` of the signature registry with
+	/// `replacement`. The hook is not applied yet.
+	///
+	/// # Errors
+	///
+	/// This function will return [`Error::SignatureNotFound`] if the signature
+	/// could not be resolved for the game version.
+	///
+	/// # Safety
+	///
+	/// The function found by the signature must have the same signature as
+	/// `replacement`.
+	pub unsafe fn from_signature_name(
+		name: &str,
+		signature_name: &str,
+		replacement: *mut c_void,
+	) -> Result<Self, Error> {
+		let raw = unsafe {
+			ffi::hook_from_signature_name(
+				name.to_string().into(),
+				signature_name.to_string().into(),
+				replacement,
+			)
+		};
+		Self::from_raw(name, raw, signature_name)
+	}
+
+	fn from_raw(name: &str, raw: *mut ffi::RawHook, signature: &str) -> Result<Self, Error> {
+		let raw =
+			NonNull::new(raw).ok_or_else(|| Error::SignatureNotFound(signature.to_string()))?;
+		Ok(Self {
+			name: name.to_string(),
+			raw,
+		})
+	}
+
+	/// Offsets the target of the hook by `offset` bytes, returning the new
+	/// target.
+	///
+	/// # Safety
+	///
+	/// The hook must not be applied, and the new target must be valid.
+	pub unsafe fn offset(&mut self, offset: isize) -> *mut *mut c_void {
+		unsafe { ffi::hook_offset_pointer(self.raw.as_ptr(), offset) }
+	}
+
+	/// Returns a result from applying the hook. Applying an applied hook does
+	/// nothing.
+	///
+	/// # Errors
+	///
+	/// This function will return [`Error::Apply`] if the framework failed to
+	/// attach the hook.
+	///
+	/// # Safety
+	///
+	/// The target and replacement of the hook must be valid.
+	pub unsafe fn apply(&mut self) -> Result<(), Error> {
+		if unsafe { ffi::hook_apply(self.raw.as_ptr()) } {
+			Ok(())
+		} else {
+			Err(Error::Apply(self.name.clone()))
+		}
+	}
+
+	/// Returns a result from reverting the hook. Reverting a hook that is not
+	/// applied does nothing.
+	///
+	/// # Errors
+	///
+	/// This function will return [`Error::Revert`] if the framework failed to
+	/// detach the hook.
+	///
+	/// # Safety
+	///
+	/// The target and replacement of the hook must be valid.
+	pub unsafe fn revert(&mut self) -> Result<(), Error> {
+		if unsafe { ffi::hook_revert(self.raw.as_ptr()) } {
+			Ok(())
+		} else {
+			Err(Error::Revert(self.name.clone()))
+		}
+	}
+
+	pub fn is_applied(&self) -> bool {
+		unsafe { ffi::hook_is_applied(self.raw.as_ptr()) }
+	}
+
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+}
+
+impl Drop for Hook {
+	fn drop(&mut self) {
+		unsafe {
+			if let Err(e) = self.revert() {
+				error!("{}", e);
+			}
+			ffi::hook_free(self.raw.as_ptr());
+		}
+	}
+}
@@ -0,0 +1,96 @@
+//! SDK for writing plugins of the Exanima Modding Toolkit in Rust.
+//!
+//! A plugin is a type implementing [`Plugin`] and `Default`, marked with the
+//! [`plugin`] attribute which exports it to the framework.
+//!
+//! ```ignore
+//! use emtk_plugin::{Hook, Plugin, plugin};
+//!
+//! #[plugin(id = "com.yourusername.pluginname")]
+//! #[derive(Default)]
+//! struct MyPlugin {
+//! 	hook: Option<Hook>,
+//! }
+//!
+//! impl Plugin for MyPlugin {
+//! 	fn enable(&mut self) -> anyhow::Result<()> {
+//! 		let mut hook = unsafe {
+//! 			Hook::from_signature_name("my_hook", "game::update", my_update as _)?
+//! 		};
+//! 		unsafe { hook.apply()? };
+//! 		self.hook = Some(hook);
+//! 		Ok(())
+//! 	}
+//!
+//! 	fn disable(&mut self) -> anyhow::Result<()> {
+//! 		// the hook is reverted once dropped
+//! 		self.hook = None;
+//! 		Ok(())
+//! 	}
+//! }
+//! ```
+
+mod ffi;
+pub mod hook;
+pub mod patch;
+pub mod settings;
+
+#[doc(hidden)]
+pub mod __private;
+
+use std::sync::OnceLock;
+
+pub use emtk_plugin_macros::plugin;
+pub use hook::Hook;
+pub use patch::Patch;
+pub use settings::{Setting, SettingValue};
+
+/// The id of the plugin, set once the framework reads its descriptor.
+static ID: OnceLock<&'static str> = OnceLock::new();
+
+/// A plugin of the framework, exported with the [`plugin`] attribute.
+pub trait Plugin: Default + Send + 'static {
+	/// Starts running the plugin, such as by applying its hooks and patches.
+	fn enable(&mut self) -> anyhow::Result<()>;
+
+	/// Stops running the plugin, reverting the changes it made to the game.
+	fn disable(&mut self) -> anyhow::Result<()>;
+
+	/// Called when the plugin `sender` sends `message` to this plugin.
+	fn on_message(&mut self, _sender: &str, _message: &str) {}
+
+	/// Called when the setting `key` of the plugin was changed to `value`.
+	fn setting_changed(&mut self, _key: &str, _value: SettingValue) {}
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+	#[error("signature of \"{0}\" not found")]
+	SignatureNotFound(String),
+	#[error("failed to apply \"{0}\"")]
+	Apply(String),
+	#[error("failed to revert \"{0}\"")]
+	Revert(String),
+}
+
+/// Returns the id of the plugin, or an empty string before the framework read
+/// its descriptor.
+pub fn id() -> &'static str {
+	ID.get().copied().unwrap_or_default()
+}
+
+/// Sends `message` to the plugin `target`, returning false if `target` is not
+/// a loaded plugin or has failed.
+///
+/// The message is delivered before this function returns. Sending a message to
+/// the plugin itself from one of its [`Plugin`] callbacks deadlocks, as the
+/// plugin stays locked for the duration of the callback.
+pub fn send_message(target: &str, message: &str) -> bool {
+	unsafe {
+		ffi::send_message(
+			id().to_string().into(),
+			target.to_string().into(),
+			message.to_string().into(),
+		)
+	}
+}
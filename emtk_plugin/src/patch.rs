@@ -0,0 +1,140 @@
+//! Byte patches that are reverted once dropped.
+
+use std::ptr::NonNull;
+
+use tracing::error;
+
+use crate::{Error, ffi};
+
+/// A patch of the bytes of the game, reverted and freed once dropped.
+#[derive(Debug)]
+pub struct Patch {
+	name: String,
+	raw: NonNull<ffi::RawPatch>,
+}
+
+// SAFETY: the framework does not tie patches to the thread that created them.
+unsafe impl Send for Patch {}
+
+impl Patch {
+	/// Returns a patch writing `data` at `address`. The patch is not applied
+	/// yet.
+	///
+	/// # Safety
+	///
+	/// `address` must point to at least as many bytes of the game as `data`.
+	pub unsafe fn new(address: u64, data: Vec<u8>) -> Self {
+		let raw = unsafe { ffi::patch_new(address, data.into()) };
+		Self {
+			name: format!("{address:#X}"),
+			raw: NonNull::new(raw).expect("framework should always create a patch"),
+		}
+	}
+
+	/// Returns a result to a patch writing `data` where `signature` is found.
+	/// The patch is not applied yet.
+	///
+	/// # Errors
+	///
+	/// This function will return [`Error::SignatureNotFound`] if `signature` is
+	/// not found in the game.
+	///
+	/// # Safety
+	///
+	/// The bytes found by `signature` must be safe to overwrite with `data`.
+	pub unsafe fn from_signature(signature: &str, data: Vec<u8>) -> Result<Self, Error> {
+		let raw = unsafe { ffi::patch_from_signature(signature.to_string().into(), data.into()) };
+		Self::from_raw(raw, signature)
+	}
+
+	/// Returns a result to a patch writing `data` where the signature
+	/// `signature_name` of the signature registry is found. The patch is not
+	/// applied yet.
+	///
+	/// # Errors
+	///
+	/// This function will return [`Error::SignatureNotFound`] if the signature
+	/// could not be resolved for the game version.
+	///
+	/// # Safety
+	///
+	/// The bytes found by the signature must be safe to overwrite with `data`.
+	pub unsafe fn from_signature_name(signature_name: &str, data: Vec<u8>) -> Result<Self, Error> {
+		let raw = unsafe {
+			ffi::patch_from_signature_name(signature_name.to_string().into(), data.into())
+		};
+		Self::from_raw(raw, signature_name)
+	}
+
+	fn from_raw(raw: *mut ffi::RawPatch, signature: &str) -> Result<Self, Error> {
+		let raw =
+			NonNull::new(raw).ok_or_else(|| Error::SignatureNotFound(signature.to_string()))?;
+		Ok(Self {
+			name: signature.to_string(),
+			raw,
+		})
+	}
+
+	/// Offsets the address of the patch by `offset` bytes, returning the new
+	/// address.
+	///
+	/// # Safety
+	///
+	/// The patch must not be applied, and the new address must be valid.
+	pub unsafe fn offset(&mut self, offset: isize) -> *const u8 {
+		unsafe { ffi::patch_offset_pointer(self.raw.as_ptr(), offset) }
+	}
+
+	/// Returns a result from applying the patch. Applying an applied patch does
+	/// nothing.
+	///
+	/// # Errors
+	///
+	/// This function will return [`Error::Apply`] if the framework failed to
+	/// write the patch.
+	///
+	/// # Safety
+	///
+	/// The bytes of the patch must be safe to overwrite.
+	pub unsafe fn apply(&mut self) -> Result<(), Error> {
+		if unsafe { ffi::patch_apply(self.raw.as_ptr()) } {
+			Ok(())
+		} else {
+			Err(Error::Apply(self.name.clone()))
+		}
+	}
+
+	/// Returns a result from restoring the original bytes of the patch.
+	/// Reverting a patch that is not applied does nothing.
+	///
+	/// # Errors
+	///
+	/// This function will return [`Error::Revert`] if the framework failed to
+	/// restore the original bytes.
+	///
+	/// # Safety
+	///
+	/// The bytes of the patch must be safe to overwrite.
+	pub unsafe fn revert(&mut self) -> Result<(), Error> {
+		if unsafe { ffi::patch_revert(self.raw.as_ptr()) } {
+			Ok(())
+		} else {
+			Err(Error::Revert(self.name.clone()))
+		}
+	}
+
+	pub fn is_applied(&self) -> bool {
+		unsafe { ffi::patch_is_applied(self.raw.as_ptr()) }
+	}
+}
+
+impl Drop for Patch {
+	fn drop(&mut self) {
+		unsafe {
+			if let Err(e) = self.revert() {
+				error!("{}", e);
+			}
+			ffi::patch_free(self.raw.as_ptr());
+		}
+	}
+}
@@ -0,0 +1,48 @@
+//! Typed access to the settings of the plugin's config.
+
+use crate::ffi;
+
+pub use emtk_framework_types::config::PluginConfigSettingValue as SettingValue;
+
+/// A type a setting can be read as.
+pub trait Setting: Sized + private::Sealed {
+	/// Returns the value of the setting `key` of the plugin `id`, or `None` if
+	/// the setting does not exist or is of another type.
+	fn get(id: &str, key: &str) -> Option<Self>;
+}
+
+mod private {
+	pub trait Sealed {}
+
+	impl Sealed for bool {}
+	impl Sealed for i64 {}
+	impl Sealed for f64 {}
+	impl Sealed for String {}
+}
+
+/// Returns the value of the setting `key` of the plugin, or `None` if the
+/// setting does not exist or is of another type.
+///
+/// ```ignore
+/// let god_mode: bool = emtk_plugin::settings::get("god_mode").unwrap_or_default();
+/// ```
+pub fn get<T: Setting>(key: &str) -> Option<T> {
+	T::get(crate::id(), key)
+}
+
+macro_rules! impl_setting {
+	($type:ty, $get:ident, $value:ident => $convert:expr) => {
+		impl Setting for $type {
+			fn get(id: &str, key: &str) -> Option<Self> {
+				let setting = unsafe { ffi::$get(id.to_string().into(), key.to_string().into()) };
+				let $value = &setting.value;
+				setting.found.then(|| $convert)
+			}
+		}
+	};
+}
+
+impl_setting!(bool, get_setting_bool, value => *value);
+impl_setting!(i64, get_setting_integer, value => *value);
+impl_setting!(f64, get_setting_float, value => *value);
+impl_setting!(String, get_setting_string, value => value.to_string());
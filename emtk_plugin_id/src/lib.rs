@@ -0,0 +1,44 @@
+//! The grammar of plugin ids, shared by `emtk_core` and the `emtk_plugin`
+//! macros without depending on either.
+
+/// Returns true if `id` is a valid plugin id.
+///
+/// The following conditions will return false:
+///
+/// - Is empty
+/// - Starts or ends with '-' or '.'
+/// - Not alphanumeric (exceptions: '-', '.')
+///
+/// # Examples
+///
+/// ```
+/// assert!(emtk_plugin_id::is_valid("com.example.my-mod"));
+/// assert!(!emtk_plugin_id::is_valid("com.example.my_mod"));
+/// ```
+pub fn is_valid(id: &str) -> bool {
+	!id.is_empty()
+		&& !id.starts_with(['-', '.'])
+		&& !id.ends_with(['-', '.'])
+		&& id
+			.chars()
+			.all(|chr| chr.is_alphanumeric() || chr == '-' || chr == '.')
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn valid_ids() {
+		for id in ["com.example.my-mod", "MyMod", "mod2"] {
+			assert!(is_valid(id), "{id}");
+		}
+	}
+
+	#[test]
+	fn invalid_ids() {
+		for id in ["", "-mod", ".mod", "mod-", "mod.", "my mod", "my_mod"] {
+			assert!(!is_valid(id), "{id}");
+		}
+	}
+}
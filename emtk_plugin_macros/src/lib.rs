@@ -0,0 +1,23 @@
+//! Procedural macros of the `emtk_plugin` SDK, which re-exports them.
+
+mod plugin;
+
+use proc_macro::TokenStream;
+
+/// Generates the entry points of a plugin from a type implementing
+/// `emtk_plugin::Plugin` and `Default`.
+///
+/// ```ignore
+/// #[emtk_plugin::plugin(id = "com.yourusername.pluginname")]
+/// #[derive(Default)]
+/// struct MyPlugin;
+/// ```
+///
+/// The plugin is created with `Default` the first time the framework calls
+/// into it, and exported through the `emtk_plugin_descriptor` function.
+#[proc_macro_attribute]
+pub fn plugin(attr: TokenStream, item: TokenStream) -> TokenStream {
+	plugin::expand(attr.into(), item.into())
+		.unwrap_or_else(syn::Error::into_compile_error)
+		.into()
+}
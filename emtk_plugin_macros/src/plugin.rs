@@ -0,0 +1,170 @@
+use std::ffi::CString;
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::{DeriveInput, LitCStr, LitStr, meta::ParseNestedMeta, parse::Parser};
+
+/// The arguments of the `plugin` attribute.
+#[derive(Default)]
+struct Args {
+	id: Option<LitStr>,
+}
+
+impl Args {
+	fn parse(&mut self, meta: ParseNestedMeta) -> syn::Result<()> {
+		if meta.path.is_ident("id") {
+			self.id = Some(meta.value()?.parse()?);
+			Ok(())
+		} else {
+			Err(meta.error("unsupported plugin argument, expected `id`"))
+		}
+	}
+}
+
+/// Returns a result to the `item` of the `plugin` attribute along with the
+/// entry points of the plugin.
+///
+/// # Errors
+///
+/// This function will return an error if:
+///
+/// - `attr` is not `id = "..."`
+/// - the id is not a valid plugin id
+/// - `item` is not a type or is generic
+pub(crate) fn expand(attr: TokenStream, item: TokenStream) -> syn::Result<TokenStream> {
+	let mut args = Args::default();
+	syn::meta::parser(|meta| args.parse(meta)).parse2(attr)?;
+	let Some(id) = args.id else {
+		return Err(syn::Error::new(
+			Span::call_site(),
+			"missing plugin id, expected `#[plugin(id = \"...\")]`",
+		));
+	};
+	if !emtk_plugin_id::is_valid(&id.value()) {
+		return Err(syn::Error::new(
+			id.span(),
+			"invalid plugin id, expected alphanumeric characters separated by '-' or '.'",
+		));
+	}
+	// valid ids never contain nul bytes
+	let id = LitCStr::new(
+		&CString::new(id.value()).expect("plugin id should not contain nul bytes"),
+		id.span(),
+	);
+
+	let input: DeriveInput = syn::parse2(item.clone())?;
+	if !input.generics.params.is_empty() {
+		return Err(syn::Error::new_spanned(
+			&input.generics,
+			"plugins cannot be generic",
+		));
+	}
+	let ident = &input.ident;
+
+	Ok(quote! {
+		#item
+
+		impl ::emtk_plugin::__private::Instance for #ident {
+			const ID: &'static ::std::ffi::CStr = #id;
+
+			fn instance() -> &'static ::std::sync::Mutex<Self> {
+				static INSTANCE: ::std::sync::LazyLock<::std::sync::Mutex<#ident>> =
+					::std::sync::LazyLock::new(::std::default::Default::default);
+				&INSTANCE
+			}
+		}
+
+		#[unsafe(no_mangle)]
+		pub extern "C" fn emtk_plugin_descriptor()
+		-> *const ::emtk_plugin::__private::PluginDescriptor {
+			static DESCRIPTOR: ::std::sync::OnceLock<::emtk_plugin::__private::PluginDescriptor> =
+				::std::sync::OnceLock::new();
+			::emtk_plugin::__private::descriptor::<#ident>(&DESCRIPTOR)
+		}
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use pretty_assertions::assert_eq;
+
+	use super::*;
+
+	fn expand_err(attr: TokenStream, item: TokenStream) -> String {
+		match expand(attr, item) {
+			Ok(tokens) => panic!("expected an error, expanded to {tokens}"),
+			Err(e) => e.to_string(),
+		}
+	}
+
+	#[test]
+	fn expand_plugin() {
+		let item = quote! {
+			#[derive(Default)]
+			struct MyPlugin {
+				hook: Option<Hook>,
+			}
+		};
+		let tokens = expand(quote!(id = "com.example.plugin"), item.clone()).unwrap();
+
+		let expected = quote! {
+			#item
+
+			impl ::emtk_plugin::__private::Instance for MyPlugin {
+				const ID: &'static ::std::ffi::CStr = c"com.example.plugin";
+
+				fn instance() -> &'static ::std::sync::Mutex<Self> {
+					static INSTANCE: ::std::sync::LazyLock<::std::sync::Mutex<MyPlugin>> =
+						::std::sync::LazyLock::new(::std::default::Default::default);
+					&INSTANCE
+				}
+			}
+
+			#[unsafe(no_mangle)]
+			pub extern "C" fn emtk_plugin_descriptor()
+			-> *const ::emtk_plugin::__private::PluginDescriptor {
+				static DESCRIPTOR: ::std::sync::OnceLock<::emtk_plugin::__private::PluginDescriptor> =
+					::std::sync::OnceLock::new();
+				::emtk_plugin::__private::descriptor::<MyPlugin>(&DESCRIPTOR)
+			}
+		};
+		assert_eq!(tokens.to_string(), expected.to_string());
+	}
+
+	#[test]
+	fn expand_plugin_errors() {
+		let item = quote!(
+			struct MyPlugin;
+		);
+		assert_eq!(
+			expand_err(quote!(), item.clone()),
+			"missing plugin id, expected `#[plugin(id = \"...\")]`"
+		);
+		assert_eq!(
+			expand_err(quote!(name = "My Plugin"), item.clone()),
+			"unsupported plugin argument, expected `id`"
+		);
+		assert_eq!(
+			expand_err(quote!(id = ".com.example"), item.clone()),
+			"invalid plugin id, expected alphanumeric characters separated by '-' or '.'"
+		);
+		assert_eq!(
+			expand_err(
+				quote!(id = "com.example.plugin"),
+				quote!(
+					struct MyPlugin<T>(T);
+				)
+			),
+			"plugins cannot be generic"
+		);
+		assert_eq!(
+			expand_err(
+				quote!(id = "com.example.plugin"),
+				quote!(
+					fn enable() {}
+				)
+			),
+			"expected one of: `struct`, `enum`, `union`"
+		);
+	}
+}